@@ -0,0 +1,178 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::core::variables::Variables;
+
+// Variables holding expressions are evaluated in turn, up to this depth
+const MAX_DEPTH: usize = 32;
+
+// Integer expressions as used by `declare -i` and array subscripts:
+// numbers (decimal, 0x hex, 0 octal), variable names, parentheses,
+// unary + - ! and the binary * / % + - < <= > >= == != && || operators.
+pub fn eval_arithmetic(expr: &str, variables: &Variables) -> Result<i64, String> {
+    eval_with_depth(expr, variables, 0)
+}
+
+fn eval_with_depth(expr: &str, variables: &Variables, depth: usize) -> Result<i64, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("{}: expression recursion level exceeded", expr.trim()));
+    }
+    let mut parser = Parser {
+        chars: expr.chars().peekable(),
+        variables,
+        depth
+    };
+    if parser.at_end() {
+        return Ok(0);
+    }
+    let value = parser.parse_binary(0)?;
+    if !parser.at_end() {
+        let rest: String = parser.chars.collect();
+        return Err(format!("{}: syntax error in expression (error token is \"{}\")", expr.trim(), rest.trim()));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    variables: &'a Variables,
+    depth: usize
+}
+
+// Binary operators from lowest to highest precedence
+const OPERATORS: [&[&str]; 6] = [
+    &["||"],
+    &["&&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["+", "-"],
+    &["*", "/", "%"]
+];
+
+impl<'a> Parser<'a> {
+    fn skip_spaces(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_spaces();
+        self.chars.peek().is_none()
+    }
+
+    // Consumes one of the operators if the input continues with it
+    fn take_operator(&mut self, operators: &[&str]) -> Option<String> {
+        self.skip_spaces();
+        let rest: String = self.chars.clone().take(2).collect();
+        let operator = operators.iter().find(|operator| rest.starts_with(**operator))?;
+        // `<` must not match the start of `<=`, nor `+` the start of `++`
+        if operator.len() == 1 && rest.len() == 2 && (rest.ends_with('=') || rest.ends_with(*operator)) {
+            return None;
+        }
+        for _ in 0..operator.len() {
+            self.chars.next();
+        }
+        Some(operator.to_string())
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<i64, String> {
+        if level == OPERATORS.len() {
+            return self.parse_unary();
+        }
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some(operator) = self.take_operator(OPERATORS[level]) {
+            let right = self.parse_binary(level + 1)?;
+            left = match operator.as_str() {
+                "||" => (left != 0 || right != 0) as i64,
+                "&&" => (left != 0 && right != 0) as i64,
+                "==" => (left == right) as i64,
+                "!=" => (left != right) as i64,
+                "<=" => (left <= right) as i64,
+                ">=" => (left >= right) as i64,
+                "<" => (left < right) as i64,
+                ">" => (left > right) as i64,
+                "+" => left.wrapping_add(right),
+                "-" => left.wrapping_sub(right),
+                "*" => left.wrapping_mul(right),
+                _ => {
+                    if right == 0 {
+                        return Err("division by 0".to_string());
+                    }
+                    if operator == "/" { left.wrapping_div(right) } else { left.wrapping_rem(right) }
+                }
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<i64, String> {
+        self.skip_spaces();
+        match self.chars.peek() {
+            Some('-') => {
+                self.chars.next();
+                Ok(self.parse_unary()?.wrapping_neg())
+            },
+            Some('+') => {
+                self.chars.next();
+                self.parse_unary()
+            },
+            Some('!') => {
+                self.chars.next();
+                Ok((self.parse_unary()? == 0) as i64)
+            },
+            _ => self.parse_operand()
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<i64, String> {
+        self.skip_spaces();
+        match self.chars.peek().copied() {
+            Some('(') => {
+                self.chars.next();
+                let value = self.parse_binary(0)?;
+                self.skip_spaces();
+                if self.chars.next() != Some(')') {
+                    return Err("missing `)'".to_string());
+                }
+                Ok(value)
+            },
+            Some(c) if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = self.chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    number.push(c);
+                    self.chars.next();
+                }
+                parse_number(&number)
+            },
+            Some(c) if c == '$' || c.is_alphabetic() || c == '_' => {
+                if c == '$' {
+                    self.chars.next();
+                }
+                let mut name = String::new();
+                while let Some(&c) = self.chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    name.push(c);
+                    self.chars.next();
+                }
+                // Unset or empty variables are 0, others are evaluated as expressions
+                match self.variables.get(&name) {
+                    Some(value) => eval_with_depth(&value, self.variables, self.depth + 1),
+                    None => Ok(0)
+                }
+            },
+            Some(c) => Err(format!("syntax error: operand expected (error token is \"{}\")", c)),
+            None => Err("syntax error: operand expected".to_string())
+        }
+    }
+}
+
+fn parse_number(number: &str) -> Result<i64, String> {
+    let result = if let Some(hex) = number.strip_prefix("0x").or_else(|| number.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16)
+    } else if number.len() > 1 && number.starts_with('0') {
+        i64::from_str_radix(&number[1..], 8)
+    } else {
+        number.parse::<i64>()
+    };
+    result.map_err(|_| format!("{}: value too great for base", number))
+}
@@ -1,10 +1,16 @@
 use std::process::Output;
+use std::time::Duration;
+
+use crate::core::jobs::exit_code;
 
 #[derive(Clone)]
 pub struct CmdOutput {
     pub status: Option<i32>,
     pub stdout: Vec<u8>,
-    pub stderr: Vec<u8>
+    pub stderr: Vec<u8>,
+    // CPU time used by the programs that produced the output, reported by `time`
+    pub user_time: Duration,
+    pub system_time: Duration
 }
 
 impl CmdOutput {
@@ -12,18 +18,19 @@ impl CmdOutput {
         return CmdOutput{
             status: None,
             stdout: Vec::new(),
-            stderr: Vec::new()
+            stderr: Vec::new(),
+            user_time: Duration::ZERO,
+            system_time: Duration::ZERO
         }
     }
     pub fn from_output(out: &Output) -> CmdOutput {
-        let mut code = 0;
-        if let Some(exitstatus) = out.status.code() {
-            code = exitstatus;
-        }
         return CmdOutput{
-            status: Some(code),
+            // A program killed by a signal reports 128 plus the signal number
+            status: Some(exit_code(&out.status)),
             stdout: out.stdout.clone(),
-            stderr: out.stderr.clone()
+            stderr: out.stderr.clone(),
+            user_time: Duration::ZERO,
+            system_time: Duration::ZERO
         };
     }
 
@@ -31,7 +38,9 @@ impl CmdOutput {
         return CmdOutput{
             status: Some(exitcode),
             stdout: Vec::new(),
-            stderr: Vec::new()
+            stderr: Vec::new(),
+            user_time: Duration::ZERO,
+            system_time: Duration::ZERO
         }
     }
 }
\ No newline at end of file
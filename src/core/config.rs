@@ -1,11 +1,19 @@
 use std::env;
 use std::fs;
+use std::io;
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
 
+use crate::core::error::ShellError;
+use crate::core::fsio::FSError;
+
 #[derive(Deserialize)]
 pub struct ShellConfig {
     pub prompt: PromptConfig,
+    #[serde(default)]
+    pub editor: EditorConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
 }
 
 #[derive(Deserialize)]
@@ -13,18 +21,90 @@ pub struct PromptConfig {
     pub ps1: String,
 }
 
-pub fn load() -> ShellConfig {
-    if let Some(config_path) = get_path() {
-        if let Ok(data) = fs::read_to_string(config_path) {
-            return toml::from_str(&data).unwrap();
-        } else {
-            return default();
+// `[editor]` section, `mode = "vi"` starts the line editor in vi mode.
+// `prefix_history = false` makes Up and Down go through every entry, not only those
+// starting with the text before the cursor.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct EditorConfig {
+    pub mode: EditMode,
+    pub prefix_history: bool,
+    // Shows pasted text in reverse video until the next edit
+    pub highlight_paste: bool,
+    // Removes the `$ ` starting pasted lines, as copied from documentation
+    pub strip_paste_prompts: bool,
+}
+
+impl Default for EditorConfig {
+    fn default() -> EditorConfig {
+        EditorConfig {
+            mode: EditMode::default(),
+            prefix_history: true,
+            highlight_paste: true,
+            strip_paste_prompts: false
         }
-    } else {
-        return default();
     }
 }
 
+// `[theme]` section, colors of the input line by the role of each word. Colors are names
+// such as "green" or "dark_grey", "reset" leaves a role uncolored.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub highlight: bool,
+    pub command: String,
+    pub unknown_command: String,
+    pub keyword: String,
+    pub string: String,
+    pub variable: String,
+    pub operator: String,
+    pub redirection: String,
+    pub comment: String,
+    // Arguments naming a file that exists
+    pub underline_paths: bool,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        ThemeConfig {
+            highlight: true,
+            command: "green".to_string(),
+            unknown_command: "red".to_string(),
+            keyword: "blue".to_string(),
+            string: "yellow".to_string(),
+            variable: "cyan".to_string(),
+            operator: "magenta".to_string(),
+            redirection: "magenta".to_string(),
+            comment: "dark_grey".to_string(),
+            underline_paths: true
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditMode {
+    #[default]
+    Emacs,
+    Vi
+}
+
+// A missing file means the defaults, but one that cannot be read or parsed is reported
+pub fn load() -> Result<ShellConfig, ShellError> {
+    let Some(config_path) = get_path() else {
+        return Ok(default());
+    };
+    let path = config_path.to_string_lossy().to_string();
+    let data = match fs::read_to_string(&config_path) {
+        Ok(data) => data,
+        Err(error) => match FSError::new(&path, &error) {
+            FSError::IOError { kind: io::ErrorKind::NotFound, .. } => return Ok(default()),
+            error => return Err(ShellError::FileSystem(error))
+        }
+    };
+    toml::from_str(&data).map_err(|error| ShellError::Config(path, error.to_string()))
+}
+
 fn get_path() -> Option<PathBuf> {
     if let Ok(home) = env::var("HOME") {
         let configdir = Path::new(&home).join(".lambdash").join("Config.toml");
@@ -33,11 +113,13 @@ fn get_path() -> Option<PathBuf> {
     return None
 }
 
-fn default() -> ShellConfig {
+pub fn default() -> ShellConfig {
     return ShellConfig{
         prompt: PromptConfig{
             ps1: "[color=yellow]λsh[/color] $PWD [color=red]($?)[/color] >".to_string(),
-        }
+        },
+        editor: EditorConfig::default(),
+        theme: ThemeConfig::default()
     };
 }
 
@@ -1,40 +1,159 @@
 use std::env;
-use std::io::Write;
+use std::io::{self, IsTerminal, Write};
 use std::collections::HashMap;
-use std::process::Child;
+use std::process;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use crossterm::terminal;
 
-use crate::core::config::{ShellConfig, load};
+use crate::core::cmdoutput::CmdOutput;
+use crate::core::config::{default, load, EditMode, ShellConfig};
+use crate::core::jobs::JobTable;
+use crate::core::pathcache::PathCache;
+use crate::core::variables::{Assignment, Variables};
+use crate::eval::builtins::BuiltinRegistry;
 use crate::features::history::History;
+use crate::features::search::HistorySearch;
+use crate::features::vi::ViState;
 
 pub struct ShellState<'a> {
     pub status: i32,
     pub ps1pos: (u16, u16),
     pub termsize: (u16, u16),
-    pub jobs: Vec<Child>,
+    pub jobs: JobTable,
     pub history: History,
     pub aliases: HashMap<String, String>,
+    // Function names and the source of their body
+    pub functions: HashMap<String, String>,
+    // Output of the functions being run, collected like a builtin's
+    pub captures: Vec<CmdOutput>,
+    // Elements of the `name=(...)` arguments of the command being run, by index in its arguments
+    pub array_arguments: HashMap<usize, Vec<String>>,
+    // Functions and sourced files being run, which `return` leaves
+    pub call_depth: usize,
+    pub in_not_found_handler: bool,
+    pub traps: HashMap<String, String>,
+    pub in_trap: bool,
+    pub dirstack: Vec<String>,
+    pub variables: Variables,
+    // $0 followed by the positional parameters $1, $2...
+    pub positional: Vec<String>,
+    // Last argument of the previous command, for $_
+    pub last_argument: String,
+    // Line of the script being run, or count of lines entered interactively, for $LINENO
+    pub lineno: usize,
+    // Whether the shell was started on a terminal, shown by $-
+    pub interactive: bool,
+    // $SECONDS counts on from `seconds_offset` at `seconds_start`
+    seconds_start: Instant,
+    seconds_offset: i64,
+    random_state: u32,
+    pub path_cache: PathCache,
+    pub builtins: BuiltinRegistry,
     pub config: ShellConfig,
+    // Keymap of the line editor, set with `set -o`
+    pub edit_mode: EditMode,
+    pub vi: ViState,
+    // Ctrl-R history search in progress
+    pub search: Option<HistorySearch>,
     pub stdout: &'a mut dyn Write,
     pub stderr: &'a mut dyn Write
 }
 
 impl<'a> ShellState<'a> {
     pub fn new(out: &'a mut dyn Write, err: &'a mut dyn Write) -> ShellState<'a> {
+        // A broken configuration should not keep the shell from starting
+        let config = match load() {
+            Ok(config) => config,
+            Err(error) => {
+                let _ = err.write_all(&error.to_output(""));
+                let _ = err.write_all(b"\n");
+                default()
+            }
+        };
         ShellState {
             status: 0,
             ps1pos: (0,0),
-            termsize: terminal::size().expect("unable to obtain terminal size."),
-            jobs: Vec::new(),
+            // Not a terminal, e.g. when the output is piped
+            termsize: terminal::size().unwrap_or((80, 24)),
+            jobs: JobTable::new(),
             history: History::load(),
             aliases: HashMap::new(),
-            config: load(),
+            functions: HashMap::new(),
+            captures: Vec::new(),
+            array_arguments: HashMap::new(),
+            call_depth: 0,
+            in_not_found_handler: false,
+            traps: HashMap::new(),
+            in_trap: false,
+            dirstack: Vec::new(),
+            variables: Variables::new(),
+            positional: env::args().take(1).collect(),
+            last_argument: String::new(),
+            lineno: 0,
+            interactive: io::stdin().is_terminal(),
+            seconds_start: Instant::now(),
+            seconds_offset: 0,
+            random_state: SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0) ^ process::id() | 1,
+            path_cache: PathCache::new(),
+            builtins: BuiltinRegistry::new(),
+            edit_mode: config.editor.mode,
+            vi: ViState::new(),
+            search: None,
+            config,
             stdout: out,
             stderr: err,
         }
     }
 
+    // Increments $SHLVL, which counts the shells started from one another
+    pub fn enter_shell_level(&mut self) {
+        let level = env::var("SHLVL").ok().and_then(|level| level.parse::<i64>().ok()).unwrap_or(0);
+        env::set_var("SHLVL", (level + 1).max(0).to_string());
+    }
+
+    // Parameters computed each time they are referenced
+    pub fn dynamic_parameter(&mut self, name: &str) -> Option<String> {
+        match name {
+            "$" => Some(process::id().to_string()),
+            "!" => self.jobs.last_pid.map(|pid| pid.to_string()),
+            // Flags of the single letter options. Command paths are always hashed, and the emacs and
+            // vi keymaps of `set -o` have no letter.
+            "-" => Some(if self.interactive { "hi" } else { "h" }.to_string()),
+            "_" => Some(self.last_argument.clone()),
+            "RANDOM" => {
+                // xorshift, which is plenty for 15 bits of randomness
+                self.random_state ^= self.random_state << 13;
+                self.random_state ^= self.random_state >> 17;
+                self.random_state ^= self.random_state << 5;
+                Some((self.random_state & 0x7fff).to_string())
+            },
+            "SECONDS" => Some((self.seconds_offset + self.seconds_start.elapsed().as_secs() as i64).to_string()),
+            "LINENO" => Some(self.lineno.to_string()),
+            "EPOCHSECONDS" => Some(SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0).to_string()),
+            _ => None
+        }
+    }
+
+    // Assigning RANDOM seeds the generator, and SECONDS counts on from the value assigned
+    pub fn assign(&mut self, assignment: &Assignment) -> Result<(), String> {
+        self.variables.assign(assignment)?;
+        let number = || self.variables.get(&assignment.name).and_then(|value| value.trim().parse::<i64>().ok()).unwrap_or(0);
+        match assignment.name.as_str() {
+            "RANDOM" => {
+                // Spread small seeds over the bits, the generator gets stuck on zero
+                let seed = (number() as u32).wrapping_mul(0x9e3779b9) ^ 0x2545f491;
+                self.random_state = if seed == 0 { 1 } else { seed };
+            },
+            "SECONDS" => {
+                self.seconds_offset = number();
+                self.seconds_start = Instant::now();
+            },
+            _ => ()
+        }
+        Ok(())
+    }
+
     pub fn update_size(&mut self, width: u16, height: u16) {
         self.termsize = (width, height);
         env::set_var("COLUMNS", width.to_string());
@@ -1,8 +1,18 @@
-use std::io::Cursor;
+use std::io::{self, Cursor};
 
-use crossterm::{style::Print, QueueableCommand};
+use crossterm::{style::{Color, Print, ResetColor, SetForegroundColor}, QueueableCommand};
 
-use crate::{eval::{builtins::BuiltinError, execute::ExecutionError, expression::ParserError}, parser::tokenizer::TokenizationError};
+use crate::core::fsio::FSError;
+use crate::{eval::{builtins::BuiltinError, execute::ExecutionError, expression::ParserError}, parser::tokenizer::{Span, TokenizationError}};
+
+// `No such file or directory` rather than `No such file or directory (os error 2)`
+pub fn io_error_message(error: &io::Error) -> String {
+  let message = error.to_string();
+  match message.find(" (os error") {
+      Some(pos) => message[..pos].to_string(),
+      None => message
+  }
+}
 
 pub trait StatusEnum {
   fn status(&self) -> u16;
@@ -14,8 +24,12 @@ pub enum ShellError {
     Execution(ExecutionError),
     Builtin(BuiltinError),
     Parser(ParserError),
+    FileSystem(FSError),
+    // Path of the configuration file and why it could not be parsed
+    Config(String, String),
     NoBuiltin,
-    ExitRequest
+    ExitRequest(i32),
+    ReturnRequest(i32)
 }
 
 impl From<ExecutionError> for ShellError {
@@ -42,15 +56,29 @@ impl From<ParserError> for ShellError {
     }
 }
 
+impl From<FSError> for ShellError {
+    fn from(err: FSError) -> Self {
+        ShellError::FileSystem(err)
+    }
+}
+
 impl ShellError {
   pub fn to_output(&self, input: &str) -> Vec<u8> {
+      self.to_located_output(input, None)
+  }
+
+  // `location` is the script name and the line `input` starts at, which prefix syntax errors
+  pub fn to_located_output(&self, input: &str, location: Option<(&str, usize)>) -> Vec<u8> {
       match self {
-          ShellError::Tokenization(error) => print_tokenization_error(error),
+          ShellError::Tokenization(error) => print_syntax_error(&error.message, Some(error.span), input, location),
           ShellError::Execution(error) => print_execution_error(error, input),
           ShellError::Builtin(error) => print_builtin_error(error),
-          ShellError::Parser(error) => print_parser_error(error),
+          ShellError::Parser(error) => print_syntax_error(&error.message, error.span, input, location),
+          ShellError::FileSystem(FSError::IOError { path, message, .. }) => format!("{}: {}", path, message).into_bytes(),
+          ShellError::Config(path, message) => format!("{}: {}\nUsing the default configuration.", path, message.trim_end()).into_bytes(),
           ShellError::NoBuiltin => "The requested builtin command was not found.".as_bytes().to_vec(),
-          ShellError::ExitRequest => "The shell received an exit request.".as_bytes().to_vec(),
+          ShellError::ExitRequest(_) => "The shell received an exit request.".as_bytes().to_vec(),
+          ShellError::ReturnRequest(_) => "return: can only `return' from a function or sourced script".as_bytes().to_vec(),
       }
   }
 
@@ -60,16 +88,40 @@ impl ShellError {
           ShellError::Execution(error) => error.status(),
           ShellError::Builtin(error) => error.status,
           ShellError::Parser(error) => error.status(),
+          ShellError::FileSystem(_) | ShellError::Config(_, _) => 1,
           ShellError::NoBuiltin => 127,
-          ShellError::ExitRequest => 0,
+          ShellError::ExitRequest(code) | ShellError::ReturnRequest(code) => *code as u16,
       }
   }
 }
 
-pub fn print_tokenization_error(error: &TokenizationError) -> Vec<u8> {
+// `syntax error: message`, followed by the line of the input holding the span, underlined
+pub fn print_syntax_error(message: &str, span: Option<Span>, input: &str, location: Option<(&str, usize)>) -> Vec<u8> {
   let mut output: Vec<u8> = Vec::new();
   let mut cursor = Cursor::new(&mut output);
-  cursor.queue(Print(format!("{:?}", error))).unwrap();
+  let Some(span) = span else {
+    if let Some((name, line)) = location {
+      cursor.queue(Print(format!("{}:{}: ", name, line))).unwrap();
+    }
+    cursor.queue(Print(format!("syntax error: {}", message))).unwrap();
+    return output;
+  };
+  let chars: Vec<char> = input.chars().collect();
+  let start = span.start.min(chars.len());
+  let line_start = chars[..start].iter().rposition(|c| *c == '\n').map_or(0, |pos| pos + 1);
+  let line_end = chars[start..].iter().position(|c| *c == '\n').map_or(chars.len(), |pos| start + pos);
+  if let Some((name, first_line)) = location {
+    let line = first_line + chars[..line_start].iter().filter(|c| **c == '\n').count();
+    cursor.queue(Print(format!("{}:{}:{}: ", name, line, start - line_start + 1))).unwrap();
+  }
+  let line: String = chars[line_start..line_end].iter().collect();
+  // Tabs are kept so that the underline lines up with the text above
+  let padding: String = chars[line_start..start].iter().map(|c| if *c == '\t' { '\t' } else { ' ' }).collect();
+  let length = span.end.min(line_end).saturating_sub(start).max(1);
+  cursor.queue(Print(format!("syntax error: {}\n  {}\n  {}", message, line, padding))).unwrap()
+        .queue(SetForegroundColor(Color::Yellow)).unwrap()
+        .queue(Print(format!("^{}", "~".repeat(length - 1)))).unwrap()
+        .queue(ResetColor).unwrap();
   return output;
 }
 
@@ -77,11 +129,26 @@ pub fn print_execution_error(error: &ExecutionError, input: &str) -> Vec<u8>  {
   let mut output: Vec<u8> = Vec::new();
   let mut cursor = Cursor::new(&mut output);
   match error {
-    ExecutionError::CommandNotFound => {
-      cursor.queue(Print(&format!("{}: command not found", input))).unwrap();
+    ExecutionError::CommandNotFound(program, _) if program.contains('/') => {
+      cursor.queue(Print(format!("{}: No such file or directory", program))).unwrap();
+    },
+    ExecutionError::CommandNotFound(program, suggestion) => {
+      cursor.queue(Print(format!("{}: command not found", program))).unwrap();
+      if let Some(suggestion) = suggestion {
+        cursor.queue(Print(format!("\nDid you mean `{}`?", suggestion))).unwrap();
+      }
+    },
+    ExecutionError::PermissionDenied(program) => {
+      cursor.queue(Print(format!("{}: Permission denied", program))).unwrap();
+    },
+    ExecutionError::NotExecutable(program) => {
+      cursor.queue(Print(format!("{}: cannot execute: Exec format error", program))).unwrap();
+    },
+    ExecutionError::IsADirectory(program) => {
+      cursor.queue(Print(format!("{}: Is a directory", program))).unwrap();
     },
     _ => {
-      cursor.queue(Print(format!("{:?}", error))).unwrap();
+      cursor.queue(Print(format!("{}: {:?}", input, error))).unwrap();
     }
   }
   return output;
@@ -93,10 +160,3 @@ pub fn print_builtin_error(error: &BuiltinError) -> Vec<u8>  {
   cursor.queue(Print(format!("{}", error.message))).unwrap();
   return output;
 }
-
-pub fn print_parser_error(error: &ParserError) -> Vec<u8>  {
-  let mut output: Vec<u8> = Vec::new();
-  let mut cursor = Cursor::new(&mut output);
-  cursor.queue(Print(format!("{:?}", error))).unwrap();
-  return output;
-}
\ No newline at end of file
@@ -1,9 +1,37 @@
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::fs::File;
 use std::fs::OpenOptions;
+use std::mem::ManuallyDrop;
+use std::os::fd::{FromRawFd, RawFd};
 
+use crate::core::core::ShellState;
+use crate::core::osstr::word_to_os;
+use crate::core::error::{io_error_message, ShellError};
+
+// A failed file operation, with the path it was about
+#[derive(Debug)]
 pub enum FSError {
-    IOError
+    IOError { path: String, kind: io::ErrorKind, message: String }
+}
+
+impl FSError {
+    pub fn new(path: &str, error: &io::Error) -> FSError {
+        FSError::IOError {
+            path: path.to_string(),
+            kind: error.kind(),
+            message: io_error_message(error)
+        }
+    }
+}
+
+// A write to the shell's own output that failed, as after `exec >/dev/full`, is reported
+// on stderr and fails the command instead of stopping the shell
+pub fn report_write_error(state: &mut ShellState, error: &io::Error) {
+    let error = ShellError::FileSystem(FSError::new("write error", error));
+    state.status = error.status() as i32;
+    let _ = state.stderr.write_all(&error.to_output(""));
+    let _ = state.stderr.write_all(b"\n");
+    let _ = state.stderr.flush();
 }
 
 pub fn open_file(path: &str, truncate: bool) -> Result<File, FSError> {
@@ -14,35 +42,71 @@ pub fn open_file(path: &str, truncate: bool) -> Result<File, FSError> {
     } else {
         options.append(true);
     }
-    match options.open(path) {
-        Ok(file) => Ok(file),
-        Err(_) => Err(FSError::IOError),
-    }
+    options.open(word_to_os(path)).map_err(|error| FSError::new(path, &error))
 }
 
 // Out
 
-pub fn write_output_to_file(output: &Vec<u8>, path: &str, truncate: bool) -> Result<(), FSError> {
-    match open_file(path, truncate) {
-        Ok(mut file) => {
-            file.write_all(output).unwrap();
-            return Ok(())
-        },
-        Err(error) => Err(error)
+// The shell's standard output. Like io::Stdout, lines are written once complete, but what cannot
+// be written is dropped rather than kept for the next write, so that one failure does not make
+// every following write fail. The error is returned by the next flush.
+pub struct ShellOutput {
+    fd: RawFd,
+    buffer: Vec<u8>,
+    error: Option<io::Error>
+}
+
+impl ShellOutput {
+    pub fn new(fd: RawFd) -> ShellOutput {
+        ShellOutput { fd, buffer: Vec::new(), error: None }
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        // The descriptor belongs to the process, it is not closed with the file
+        let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(self.fd) });
+        let buffer = std::mem::take(&mut self.buffer);
+        file.write_all(&buffer)
     }
 }
 
+impl Write for ShellOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if data.contains(&b'\n') {
+            if let Err(error) = self.write_buffer() {
+                self.error.get_or_insert(error);
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.write_buffer();
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => result
+        }
+    }
+}
+
+// Flushes the shell's output, reporting a failure
+pub fn flush_output(state: &mut ShellState) {
+    if let Err(error) = state.stdout.flush() {
+        report_write_error(state, &error);
+    }
+}
+
+pub fn write_output_to_file(output: &[u8], path: &str, truncate: bool) -> Result<(), FSError> {
+    let mut file = open_file(path, truncate)?;
+    // A full disk only shows up here
+    file.write_all(output).map_err(|error| FSError::new(path, &error))
+}
+
 // In
 
 pub fn read_file_as_input(path: &str) -> Result<Vec<u8>, FSError> {
-    match File::open(path) {
-        Ok(mut file) => {
-            let mut buffer: Vec<u8> = Vec::new();
-            match file.read_to_end(&mut buffer) {
-                Ok(_) => return Ok(buffer),
-                Err(_) => Err(FSError::IOError)
-            }
-        },
-        Err(_) => Err(FSError::IOError)
-    }
-}
\ No newline at end of file
+    let mut file = File::open(word_to_os(path)).map_err(|error| FSError::new(path, &error))?;
+    let mut buffer: Vec<u8> = Vec::new();
+    file.read_to_end(&mut buffer).map_err(|error| FSError::new(path, &error))?;
+    Ok(buffer)
+}
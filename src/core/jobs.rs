@@ -0,0 +1,104 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+
+use crossterm::style::Print;
+use crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+
+pub struct Job {
+    pub id: usize,
+    pub pid: u32,
+    pub command: String,
+    pub child: Child
+}
+
+// Commands started with `&`, numbered like bash's jobs: `%1`, `%2`...
+pub struct JobTable {
+    jobs: Vec<Job>,
+    pub last_pid: Option<u32>
+}
+
+// Exit code of a finished process, 128+N when it was killed by signal N
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    match status.code() {
+        Some(code) => code,
+        None => 128 + status.signal().unwrap_or(0)
+    }
+}
+
+impl JobTable {
+    pub fn new() -> JobTable {
+        JobTable {
+            jobs: Vec::new(),
+            last_pid: None
+        }
+    }
+
+    pub fn add(&mut self, child: Child, command: &str) -> &Job {
+        let id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        let pid = child.id();
+        self.last_pid = Some(pid);
+        self.jobs.push(Job { id, pid, command: command.to_string(), child });
+        self.jobs.last().unwrap()
+    }
+
+    pub fn get_jobs(&self) -> &Vec<Job> {
+        &self.jobs
+    }
+
+    // Resolves `%N`, `%%`, `%+`, `%-`, `%string` (command starts with) and `%?string` (command contains)
+    pub fn find_spec(&self, spec: &str) -> Option<&Job> {
+        let spec = spec.strip_prefix('%')?;
+        match spec {
+            "" | "%" | "+" => self.jobs.last(),
+            "-" => if self.jobs.len() > 1 { self.jobs.get(self.jobs.len() - 2) } else { self.jobs.last() },
+            _ => {
+                if let Ok(id) = spec.parse::<usize>() {
+                    self.jobs.iter().find(|job| job.id == id)
+                } else if let Some(pattern) = spec.strip_prefix('?') {
+                    self.jobs.iter().rev().find(|job| job.command.contains(pattern))
+                } else {
+                    self.jobs.iter().rev().find(|job| job.command.starts_with(spec))
+                }
+            }
+        }
+    }
+
+    pub fn find_pid(&self, pid: u32) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.pid == pid)
+    }
+
+    // Blocks until the job finishes and forgets it
+    pub fn wait(&mut self, pid: u32) -> Option<i32> {
+        let index = self.jobs.iter().position(|job| job.pid == pid)?;
+        let mut job = self.jobs.remove(index);
+        Some(match job.child.wait() {
+            Ok(status) => exit_code(&status),
+            Err(_) => 127
+        })
+    }
+
+    // Removes the jobs that finished, returning them with their exit code
+    pub fn reap(&mut self) -> Vec<(Job, i32)> {
+        let mut finished = Vec::new();
+        let mut index = 0;
+        while index < self.jobs.len() {
+            match self.jobs[index].child.try_wait() {
+                Ok(Some(status)) => finished.push((self.jobs.remove(index), exit_code(&status))),
+                Ok(None) => index += 1,
+                Err(_) => finished.push((self.jobs.remove(index), 127))
+            }
+        }
+        finished
+    }
+}
+
+// Prints `[1]+  Done   cmd` for the jobs that finished since the last prompt
+pub fn notify_finished_jobs(state: &mut ShellState) {
+    for (job, code) in state.jobs.reap() {
+        let status = if code == 0 { "Done".to_string() } else { format!("Exit {}", code) };
+        let _ = state.stderr.queue(Print(format!("[{}]+  {:<24}{}\n", job.id, status, job.command)));
+    }
+    let _ = state.stderr.flush();
+}
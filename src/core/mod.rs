@@ -1,6 +1,12 @@
+pub mod arithmetic;
 pub mod cmdoutput;
 pub mod config;
 pub mod core;
 pub mod fsio;
+pub mod jobs;
+pub mod osstr;
+pub mod pathcache;
 pub mod readloop;
-pub mod error;
\ No newline at end of file
+pub mod signals;
+pub mod error;
+pub mod variables;
\ No newline at end of file
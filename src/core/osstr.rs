@@ -0,0 +1,100 @@
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+// Words are strings, so the bytes of file names and output that are not valid UTF-8
+// are kept as characters of a private use area, one per byte. They become bytes
+// again when the word leaves the shell: program arguments, paths and output.
+// Characters of that range found in valid text are escaped the same way, byte by byte,
+// so that they come out unchanged.
+const ESCAPED_BYTES: u32 = 0x10FF00;
+
+fn push_escaped_byte(word: &mut String, byte: u8) {
+    // Escaped bytes are always 0x80 or above, far below the end of the plane
+    word.push(char::from_u32(ESCAPED_BYTES + byte as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+}
+
+pub fn bytes_to_word(bytes: &[u8]) -> String {
+    let mut word = String::new();
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c as u32 >= ESCAPED_BYTES + 0x80 {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    push_escaped_byte(&mut word, byte);
+                }
+            } else {
+                word.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            push_escaped_byte(&mut word, *byte);
+        }
+    }
+    word
+}
+
+// Text typed at the prompt or read from a script, where characters of the escape range are literal
+pub fn text_to_word(text: &str) -> String {
+    bytes_to_word(text.as_bytes())
+}
+
+pub fn os_to_word(os: &OsStr) -> String {
+    bytes_to_word(os.as_bytes())
+}
+
+// Appends the bytes `c` stands for
+pub fn push_char_bytes(bytes: &mut Vec<u8>, c: char) {
+    match (c as u32).checked_sub(ESCAPED_BYTES) {
+        Some(byte) if byte >= 0x80 => bytes.push(byte as u8),
+        _ => {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+        }
+    }
+}
+
+pub fn word_to_bytes(word: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(word.len());
+    for c in word.chars() {
+        push_char_bytes(&mut bytes, c);
+    }
+    bytes
+}
+
+pub fn word_to_os(word: &str) -> OsString {
+    OsString::from_vec(word_to_bytes(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_round_trip() {
+        let bytes = b"caf\xe9 \xff\x80 ok".to_vec();
+        let word = bytes_to_word(&bytes);
+        assert_eq!(word.chars().filter(|c| *c as u32 >= ESCAPED_BYTES).count(), 3);
+        assert_eq!(word_to_bytes(&word), bytes);
+    }
+
+    #[test]
+    fn valid_text_is_unchanged() {
+        let text = "héllo \u{6f22} \u{1f469}\u{200d}\u{1f4bb}";
+        assert_eq!(bytes_to_word(text.as_bytes()), text);
+        assert_eq!(word_to_bytes(text), text.as_bytes());
+    }
+
+    #[test]
+    fn characters_of_the_escape_range_round_trip() {
+        let text = "\u{10ff80}\u{10ffff}";
+        let word = text_to_word(text);
+        assert_eq!(word.chars().count(), 8);
+        assert_eq!(word_to_bytes(&word), text.as_bytes());
+    }
+
+    #[test]
+    fn os_strings_round_trip() {
+        let os = OsStr::from_bytes(b"dir/\xe9t\xe9");
+        assert_eq!(word_to_os(&os_to_word(os)), os);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::core::osstr::os_to_word;
+
+pub struct HashEntry {
+    pub path: PathBuf,
+    pub hits: u32
+}
+
+// Remembers where commands were found in $PATH, like bash's `hash` table.
+// Everything is dropped as soon as PATH changes.
+pub struct PathCache {
+    path_var: Option<String>,
+    entries: BTreeMap<String, HashEntry>,
+    executables: Option<Vec<String>>
+}
+
+pub fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false
+    }
+}
+
+pub fn search_path(name: &str, path_var: &str, all: bool) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for dir in path_var.split(':') {
+        // An empty PATH entry means the current directory
+        let candidate = Path::new(if dir.is_empty() { "." } else { dir }).join(name);
+        if is_executable(&candidate) {
+            found.push(candidate);
+            if !all {
+                break;
+            }
+        }
+    }
+    found
+}
+
+impl PathCache {
+    pub fn new() -> PathCache {
+        PathCache {
+            path_var: env::var("PATH").ok(),
+            entries: BTreeMap::new(),
+            executables: None
+        }
+    }
+
+    fn validate(&mut self) {
+        let current = env::var("PATH").ok();
+        if current != self.path_var {
+            self.path_var = current;
+            self.clear();
+        }
+    }
+
+    pub fn get_path_var(&mut self) -> String {
+        self.validate();
+        self.path_var.clone().unwrap_or_default()
+    }
+
+    // Resolves a command for execution, counting a hit
+    pub fn lookup(&mut self, name: &str) -> Option<PathBuf> {
+        self.validate();
+        if let Some(entry) = self.entries.get_mut(name) {
+            if is_executable(&entry.path) {
+                entry.hits += 1;
+                return Some(entry.path.clone());
+            }
+            // The binary went away, search again
+            self.entries.remove(name);
+        }
+        let path = search_path(name, self.path_var.as_deref().unwrap_or(""), false).into_iter().next()?;
+        self.entries.insert(name.to_string(), HashEntry { path: path.clone(), hits: 1 });
+        Some(path)
+    }
+
+    // Searches and remembers a command without counting a hit
+    pub fn add(&mut self, name: &str) -> bool {
+        self.validate();
+        match search_path(name, self.path_var.as_deref().unwrap_or(""), false).into_iter().next() {
+            Some(path) => {
+                self.insert(name, path);
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, path: PathBuf) {
+        self.validate();
+        self.entries.insert(name.to_string(), HashEntry { path, hits: 0 });
+    }
+
+    pub fn get(&mut self, name: &str) -> Option<&HashEntry> {
+        self.validate();
+        self.entries.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.executables = None;
+    }
+
+    pub fn get_entries(&mut self) -> &BTreeMap<String, HashEntry> {
+        self.validate();
+        &self.entries
+    }
+
+    // Names of every executable reachable through PATH, scanned once per PATH value
+    pub fn get_executables(&mut self) -> &Vec<String> {
+        self.validate();
+        if self.executables.is_none() {
+            let mut names: BTreeSet<String> = BTreeSet::new();
+            for dir in self.path_var.as_deref().unwrap_or("").split(':') {
+                if let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+                    for entry in entries.flatten() {
+                        if is_executable(&entry.path()) {
+                            names.insert(os_to_word(&entry.file_name()));
+                        }
+                    }
+                }
+            }
+            self.executables = Some(names.into_iter().collect());
+        }
+        self.executables.as_ref().unwrap()
+    }
+}
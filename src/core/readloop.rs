@@ -1,14 +1,64 @@
-use crossterm::{cursor, event::{read, Event, KeyCode, KeyEvent, KeyModifiers}, style::Print, terminal::{Clear, ClearType}, QueueableCommand};
+use std::time::Duration;
 
-use crate::{features::{autocomplete::Autocomplete, prompt::{CursorMovement, CursorPosition, Prompt}}, rendering::prompt::{align_cursor_with_prompt, clear_prompt_input, print_prompt_input}};
+use crossterm::{cursor, event::{poll, read, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyModifiers}, style::Print, terminal::{Clear, ClearType}, QueueableCommand};
+
+use crate::{core::config::EditMode, features::{autocomplete::Autocomplete, autosuggest::suggest, prompt::{CursorMovement, CursorPosition, Prompt, WordBoundary, WordCase}, search::{accept_search, handle_search_key, start_search}, vi::{handle_vi_key, ViMode}}, rendering::{layout::Layout, search::render_search, prompt::{align_cursor_with_prompt, clear_prompt_input, highlight_selection, print_prompt, print_prompt_input, render_suggestion, repaint_prompt}}};
+
+use crate::core::error::ShellError;
+use crate::core::fsio::{flush_output, report_write_error};
+use crate::core::signals::signals_pending;
+use crate::eval::traps::run_pending_traps;
 
 use super::core::ShellState;
 
-pub fn handle_ctrl_modifiers(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, event: KeyEvent) -> (i32, bool) {
-  match event.code {
+// How often the line editor wakes up to run the traps of signals received meanwhile
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Redraws the whole input after an edit, which may have removed lines
+pub fn redraw_input(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &Prompt) {
+  let (ps1col, ps1row) = state.ps1pos;
+  autocomplete.reset(state);
+  state.stdout.queue(cursor::MoveTo(ps1col, ps1row)).unwrap()
+              .queue(Clear(ClearType::FromCursorDown)).unwrap();
+  print_prompt_input(state, prompt.get_input());
+  align_cursor_with_prompt(state, prompt);
+}
+
+// Up and Down only go through the entries starting with the text that was before the cursor
+fn history_prefix(state: &ShellState, prompt: &Prompt, history_idx: &Option<usize>) -> String {
+  if !state.config.editor.prefix_history {
+    return String::new();
+  }
+  match history_idx {
+    Some(_) => prompt.get_stash_prefix().to_string(),
+    None => prompt.get_input()[..prompt.get_cursor()].to_string()
+  }
+}
+
+// The cursor stays after the prefix, or goes to the end without one
+fn show_history_entry(state: &mut ShellState, prompt: &mut Prompt, entry: &str, prefix_len: usize) {
+  clear_prompt_input(state, prompt);
+  prompt.set_input(entry);
+  if prefix_len > 0 {
+    prompt.set_cursor(prefix_len);
+  }
+  print_prompt_input(state, prompt.get_input());
+  align_cursor_with_prompt(state, prompt);
+}
+
+// Erases the suggestion before the line is left
+fn clear_suggestion(state: &mut ShellState, prompt: &mut Prompt) {
+  prompt.set_suggestion(None);
+  render_suggestion(state, prompt);
+}
+
+// Emacs keymap, `count` is the numeric argument given with Alt and digits
+pub fn handle_ctrl_modifiers(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: KeyEvent, count: i64) -> (i32, bool) {
+  let changed = match event.code {
     KeyCode::Char(c) => {
         match c {
             'c' => {
+                clear_suggestion(state, prompt);
                 align_cursor_with_prompt(state, prompt);
                 state.stdout.queue(Print("^C")).unwrap();
                 prompt.clear_stash();
@@ -27,43 +77,60 @@ pub fn handle_ctrl_modifiers(state: &mut ShellState, autocomplete: &mut Autocomp
                 prompt.clear_input();
                 return (0, false);
             },
-            'k' => {
-                if prompt.truncate_input() {
-                    state.stdout.queue(Clear(ClearType::FromCursorDown)).unwrap();
-                }
+            'r' | 's' => {
+                autocomplete.reset(state);
+                start_search(state, prompt, history_idx, c == 'r');
                 return (0, false);
-            }
-            _ => return (0, false)
+            },
+            'a' => prompt.move_cursor(CursorPosition::Origin),
+            'e' => prompt.accept_suggestion(false) || prompt.move_cursor(CursorPosition::End),
+            'b' => prompt.move_cursor_by(-count, CursorMovement::One),
+            'f' => prompt.accept_suggestion(false) || prompt.move_cursor_by(count, CursorMovement::One),
+            'k' if count < 0 => prompt.kill_to_start(),
+            'k' => prompt.truncate_input(),
+            'u' => prompt.kill_to_start(),
+            'w' => prompt.kill_words(-count.abs(), WordBoundary::Whitespace),
+            'y' => prompt.yank(),
+            't' => prompt.transpose_chars(count),
+            // Ctrl-_ and Ctrl-/ both send 0x1f, which is reported as Ctrl-7
+            '_' | '/' | '7' => prompt.undo(),
+            _ => false
         }
     },
-    _ => return (0, false)
+    _ => false
+  };
+  if changed {
+    redraw_input(state, autocomplete, prompt);
   }
+  (0, false)
 }
 
-pub fn handle_alt_modifiers(state: &mut ShellState, prompt: &mut Prompt, event: KeyEvent) -> (i32, bool) {
-  match event.code {
-    KeyCode::Left => {
-        let diff = prompt.move_cursor_left(CursorMovement::Word);
-        if diff > 0 {
-            align_cursor_with_prompt(state, prompt);
-        }
-        return (0, false);
-    },
-    KeyCode::Right => {
-        let diff = prompt.move_cursor_right(CursorMovement::Word);
-        if diff > 0 {
-            align_cursor_with_prompt(state, prompt);
-        }
-        return (0, false);
-    },
-    _ => return (0, false)
-}
+pub fn handle_alt_modifiers(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, event: KeyEvent, count: i64) -> (i32, bool) {
+  let changed = match event.code {
+    KeyCode::Left | KeyCode::Char('b') => prompt.move_cursor_by(-count, CursorMovement::Word),
+    KeyCode::Right | KeyCode::Char('f') => prompt.accept_suggestion(true) || prompt.move_cursor_by(count, CursorMovement::Word),
+    KeyCode::Char('d') => prompt.kill_words(count, WordBoundary::Alphanumeric),
+    KeyCode::Backspace => prompt.kill_words(-count, WordBoundary::Alphanumeric),
+    KeyCode::Char('y') => prompt.yank_pop(),
+    KeyCode::Char('t') => prompt.transpose_words(),
+    KeyCode::Char('u') => prompt.change_case(count, WordCase::Upper),
+    KeyCode::Char('l') => prompt.change_case(count, WordCase::Lower),
+    KeyCode::Char('c') => prompt.change_case(count, WordCase::Capitalize),
+    KeyCode::Char('_') => prompt.redo(),
+    _ => false
+  };
+  if changed {
+    redraw_input(state, autocomplete, prompt);
+  }
+  (0, false)
 }
 
-pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: KeyEvent) -> (i32, bool) {
+pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: KeyEvent, count: i64) -> (i32, bool) {
   match event.code {
     KeyCode::Char(c) => {
-        prompt.add_char(c);
+        for _ in 0..count.max(1) {
+            prompt.add_char(c);
+        }
         autocomplete.reset(state);
         clear_prompt_input(state, &prompt);
         print_prompt_input(state, prompt.get_input());
@@ -78,7 +145,9 @@ pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, pro
         return (0, false);
     },
     KeyCode::End => {
-        if prompt.move_cursor(CursorPosition::End) {
+        if prompt.accept_suggestion(false) {
+            redraw_input(state, autocomplete, prompt);
+        } else if prompt.move_cursor(CursorPosition::End) {
             align_cursor_with_prompt(state, prompt);
         }
         return (0, false);
@@ -91,6 +160,10 @@ pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, pro
         return (0, false);
     },
     KeyCode::Right => {
+        if prompt.accept_suggestion(false) {
+            redraw_input(state, autocomplete, prompt);
+            return (0, false);
+        }
         let diff = prompt.move_cursor_right(CursorMovement::One);
         if diff > 0 {
             align_cursor_with_prompt(state, prompt);
@@ -98,38 +171,41 @@ pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, pro
         return (0, false);
     },
     KeyCode::Up => {
-        if !history_idx.is_some() {
-            let index = state.history.get_first_index();
-            if index.is_some() {
-                *history_idx = index;
+        let prefix = history_prefix(state, prompt, history_idx);
+        let values = state.history.get_values();
+        let from = history_idx.unwrap_or(values.len());
+        let found = (0..from).rev().find(|index| values[*index].starts_with(&prefix) && values[*index] != *prompt.get_input());
+        if let Some(index) = found {
+            if history_idx.is_none() {
                 prompt.stash_input();
-                clear_prompt_input(state, &prompt);
-                prompt.set_input(state.history.get(history_idx.unwrap()));
-                print_prompt_input(state, prompt.get_input());
-            }
-        } else {
-            if history_idx.unwrap() > 0 {
-                *history_idx = Some(history_idx.unwrap() - 1);
-                clear_prompt_input(state, &prompt);
-                prompt.set_input(state.history.get(history_idx.unwrap()));
-                print_prompt_input(state, prompt.get_input());
             }
+            *history_idx = Some(index);
+            let entry = state.history.get(index).to_string();
+            show_history_entry(state, prompt, &entry, prefix.len());
         }
         return (0, false);
     },
     KeyCode::Down => {
-        if history_idx.is_some() {
-            if let Some(last_index) = state.history.get_first_index() {
-                if history_idx.unwrap() < last_index {
-                    *history_idx = Some(history_idx.unwrap() + 1);
-                    clear_prompt_input(state, &prompt);
-                    prompt.set_input(state.history.get(history_idx.unwrap()));
-                    print_prompt_input(state, prompt.get_input());
-                } else {
+        if let Some(current) = *history_idx {
+            let prefix = history_prefix(state, prompt, history_idx);
+            let values = state.history.get_values();
+            let found = (current + 1..values.len()).find(|index| values[*index].starts_with(&prefix) && values[*index] != *prompt.get_input());
+            match found {
+                Some(index) => {
+                    *history_idx = Some(index);
+                    let entry = state.history.get(index).to_string();
+                    show_history_entry(state, prompt, &entry, prefix.len());
+                },
+                // Past the newest entry, back to what was typed
+                None => {
                     *history_idx = None;
                     clear_prompt_input(state, &prompt);
                     prompt.unstash_input();
+                    if !prefix.is_empty() {
+                        prompt.set_cursor(prefix.len());
+                    }
                     print_prompt_input(state, prompt.get_input());
+                    align_cursor_with_prompt(state, prompt);
                 }
             }
         }
@@ -168,43 +244,150 @@ pub fn handle_input(state: &mut ShellState, autocomplete: &mut Autocomplete, pro
         return (0, false);
     },
     KeyCode::Enter => {
+        // Output starts below the last row of the input, wherever the cursor was
+        prompt.move_cursor(CursorPosition::End);
+        align_cursor_with_prompt(state, prompt);
         autocomplete.reset(state);
+        // The line typed before browsing history is dropped once another one runs
+        prompt.clear_stash();
+        clear_suggestion(state, prompt);
         return (1, true);
     },
     _ => return (0, false)
   }
 }
 
+// With bracketed paste, pasted text comes at once and is inserted as is, newlines included,
+// instead of being typed key by key and run at the first newline
+pub fn handle_paste(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, text: &str) {
+  accept_search(state, prompt, history_idx);
+  // Terminals send line breaks as carriage returns
+  let mut text = text.replace("\r\n", "\n").replace('\r', "\n");
+  if state.config.editor.strip_paste_prompts {
+    let lines: Vec<&str> = text.split('\n').map(|line| line.strip_prefix("$ ").unwrap_or(line)).collect();
+    text = lines.join("\n");
+  }
+  let start = prompt.get_cursor();
+  prompt.insert_str(&text);
+  redraw_input(state, autocomplete, prompt);
+  if state.config.editor.highlight_paste {
+    highlight_selection(state, prompt, start, start + text.len());
+  }
+}
+
 pub fn handle_event(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: Event) -> (i32, bool) {
   match event {
     Event::Resize(width, height) => {
+      // The terminal rewraps the rows, which moves ps1: it is found again from the cursor
+      let (_, row) = Layout::new(state.ps1pos.0, state.termsize.0).position(prompt.get_input(), prompt.get_cursor());
       state.update_size(width, height);
+      let (_, currow) = cursor::position().unwrap_or((0, state.ps1pos.1 + row as u16));
+      state.ps1pos.1 = currow.saturating_sub(row as u16);
+      autocomplete.reset(state);
+      if state.search.is_some() {
+        render_search(state, prompt);
+      } else {
+        repaint_prompt(state, prompt);
+      }
       return (0, false);
     },
+    Event::Paste(text) => {
+      prompt.begin_command();
+      handle_paste(state, autocomplete, prompt, history_idx, &text);
+      (0, false)
+    },
     Event::Key(event) => {
+        prompt.begin_command();
+        if let Some(result) = handle_search_key(state, prompt, history_idx, event) {
+          return result;
+        }
+        if state.edit_mode == EditMode::Vi {
+          if let Some(result) = handle_vi_key(state, autocomplete, prompt, history_idx, event) {
+            return result;
+          }
+        }
+        // `Alt-3 Ctrl-W`: digits typed with Alt, or right after, make the numeric argument
+        if let KeyCode::Char(c) = event.code {
+          let alt = event.modifiers.contains(KeyModifiers::ALT);
+          let vi_command = state.edit_mode == EditMode::Vi && state.vi.mode != ViMode::Insert;
+          if (c.is_ascii_digit() || c == '-') && !vi_command && (alt || (prompt.has_argument() && c != '-' && event.modifiers.is_empty())) {
+            prompt.push_argument(c);
+            return (0, false);
+          }
+        }
+        let count = prompt.take_argument();
         if event.modifiers.contains(KeyModifiers::CONTROL) {
-          return handle_ctrl_modifiers(state, autocomplete, prompt, event);
+          handle_ctrl_modifiers(state, autocomplete, prompt, history_idx, event, count)
         } else if event.modifiers.contains(KeyModifiers::ALT) {
-          return handle_alt_modifiers(state, prompt, event);
+          handle_alt_modifiers(state, autocomplete, prompt, event, count)
         } else {
-          return handle_input(state, autocomplete, prompt, history_idx, event);
+          handle_input(state, autocomplete, prompt, history_idx, event, count)
         }
     }
     _ => return (0, false)
   }
 }
 
+// Runs the traps of the signals received while a line is edited below the input, then shows the
+// prompt and the input again. Returns false when a trap asked to exit the shell.
+fn run_traps_at_prompt(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &Prompt) -> bool {
+  autocomplete.reset(state);
+  let rows = Layout::new(state.ps1pos.0, state.termsize.0).height(prompt.get_input());
+  state.stdout.queue(cursor::MoveTo(0, state.ps1pos.1 + rows as u16 - 1)).unwrap()
+              .queue(Print("\n")).unwrap();
+  flush_output(state);
+  let _ = crossterm::terminal::disable_raw_mode();
+  let result = run_pending_traps(state);
+  flush_output(state);
+  let _ = state.stderr.flush();
+  let _ = crossterm::terminal::enable_raw_mode();
+  if let Err(ShellError::ExitRequest(code)) = result {
+    state.status = code;
+    return false;
+  }
+  print_prompt(state, prompt);
+  flush_output(state);
+  state.ps1pos = cursor::position().unwrap_or(state.ps1pos);
+  print_prompt_input(state, prompt.get_input());
+  align_cursor_with_prompt(state, prompt);
+  true
+}
+
+// Every redraw of the input fails the same way, so a write failure is only reported once per line
+fn flush_input(state: &mut ShellState, write_failed: &mut bool) {
+  if let Err(error) = state.stdout.flush() {
+    if !*write_failed {
+      report_write_error(state, &error);
+    }
+    *write_failed = true;
+  }
+}
+
 pub fn prompt_readloop(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>) -> i32 {
   let mut chars_read = -1;
+  let mut write_failed = false;
   crossterm::terminal::enable_raw_mode().unwrap();
+  state.stdout.queue(EnableBracketedPaste).unwrap();
+  flush_input(state, &mut write_failed);
   loop {
+      if !poll(SIGNAL_POLL_INTERVAL).unwrap_or(false) {
+        if signals_pending() && !run_traps_at_prompt(state, autocomplete, prompt) {
+          prompt.clear_input();
+          break;
+        }
+        continue;
+      }
       if let Ok(event) = read() {
         let (chars, finished) = handle_event(state, autocomplete, prompt, history_idx, event);
         chars_read += chars;
         if finished { break; }
+        let suggestion = suggest(state, prompt);
+        prompt.set_suggestion(suggestion);
+        render_suggestion(state, prompt);
       }
-      state.stdout.flush().unwrap();
+      flush_input(state, &mut write_failed);
   }
+  state.stdout.queue(DisableBracketedPaste).unwrap();
   crossterm::terminal::disable_raw_mode().unwrap();
   return chars_read;
 }
@@ -0,0 +1,72 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const MAX_SIGNAL: usize = 65;
+
+// Set by the signal handler, consumed by the main loop which runs the trap
+static PENDING: [AtomicBool; MAX_SIGNAL] = [const { AtomicBool::new(false) }; MAX_SIGNAL];
+
+pub const SIGNALS: [(&str, libc::c_int); 31] = [
+    ("HUP", libc::SIGHUP), ("INT", libc::SIGINT), ("QUIT", libc::SIGQUIT), ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP), ("ABRT", libc::SIGABRT), ("BUS", libc::SIGBUS), ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL), ("USR1", libc::SIGUSR1), ("SEGV", libc::SIGSEGV), ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE), ("ALRM", libc::SIGALRM), ("TERM", libc::SIGTERM), ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT), ("STOP", libc::SIGSTOP), ("TSTP", libc::SIGTSTP), ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU), ("URG", libc::SIGURG), ("XCPU", libc::SIGXCPU), ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM), ("PROF", libc::SIGPROF), ("WINCH", libc::SIGWINCH), ("IO", libc::SIGIO),
+    ("SYS", libc::SIGSYS), ("STKFLT", libc::SIGSTKFLT), ("PWR", libc::SIGPWR)
+];
+
+pub enum SignalAction {
+    Default,
+    Ignore,
+    Trap
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    if (signal as usize) < MAX_SIGNAL {
+        PENDING[signal as usize].store(true, Ordering::SeqCst);
+    }
+}
+
+// Accepts `INT`, `SIGINT`, `int` or `2`
+pub fn signal_number(spec: &str) -> Option<libc::c_int> {
+    if let Ok(number) = spec.parse::<libc::c_int>() {
+        return if number > 0 && (number as usize) < MAX_SIGNAL { Some(number) } else { None };
+    }
+    let upper = spec.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS.iter().find(|(signal, _)| *signal == name).map(|(_, number)| *number)
+}
+
+pub fn signal_name(number: libc::c_int) -> Option<&'static str> {
+    SIGNALS.iter().find(|(_, signal)| *signal == number).map(|(name, _)| *name)
+}
+
+pub fn set_signal_action(signal: libc::c_int, action: SignalAction) -> bool {
+    let handler = match action {
+        SignalAction::Default => libc::SIG_DFL,
+        SignalAction::Ignore => libc::SIG_IGN,
+        SignalAction::Trap => handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t
+    };
+    unsafe {
+        let mut sigaction: libc::sigaction = std::mem::zeroed();
+        sigaction.sa_sigaction = handler;
+        sigaction.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut sigaction.sa_mask);
+        libc::sigaction(signal, &sigaction, std::ptr::null_mut()) == 0
+    }
+}
+
+pub fn signals_pending() -> bool {
+    PENDING.iter().any(|pending| pending.load(Ordering::SeqCst))
+}
+
+pub fn take_pending_signals() -> Vec<libc::c_int> {
+    let mut signals = Vec::new();
+    for (signal, pending) in PENDING.iter().enumerate() {
+        if pending.swap(false, Ordering::SeqCst) {
+            signals.push(signal as libc::c_int);
+        }
+    }
+    signals
+}
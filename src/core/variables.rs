@@ -0,0 +1,532 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+use crate::core::arithmetic::eval_arithmetic;
+use crate::core::osstr::{os_to_word, word_to_os};
+use crate::parser::tokenizer::is_name;
+
+#[derive(Clone)]
+pub enum Value {
+    Scalar(String),
+    Indexed(BTreeMap<usize, String>),
+    Associative(BTreeMap<String, String>)
+}
+
+// Set with `declare -ilurx`
+#[derive(Clone, Default, PartialEq)]
+pub struct Attributes {
+    pub integer: bool,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub readonly: bool,
+    pub export: bool
+}
+
+// State of a variable before `local` shadowed it, put back when the function returns
+struct SavedVariable {
+    name: String,
+    value: Option<Value>,
+    env: Option<String>,
+    attributes: Option<Attributes>
+}
+
+// `name=value`, `name+=value`, `name[subscript]=value` or `name=(a b c)`
+pub struct Assignment {
+    pub name: String,
+    pub subscript: Option<String>,
+    pub append: bool,
+    pub value: String,
+    // Elements of a `name=(...)` compound assignment
+    pub elements: Option<Vec<String>>
+}
+
+// Exported variables live in the environment, which may hold any bytes
+fn env_value(name: &str) -> Option<String> {
+    env::var_os(name).map(|value| os_to_word(&value))
+}
+
+pub fn parse_assignment(word: &str) -> Option<Assignment> {
+    let (target, value) = word.split_once('=')?;
+    let (target, append) = match target.strip_suffix('+') {
+        Some(target) => (target, true),
+        None => (target, false)
+    };
+    let (name, subscript) = match target.find('[') {
+        Some(pos) if target.ends_with(']') => (&target[..pos], Some(target[pos + 1..target.len() - 1].to_string())),
+        Some(_) => return None,
+        None => (target, None)
+    };
+    if !is_name(name) {
+        return None;
+    }
+    Some(Assignment {
+        name: name.to_string(),
+        subscript,
+        append,
+        value: value.to_string(),
+        elements: None
+    })
+}
+
+// `[key]=value` element of a compound assignment
+fn split_element(element: &str) -> Option<(&str, &str)> {
+    let rest = element.strip_prefix('[')?;
+    let (key, value) = rest.split_once("]=")?;
+    Some((key, value))
+}
+
+// Shell variables. Exported scalars live in the process environment so that
+// children inherit them, everything else is kept in this table.
+pub struct Variables {
+    values: HashMap<String, Value>,
+    attributes: HashMap<String, Attributes>,
+    scopes: Vec<Vec<SavedVariable>>
+}
+
+impl Variables {
+    pub fn new() -> Variables {
+        Variables {
+            values: HashMap::new(),
+            attributes: HashMap::new(),
+            scopes: Vec::new()
+        }
+    }
+
+    pub fn get_attributes(&self, name: &str) -> Attributes {
+        let mut attributes = self.attributes.get(name).cloned().unwrap_or_default();
+        attributes.export |= env::var_os(name).is_some();
+        attributes
+    }
+
+    // Changes the attributes, converting the current value when -i, -l or -u are added
+    pub fn set_attributes(&mut self, name: &str, attributes: Attributes) -> Result<(), String> {
+        let previous = self.get_attributes(name);
+        if previous.readonly && !attributes.readonly {
+            return Err(format!("{}: readonly variable", name));
+        }
+        if attributes.export && !previous.export {
+            if let Some(Value::Scalar(value)) = self.values.remove(name) {
+                env::set_var(name, word_to_os(&value));
+            }
+        } else if !attributes.export && previous.export {
+            if let Some(value) = env_value(name) {
+                env::remove_var(name);
+                self.values.insert(name.to_string(), Value::Scalar(value));
+            }
+        }
+        let convert = (attributes.integer && !previous.integer) || (attributes.lowercase && !previous.lowercase) || (attributes.uppercase && !previous.uppercase);
+        self.attributes.insert(name.to_string(), Attributes { readonly: false, ..attributes.clone() });
+        if convert {
+            if let Some(Value::Scalar(value)) = self.get_value(name) {
+                self.set(name, &value)?;
+            }
+        }
+        self.attributes.insert(name.to_string(), attributes);
+        Ok(())
+    }
+
+    fn check_writable(&self, name: &str) -> Result<(), String> {
+        if self.attributes.get(name).is_some_and(|attributes| attributes.readonly) {
+            return Err(format!("{}: readonly variable", name));
+        }
+        Ok(())
+    }
+
+    // Applies the -i, -l and -u attributes to a value being assigned
+    fn convert(&self, name: &str, previous: Option<String>, value: &str, append: bool) -> Result<String, String> {
+        let attributes = self.attributes.get(name).cloned().unwrap_or_default();
+        let mut value = if attributes.integer {
+            let mut number = eval_arithmetic(value, self)?;
+            if append {
+                number = number.wrapping_add(eval_arithmetic(&previous.unwrap_or_default(), self)?);
+            }
+            number.to_string()
+        } else if append {
+            format!("{}{}", previous.unwrap_or_default(), value)
+        } else {
+            value.to_string()
+        };
+        if attributes.lowercase {
+            value = value.to_lowercase();
+        } else if attributes.uppercase {
+            value = value.to_uppercase();
+        }
+        Ok(value)
+    }
+
+    // Every variable name, exported or not
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned()
+                                         .chain(self.attributes.keys().cloned())
+                                         .chain(env::vars_os().filter_map(|(name, _)| name.into_string().ok()))
+                                         .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    // Whether a function is running, in which case `local` is allowed
+    pub fn in_scope(&self) -> bool {
+        !self.scopes.is_empty()
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        let Some(saved) = self.scopes.pop() else {
+            return;
+        };
+        for variable in saved.into_iter().rev() {
+            match variable.value {
+                Some(value) => self.values.insert(variable.name.clone(), value),
+                None => self.values.remove(&variable.name)
+            };
+            match variable.env {
+                Some(value) => env::set_var(&variable.name, word_to_os(&value)),
+                None => env::remove_var(&variable.name)
+            }
+            match variable.attributes {
+                Some(attributes) => self.attributes.insert(variable.name, attributes),
+                None => self.attributes.remove(&variable.name)
+            };
+        }
+    }
+
+    // `local name`: the variable starts unset and the previous one comes back with pop_scope
+    pub fn make_local(&mut self, name: &str) -> Result<(), String> {
+        let Some(scope) = self.scopes.last_mut() else {
+            return Err("can only be used in a function".to_string());
+        };
+        if scope.iter().any(|variable| variable.name == name) {
+            return Ok(());
+        }
+        scope.push(SavedVariable {
+            name: name.to_string(),
+            value: self.values.remove(name),
+            env: env_value(name),
+            attributes: self.attributes.remove(name)
+        });
+        env::remove_var(name);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        match self.values.get(name) {
+            Some(Value::Scalar(value)) => Some(value.clone()),
+            // Like bash, an array referenced without subscript is its first element
+            Some(Value::Indexed(values)) => values.get(&0).cloned(),
+            Some(Value::Associative(values)) => values.get("0").cloned(),
+            None => env_value(name)
+        }
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => env_value(name).map(Value::Scalar)
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.check_writable(name)?;
+        let value = self.convert(name, None, value, false)?;
+        self.store(name, value);
+        Ok(())
+    }
+
+    // Stores a scalar value that was already checked and converted
+    fn store(&mut self, name: &str, value: String) {
+        match self.values.get_mut(name) {
+            Some(Value::Indexed(values)) => {
+                values.insert(0, value);
+            },
+            Some(Value::Associative(values)) => {
+                values.insert("0".to_string(), value);
+            },
+            _ => {
+                if self.get_attributes(name).export {
+                    env::set_var(name, word_to_os(&value));
+                } else {
+                    self.values.insert(name.to_string(), Value::Scalar(value));
+                }
+            }
+        }
+    }
+
+    pub fn set_array(&mut self, name: &str, values: Vec<String>) -> Result<(), String> {
+        self.check_writable(name)?;
+        let mut converted = BTreeMap::new();
+        for (index, value) in values.into_iter().enumerate() {
+            converted.insert(index, self.convert(name, None, &value, false)?);
+        }
+        self.set_value(name, Value::Indexed(converted));
+        Ok(())
+    }
+
+    // Arrays cannot be exported, so the variable leaves the environment
+    fn set_value(&mut self, name: &str, value: Value) {
+        env::remove_var(name);
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn unset(&mut self, name: &str) -> Result<(), String> {
+        if self.attributes.get(name).is_some_and(|attributes| attributes.readonly) {
+            return Err(format!("{}: cannot unset: readonly variable", name));
+        }
+        self.values.remove(name);
+        self.attributes.remove(name);
+        env::remove_var(name);
+        Ok(())
+    }
+
+    // `declare -a`: a scalar becomes element 0, an existing array is kept
+    pub fn declare_indexed(&mut self, name: &str) -> Result<(), String> {
+        match self.get_value(name) {
+            Some(Value::Indexed(_)) => (),
+            Some(Value::Associative(_)) => return Err(format!("{}: cannot convert associative to indexed array", name)),
+            Some(Value::Scalar(value)) => {
+                self.check_writable(name)?;
+                self.set_value(name, Value::Indexed(BTreeMap::from([(0, value)])));
+            },
+            None => self.set_value(name, Value::Indexed(BTreeMap::new()))
+        }
+        Ok(())
+    }
+
+    // `declare -A`: like bash, a scalar becomes the element with key 0
+    pub fn declare_associative(&mut self, name: &str) -> Result<(), String> {
+        self.check_writable(name)?;
+        match self.get_value(name) {
+            Some(Value::Associative(_)) => (),
+            Some(Value::Indexed(values)) if !values.is_empty() => {
+                return Err(format!("{}: cannot convert indexed to associative array", name));
+            },
+            Some(Value::Scalar(value)) => self.set_value(name, Value::Associative(BTreeMap::from([("0".to_string(), value)]))),
+            _ => self.set_value(name, Value::Associative(BTreeMap::new()))
+        }
+        Ok(())
+    }
+
+    // Indexed array subscripts are arithmetic expressions: `a[i+1]`
+    pub fn eval_index(&self, subscript: &str) -> Option<i64> {
+        eval_arithmetic(subscript, self).ok()
+    }
+
+    // Negative subscripts count back from the end of the array
+    fn resolve_index(&self, values: &BTreeMap<usize, String>, subscript: &str) -> Option<usize> {
+        let index = self.eval_index(subscript)?;
+        if index >= 0 {
+            return Some(index as usize);
+        }
+        let end = values.keys().next_back().map(|last| *last as i64 + 1).unwrap_or(0);
+        if end + index >= 0 { Some((end + index) as usize) } else { None }
+    }
+
+    pub fn get_element(&self, name: &str, subscript: &str) -> Option<String> {
+        match self.get_value(name)? {
+            Value::Indexed(values) => values.get(&self.resolve_index(&values, subscript)?).cloned(),
+            Value::Associative(values) => values.get(subscript).cloned(),
+            Value::Scalar(value) => if self.eval_index(subscript)? == 0 { Some(value) } else { None }
+        }
+    }
+
+    pub fn set_element(&mut self, name: &str, subscript: &str, value: &str, append: bool) -> Result<(), String> {
+        self.check_writable(name)?;
+        let mut current = self.get_value(name);
+        if let Some(Value::Scalar(scalar)) = current {
+            current = Some(Value::Indexed(BTreeMap::from([(0, scalar)])));
+        }
+        match current {
+            Some(Value::Associative(mut values)) => {
+                let value = self.convert(name, values.get(subscript).cloned(), value, append)?;
+                values.insert(subscript.to_string(), value);
+                self.set_value(name, Value::Associative(values));
+            },
+            current => {
+                let mut values = match current {
+                    Some(Value::Indexed(values)) => values,
+                    _ => BTreeMap::new()
+                };
+                let Some(index) = self.resolve_index(&values, subscript) else {
+                    return Err(format!("{}[{}]: bad array subscript", name, subscript));
+                };
+                let value = self.convert(name, values.get(&index).cloned(), value, append)?;
+                values.insert(index, value);
+                self.set_value(name, Value::Indexed(values));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unset_element(&mut self, name: &str, subscript: &str) -> Result<(), String> {
+        if self.attributes.get(name).is_some_and(|attributes| attributes.readonly) {
+            return Err(format!("{}: cannot unset: readonly variable", name));
+        }
+        match self.get_value(name) {
+            Some(Value::Indexed(mut values)) => {
+                let Some(index) = self.resolve_index(&values, subscript) else {
+                    return Err(format!("{}[{}]: bad array subscript", name, subscript));
+                };
+                values.remove(&index);
+                self.set_value(name, Value::Indexed(values));
+            },
+            Some(Value::Associative(mut values)) => {
+                values.remove(subscript);
+                self.set_value(name, Value::Associative(values));
+            },
+            Some(Value::Scalar(_)) if self.eval_index(subscript) == Some(0) => self.unset(name)?,
+            _ => ()
+        }
+        Ok(())
+    }
+
+    // `name=(a b c)` replaces the array, `name+=(d e)` adds after its last element
+    fn assign_array(&mut self, name: &str, elements: Vec<String>, append: bool) -> Result<(), String> {
+        let current = self.get_value(name);
+        if let Some(Value::Associative(existing)) = current {
+            let mut values = if append { existing } else { BTreeMap::new() };
+            for element in elements {
+                let Some((key, value)) = split_element(&element) else {
+                    return Err(format!("{}: {}: must use subscript when assigning associative array", name, element));
+                };
+                let value = self.convert(name, None, value, false)?;
+                values.insert(key.to_string(), value);
+            }
+            self.set_value(name, Value::Associative(values));
+            return Ok(());
+        }
+        let mut values = match current {
+            Some(Value::Indexed(existing)) if append => existing,
+            Some(Value::Scalar(scalar)) if append => BTreeMap::from([(0, scalar)]),
+            _ => BTreeMap::new()
+        };
+        let mut next = values.keys().next_back().map(|last| last + 1).unwrap_or(0);
+        for element in elements {
+            match split_element(&element) {
+                Some((key, value)) => {
+                    let Some(index) = self.resolve_index(&values, key) else {
+                        return Err(format!("{}[{}]: bad array subscript", name, key));
+                    };
+                    values.insert(index, self.convert(name, None, value, false)?);
+                    next = index + 1;
+                },
+                None => {
+                    values.insert(next, self.convert(name, None, &element, false)?);
+                    next += 1;
+                }
+            }
+        }
+        self.set_value(name, Value::Indexed(values));
+        Ok(())
+    }
+
+    pub fn assign(&mut self, assignment: &Assignment) -> Result<(), String> {
+        self.check_writable(&assignment.name)?;
+        if let Some(elements) = &assignment.elements {
+            if assignment.subscript.is_some() {
+                return Err(format!("{}: cannot assign list to array member", assignment.name));
+            }
+            return self.assign_array(&assignment.name, elements.clone(), assignment.append);
+        }
+        match &assignment.subscript {
+            Some(subscript) => return self.set_element(&assignment.name, subscript, &assignment.value, assignment.append),
+            None => {
+                let previous = self.get(&assignment.name);
+                let value = self.convert(&assignment.name, previous, &assignment.value, assignment.append)?;
+                self.store(&assignment.name, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assign(variables: &mut Variables, word: &str, elements: Option<&[&str]>) -> Result<(), String> {
+        let mut assignment = parse_assignment(word).unwrap();
+        assignment.elements = elements.map(|elements| elements.iter().map(|element| element.to_string()).collect());
+        variables.assign(&assignment)
+    }
+
+    #[test]
+    fn parses_assignment_forms() {
+        let assignment = parse_assignment("a[i+1]+=x=y").unwrap();
+        assert_eq!(assignment.name, "a");
+        assert_eq!(assignment.subscript.as_deref(), Some("i+1"));
+        assert!(assignment.append);
+        assert_eq!(assignment.value, "x=y");
+        assert!(parse_assignment("1a=x").is_none());
+        assert!(parse_assignment("a[0=x").is_none());
+    }
+
+    #[test]
+    fn indexed_array_elements() {
+        let mut variables = Variables::new();
+        assign(&mut variables, "lsh_test_a=", Some(&["a", "b", "[5]=f", "g"])).unwrap();
+        assert_eq!(variables.get_element("lsh_test_a", "1"), Some("b".to_string()));
+        assert_eq!(variables.get_element("lsh_test_a", "6"), Some("g".to_string()));
+        assert_eq!(variables.get_element("lsh_test_a", "-1"), Some("g".to_string()));
+        assert_eq!(variables.get_element("lsh_test_a", "2"), None);
+        assert_eq!(variables.get("lsh_test_a"), Some("a".to_string()));
+        assign(&mut variables, "lsh_test_a+=", Some(&["h"])).unwrap();
+        assert_eq!(variables.get_element("lsh_test_a", "7"), Some("h".to_string()));
+        variables.unset_element("lsh_test_a", "0").unwrap();
+        assert_eq!(variables.get("lsh_test_a"), None);
+    }
+
+    #[test]
+    fn element_assignment_turns_a_scalar_into_an_array() {
+        let mut variables = Variables::new();
+        assign(&mut variables, "lsh_test_s=zero", None).unwrap();
+        assign(&mut variables, "lsh_test_s[2]=two", None).unwrap();
+        assert_eq!(variables.get_element("lsh_test_s", "0"), Some("zero".to_string()));
+        assert_eq!(variables.get_element("lsh_test_s", "1+1"), Some("two".to_string()));
+        assign(&mut variables, "lsh_test_s[2]+=!", None).unwrap();
+        assert_eq!(variables.get_element("lsh_test_s", "2"), Some("two!".to_string()));
+    }
+
+    #[test]
+    fn associative_array_elements() {
+        let mut variables = Variables::new();
+        variables.declare_associative("lsh_test_h").unwrap();
+        assign(&mut variables, "lsh_test_h=", Some(&["[k]=v", "[a b]=c"])).unwrap();
+        assert_eq!(variables.get_element("lsh_test_h", "a b"), Some("c".to_string()));
+        assign(&mut variables, "lsh_test_h[k]=w", None).unwrap();
+        assert_eq!(variables.get_element("lsh_test_h", "k"), Some("w".to_string()));
+        assert!(assign(&mut variables, "lsh_test_h=", Some(&["v"])).is_err());
+    }
+
+    #[test]
+    fn local_variables_are_dropped_with_their_scope() {
+        let mut variables = Variables::new();
+        assert!(variables.make_local("lsh_test_l").is_err());
+        variables.set("lsh_test_l", "outer").unwrap();
+        variables.push_scope();
+        variables.make_local("lsh_test_l").unwrap();
+        assert_eq!(variables.get("lsh_test_l"), None);
+        variables.set("lsh_test_l", "inner").unwrap();
+        variables.set("lsh_test_g", "global").unwrap();
+        variables.pop_scope();
+        assert_eq!(variables.get("lsh_test_l"), Some("outer".to_string()));
+        assert_eq!(variables.get("lsh_test_g"), Some("global".to_string()));
+    }
+
+    #[test]
+    fn attributes_convert_values() {
+        let mut variables = Variables::new();
+        variables.set("lsh_test_i", "2+3").unwrap();
+        variables.set_attributes("lsh_test_i", Attributes { integer: true, ..Attributes::default() }).unwrap();
+        assert_eq!(variables.get("lsh_test_i"), Some("5".to_string()));
+        assign(&mut variables, "lsh_test_i+=4*2", None).unwrap();
+        assert_eq!(variables.get("lsh_test_i"), Some("13".to_string()));
+        variables.set_attributes("lsh_test_u", Attributes { uppercase: true, readonly: true, ..Attributes::default() }).unwrap();
+        assert!(variables.set("lsh_test_u", "x").is_err());
+        variables.set_attributes("lsh_test_c", Attributes { lowercase: true, ..Attributes::default() }).unwrap();
+        variables.set("lsh_test_c", "MiXed").unwrap();
+        assert_eq!(variables.get("lsh_test_c"), Some("mixed".to_string()));
+    }
+}
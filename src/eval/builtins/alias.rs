@@ -0,0 +1,58 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Alias;
+
+pub fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {}='{}'", name, value.replace('\'', "'\\''"))
+}
+
+impl Builtin for Alias {
+    fn name(&self) -> &str {
+        "alias"
+    }
+
+    fn help(&self) -> &str {
+        "Define or display aliases. NAME=VALUE defines an alias, NAME alone prints it. Without arguments, prints the list of aliases."
+    }
+
+    fn usage(&self) -> &str {
+        "alias [name[=value] ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            let mut names: Vec<&String> = state.aliases.keys().collect();
+            names.sort();
+            for name in names {
+                stdout.queue(Print(format!("{}\n", format_alias(name, &state.aliases[name])))).unwrap();
+            }
+            return Ok(0);
+        }
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("", _)) => {
+                    return Err(ShellError::Builtin(BuiltinError::new(1, format!("alias: `{}': invalid alias name", arg))));
+                },
+                Some((name, value)) => {
+                    state.aliases.insert(name.to_string(), value.to_string());
+                },
+                None => match state.aliases.get(arg) {
+                    Some(value) => {
+                        stdout.queue(Print(format!("{}\n", format_alias(arg, value)))).unwrap();
+                    },
+                    None => {
+                        return Err(ShellError::Builtin(BuiltinError::new(1, format!("alias: {}: not found", arg))));
+                    }
+                }
+            }
+        }
+        Ok(0)
+    }
+}
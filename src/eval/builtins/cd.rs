@@ -0,0 +1,129 @@
+use std::env;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::{io_error_message, ShellError};
+use crate::core::osstr::{os_to_word, word_to_os};
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+
+pub struct Cd;
+
+// Resolves `.` and `..` components without following symlinks
+pub fn normalize_logical(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            other => normalized.push(other.as_os_str())
+        }
+    }
+    normalized
+}
+
+pub fn get_logical_pwd() -> PathBuf {
+    if let Some(pwd) = env::var_os("PWD") {
+        // Only trust PWD when it still designates the current directory
+        let pwd = PathBuf::from(pwd);
+        if let (Ok(pwd_canonical), Ok(cwd)) = (pwd.canonicalize(), env::current_dir()) {
+            if pwd.is_absolute() && pwd_canonical == cwd {
+                return pwd;
+            }
+        }
+    }
+    env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))
+}
+
+// Changes directory, updating PWD and OLDPWD. With `physical`, symlinks are resolved in PWD.
+pub fn change_directory(target: &str, physical: bool) -> Result<(), BuiltinError> {
+    let oldpwd = get_logical_pwd();
+    let target_path = PathBuf::from(word_to_os(target));
+    let requested = if target_path.is_absolute() { target_path } else { oldpwd.join(target_path) };
+    let mut newpwd = if physical { requested.clone() } else { normalize_logical(&requested) };
+    if let Err(error) = env::set_current_dir(&newpwd) {
+        // The logical path may not exist when `..` crosses a symlink, try the physical one
+        if physical || env::set_current_dir(&requested).is_err() {
+            return Err(BuiltinError::new(1, format!("{}: {}", target, io_error_message(&error))));
+        }
+        newpwd = requested;
+    }
+    if physical {
+        newpwd = env::current_dir().unwrap_or(newpwd);
+    }
+    env::set_var("OLDPWD", oldpwd);
+    env::set_var("PWD", newpwd);
+    Ok(())
+}
+
+// Looks for a relative directory in each CDPATH entry. Returns the directory
+// and whether it was found through a non-empty entry, which makes cd print it.
+fn search_cdpath(state: &ShellState, target: &str) -> Option<(String, bool)> {
+    let first = Path::new(target).components().next();
+    if Path::new(target).is_absolute() || matches!(first, Some(Component::CurDir) | Some(Component::ParentDir)) {
+        return None;
+    }
+    let cdpath = state.variables.get("CDPATH")?;
+    for entry in cdpath.split(':') {
+        let candidate = Path::new(if entry.is_empty() { "." } else { entry }).join(target);
+        if candidate.is_dir() {
+            return Some((os_to_word(candidate.as_os_str()), !entry.is_empty()));
+        }
+    }
+    None
+}
+
+impl Builtin for Cd {
+    fn name(&self) -> &str {
+        "cd"
+    }
+
+    fn help(&self) -> &str {
+        "Change the current directory to DIR. Without arguments, changes to $HOME; `cd -` goes back to $OLDPWD and prints it. Relative directories are also searched in the colon separated $CDPATH. -L (default) keeps symbolic links in $PWD, -P resolves them."
+    }
+
+    fn usage(&self) -> &str {
+        "cd [-L|-P] [dir]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("cd", args, "LP", "")?;
+        let physical = options.options.last().is_some_and(|(flag, _)| *flag == 'P');
+        let mut print_directory = false;
+        let target = match options.operands.len() {
+            0 => match state.variables.get("HOME") {
+                Some(home) => home,
+                None => return Err(ShellError::Builtin(BuiltinError::new(1, "cd: HOME not set".to_string())))
+            },
+            1 if options.operands[0] == "-" => match state.variables.get("OLDPWD") {
+                Some(oldpwd) => {
+                    print_directory = true;
+                    oldpwd
+                },
+                None => return Err(ShellError::Builtin(BuiltinError::new(1, "cd: OLDPWD not set".to_string())))
+            },
+            1 => match search_cdpath(state, &options.operands[0]) {
+                Some((directory, found_in_cdpath)) => {
+                    print_directory = found_in_cdpath;
+                    directory
+                },
+                None => options.operands[0].clone()
+            },
+            _ => {
+                return Err(ShellError::Builtin(BuiltinError::new(1, "cd: too many arguments".to_string())));
+            }
+        };
+        if let Err(error) = change_directory(&target, physical) {
+            return Err(ShellError::Builtin(BuiltinError::new(error.status, format!("cd: {}", error.message))));
+        }
+        if print_directory {
+            stdout.queue(Print(format!("{}\n", get_logical_pwd().display()))).unwrap();
+        }
+        Ok(0)
+    }
+}
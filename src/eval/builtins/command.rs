@@ -0,0 +1,76 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::osstr::os_to_word;
+use crate::core::pathcache::search_path;
+use crate::eval::builtins::alias::format_alias;
+use crate::eval::builtins::r#type::describe_command;
+use crate::eval::builtins::{parse_options, Builtin};
+use crate::eval::eval::run_program;
+use crate::eval::execute::{execute_program, ExecutionError};
+use crate::eval::resolve::{classify_command, CommandKind};
+
+// Used by -p, guaranteed to find the standard utilities
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin:/usr/sbin:/sbin";
+
+pub struct Command;
+
+impl Builtin for Command {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn help(&self) -> &str {
+        "Run COMMAND with ARGS ignoring aliases and functions, so that only builtins and programs from PATH are run. -p searches a default PATH instead. -v prints the word or path that would be used and -V a verbose description, like `type`."
+    }
+
+    fn usage(&self) -> &str {
+        "command [-pVv] command [arg ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("command", args, "pVv", "")?;
+        let Some(program) = options.operands.first() else {
+            return Ok(0);
+        };
+        if options.has('v') || options.has('V') {
+            let mut status = 0;
+            for name in &options.operands {
+                let Some(kind) = classify_command(state, name, false, false, false).into_iter().next() else {
+                    if options.has('V') {
+                        stderr.queue(Print(format!("command: {}: not found\n", name))).unwrap();
+                    }
+                    status = 1;
+                    continue;
+                };
+                let line = if options.has('V') {
+                    describe_command(state, name, &kind)
+                } else {
+                    match kind {
+                        CommandKind::Alias(value) => format_alias(name, &value),
+                        CommandKind::File(path) => path.display().to_string(),
+                        _ => name.clone()
+                    }
+                };
+                stdout.queue(Print(format!("{}\n", line))).unwrap();
+            }
+            return Ok(status);
+        }
+        let program_args = options.operands[1..].to_vec();
+        let output = if options.has('p') && !program.contains('/') && !state.builtins.is_enabled(program) {
+            match search_path(program, DEFAULT_PATH, false).into_iter().next() {
+                Some(path) => execute_program(&os_to_word(path.as_os_str()), program, &program_args, stdin)?,
+                None => return Err(ShellError::Execution(ExecutionError::CommandNotFound(program.clone(), None)))
+            }
+        } else {
+            run_program(state, program, &program_args, stdin)?
+        };
+        stdout.write_all(&output.stdout).unwrap();
+        stderr.write_all(&output.stderr).unwrap();
+        Ok(output.status.unwrap_or(0))
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::variables::{parse_assignment, Assignment, Attributes, Value};
+use crate::eval::builtins::{Builtin, BuiltinError};
+use crate::parser::tokenizer::is_name;
+
+// Attribute options of a declare-like command: `-x` adds an attribute, `+x` removes it
+#[derive(Default)]
+pub struct Declaration {
+    pub add: Attributes,
+    pub remove: Attributes,
+    pub indexed: bool,
+    pub associative: bool,
+    pub local: bool
+}
+
+impl Declaration {
+    fn apply(&self, mut attributes: Attributes) -> Attributes {
+        attributes.integer = (attributes.integer || self.add.integer) && !self.remove.integer;
+        attributes.readonly = attributes.readonly || self.add.readonly;
+        attributes.export = (attributes.export || self.add.export) && !self.remove.export;
+        // -l and -u exclude each other, the last one given wins
+        if self.add.lowercase {
+            attributes.lowercase = true;
+            attributes.uppercase = false;
+        } else if self.add.uppercase {
+            attributes.uppercase = true;
+            attributes.lowercase = false;
+        }
+        attributes.lowercase &= !self.remove.lowercase;
+        attributes.uppercase &= !self.remove.uppercase;
+        attributes
+    }
+
+    // Whether a variable has every attribute selected by the options, used to filter listings
+    fn matches(&self, state: &ShellState, name: &str) -> bool {
+        let attributes = state.variables.get_attributes(name);
+        let value = state.variables.get_value(name);
+        (!self.add.integer || attributes.integer)
+            && (!self.add.lowercase || attributes.lowercase)
+            && (!self.add.uppercase || attributes.uppercase)
+            && (!self.add.readonly || attributes.readonly)
+            && (!self.add.export || attributes.export)
+            && (!self.indexed || matches!(value, Some(Value::Indexed(_))))
+            && (!self.associative || matches!(value, Some(Value::Associative(_))))
+    }
+}
+
+// `"value"` with the characters special within double quotes escaped
+pub fn quote_value(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if "\"\\$`".contains(c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// `declare -ax name=([0]="a" [1]="b")`, which recreates the variable when evaluated
+pub fn format_declaration(state: &ShellState, name: &str) -> Option<String> {
+    let attributes = state.variables.get_attributes(name);
+    let value = state.variables.get_value(name);
+    if value.is_none() && attributes == Attributes::default() {
+        return None;
+    }
+    let mut flags = String::new();
+    match value {
+        Some(Value::Indexed(_)) => flags.push('a'),
+        Some(Value::Associative(_)) => flags.push('A'),
+        _ => ()
+    }
+    for (set, flag) in [(attributes.integer, 'i'), (attributes.lowercase, 'l'), (attributes.readonly, 'r'), (attributes.uppercase, 'u'), (attributes.export, 'x')] {
+        if set {
+            flags.push(flag);
+        }
+    }
+    let flags = if flags.is_empty() { "--".to_string() } else { format!("-{}", flags) };
+    let value = match value {
+        Some(Value::Scalar(value)) => format!("={}", quote_value(&value)),
+        Some(Value::Indexed(values)) => {
+            let elements: Vec<String> = values.iter().map(|(index, value)| format!("[{}]={}", index, quote_value(value))).collect();
+            format!("=({})", elements.join(" "))
+        },
+        Some(Value::Associative(values)) => {
+            let elements: Vec<String> = values.iter().map(|(key, value)| format!("[{}]={}", key, quote_value(value))).collect();
+            format!("=({})", elements.join(" "))
+        },
+        None => String::new()
+    };
+    Some(format!("declare {} {}{}", flags, name, value))
+}
+
+// Declares one `name`, `name=value` or `name=(...)` operand with the given attributes
+pub fn declare_variable(state: &mut ShellState, operand: &str, elements: Option<Vec<String>>, declaration: &Declaration) -> Result<(), String> {
+    let assignment = parse_assignment(operand).map(|assignment| Assignment { elements, ..assignment });
+    let name = match &assignment {
+        Some(assignment) => assignment.name.clone(),
+        None => operand.to_string()
+    };
+    if !is_name(&name) {
+        return Err(format!("`{}': not a valid identifier", operand));
+    }
+    if declaration.local {
+        state.variables.make_local(&name)?;
+    }
+    if declaration.associative {
+        state.variables.declare_associative(&name)?;
+    } else if declaration.indexed {
+        state.variables.declare_indexed(&name)?;
+    }
+    // Readonly is set last so that `declare -r name=value` can still assign
+    let attributes = declaration.apply(state.variables.get_attributes(&name));
+    state.variables.set_attributes(&name, Attributes { readonly: state.variables.get_attributes(&name).readonly, ..attributes.clone() })?;
+    if let Some(assignment) = assignment {
+        state.assign(&assignment)?;
+    }
+    if attributes.readonly {
+        state.variables.set_attributes(&name, attributes)?;
+    }
+    Ok(())
+}
+
+// Splits `-aix +r` style options from the operands
+fn parse_declaration(command: &str, args: &[String], flags: &str) -> Result<(Declaration, bool, bool, Vec<String>), ShellError> {
+    let mut declaration = Declaration::default();
+    let mut print = false;
+    let mut global = false;
+    let mut index = 0;
+    while index < args.len() {
+        let arg = &args[index];
+        if arg == "--" {
+            index += 1;
+            break;
+        }
+        if arg.len() < 2 || !arg.starts_with(['-', '+']) {
+            break;
+        }
+        let adding = arg.starts_with('-');
+        for flag in arg[1..].chars() {
+            if !flags.contains(flag) {
+                return Err(ShellError::Builtin(BuiltinError::new(2, format!("{}: {}{}: invalid option", command, &arg[..1], flag))));
+            }
+            let attributes = if adding { &mut declaration.add } else { &mut declaration.remove };
+            match flag {
+                'a' => declaration.indexed = adding,
+                'A' => declaration.associative = adding,
+                'i' => attributes.integer = true,
+                'l' => attributes.lowercase = true,
+                'u' => attributes.uppercase = true,
+                'r' => attributes.readonly = true,
+                'x' => attributes.export = true,
+                'p' => print = true,
+                'g' => global = true,
+                _ => ()
+            }
+        }
+        index += 1;
+    }
+    if declaration.remove.readonly {
+        return Err(ShellError::Builtin(BuiltinError::new(1, format!("{}: +r: cannot remove the readonly attribute", command))));
+    }
+    Ok((declaration, print, global, args[index..].to_vec()))
+}
+
+// Prints the declarations of the given names, or of every variable matching the options
+pub fn print_declarations(state: &ShellState, command: &str, names: &[String], declaration: &Declaration, stdout: &mut dyn Write, stderr: &mut dyn Write) -> i32 {
+    let mut status = 0;
+    if names.is_empty() {
+        for name in state.variables.names().iter().filter(|name| declaration.matches(state, name)) {
+            if let Some(line) = format_declaration(state, name) {
+                stdout.queue(Print(format!("{}\n", line))).unwrap();
+            }
+        }
+        return status;
+    }
+    for name in names {
+        match format_declaration(state, name) {
+            Some(line) => {
+                stdout.queue(Print(format!("{}\n", line))).unwrap();
+            },
+            None => {
+                stderr.queue(Print(format!("{}: {}: not found\n", command, name))).unwrap();
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+// declare, typeset, local and readonly
+pub struct Declare {
+    pub name: &'static str
+}
+
+impl Builtin for Declare {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        match self.name {
+            "local" => "Create variables visible only to the current function and the commands it calls. Options are the same as declare.",
+            "readonly" => "Mark each NAME as readonly, optionally assigning it first. Readonly variables cannot be assigned or unset. Without NAME or with -p, lists the readonly variables.",
+            _ => "Declare variables and give them attributes. -a makes NAME an indexed array and -A an associative array (NAME=([key]=value ...)), -i evaluates assignments as integer expressions, -l and -u convert values to lower or upper case, -r makes NAME readonly and -x exports it. +OPTION removes the attribute. -p prints reusable declarations. Within a function, variables are local unless -g is given."
+        }
+    }
+
+    fn usage(&self) -> &str {
+        match self.name {
+            "local" => "local [-aAilux] [name[=value] ...]",
+            "readonly" => "readonly [-aAp] [name[=value] ...]",
+            "typeset" => "typeset [-aAgilprux] [name[=value] ...]",
+            _ => "declare [-aAgilprux] [name[=value] ...]"
+        }
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let flags = match self.name {
+            "local" => "aAilux",
+            "readonly" => "aAp",
+            _ => "aAgilprux"
+        };
+        let (mut declaration, print, global, operands) = parse_declaration(self.name, args, flags)?;
+        if self.name == "readonly" {
+            declaration.add.readonly = true;
+        }
+        // Like bash, declare within a function creates local variables
+        declaration.local = self.name == "local" || (self.name != "readonly" && !global && state.variables.in_scope());
+        if print || operands.is_empty() {
+            return Ok(print_declarations(state, self.name, &operands, &declaration, stdout, stderr));
+        }
+        let arrays = std::mem::take(&mut state.array_arguments);
+        let offset = args.len() - operands.len();
+        let mut status = 0;
+        for (index, operand) in operands.iter().enumerate() {
+            if let Err(message) = declare_variable(state, operand, arrays.get(&(offset + index)).cloned(), &declaration) {
+                stderr.queue(Print(format!("{}: {}\n", self.name, message))).unwrap();
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,206 @@
+use std::io::Write;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::osstr::{os_to_word, word_to_bytes};
+use crate::eval::builtins::cd::{change_directory, get_logical_pwd};
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+
+// The full stack as shown by `dirs`: the current directory followed by the saved ones
+pub fn get_stack(state: &ShellState) -> Vec<String> {
+    let mut stack = vec![os_to_word(get_logical_pwd().as_os_str())];
+    stack.extend(state.dirstack.iter().cloned());
+    stack
+}
+
+// `+N` counts from the left of the `dirs` listing, `-N` from the right
+pub fn stack_index(arg: &str, len: usize) -> Option<usize> {
+    let (from_right, digits) = match arg.chars().next() {
+        Some('+') => (false, &arg[1..]),
+        Some('-') => (true, &arg[1..]),
+        _ => return None
+    };
+    let n: usize = digits.parse().ok()?;
+    if n >= len {
+        return None;
+    }
+    Some(if from_right { len - 1 - n } else { n })
+}
+
+fn is_stack_index(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with(['+', '-']) && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn with_tilde(state: &ShellState, path: &str) -> String {
+    if let Some(home) = state.variables.get("HOME").filter(|home| !home.is_empty()) {
+        if path == home {
+            return "~".to_string();
+        } else if let Some(rest) = path.strip_prefix(&format!("{}/", home.trim_end_matches('/'))) {
+            return format!("~/{}", rest);
+        }
+    }
+    path.to_string()
+}
+
+fn print_stack(state: &ShellState, stdout: &mut dyn Write) {
+    let stack: Vec<String> = get_stack(state).iter().map(|dir| with_tilde(state, dir)).collect();
+    stdout.write_all(&word_to_bytes(&format!("{}\n", stack.join(" ")))).unwrap();
+}
+
+fn stack_error(name: &str, message: &str) -> ShellError {
+    ShellError::Builtin(BuiltinError::new(1, format!("{}: {}", name, message)))
+}
+
+// Makes stack[0] the current directory and keeps the rest as the saved stack
+fn switch_to(state: &mut ShellState, name: &str, mut stack: Vec<String>) -> Result<(), ShellError> {
+    if let Err(error) = change_directory(&stack[0], false) {
+        return Err(stack_error(name, &error.message));
+    }
+    stack.remove(0);
+    state.dirstack = stack;
+    Ok(())
+}
+
+pub struct Pushd;
+
+impl Builtin for Pushd {
+    fn name(&self) -> &str {
+        "pushd"
+    }
+
+    fn help(&self) -> &str {
+        "Save the current directory on the directory stack and change to DIR. Without arguments, exchanges the top two directories. +N and -N rotate the stack so that the Nth directory, counted from the left or right of `dirs`, is on top. -n manipulates the stack without changing directory."
+    }
+
+    fn usage(&self) -> &str {
+        "pushd [-n] [+N | -N | dir]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let no_cd = args.iter().any(|arg| arg == "-n");
+        let operands: Vec<&String> = args.iter().filter(|arg| *arg != "-n").collect();
+        let mut stack = get_stack(state);
+        match operands.first() {
+            None => {
+                if stack.len() < 2 {
+                    return Err(stack_error("pushd", "no other directory"));
+                }
+                stack.swap(0, 1);
+                if no_cd {
+                    state.dirstack = stack[1..].to_vec();
+                } else {
+                    switch_to(state, "pushd", stack)?;
+                }
+            },
+            Some(arg) if is_stack_index(arg) => {
+                let Some(index) = stack_index(arg, stack.len()) else {
+                    return Err(stack_error("pushd", &format!("{}: directory stack index out of range", arg)));
+                };
+                stack.rotate_left(index);
+                switch_to(state, "pushd", stack)?;
+            },
+            Some(dir) => {
+                if no_cd {
+                    state.dirstack.insert(0, dir.to_string());
+                } else {
+                    if let Err(error) = change_directory(dir, false) {
+                        return Err(stack_error("pushd", &error.message));
+                    }
+                    state.dirstack.insert(0, stack.remove(0));
+                }
+            }
+        }
+        print_stack(state, stdout);
+        Ok(0)
+    }
+}
+
+pub struct Popd;
+
+impl Builtin for Popd {
+    fn name(&self) -> &str {
+        "popd"
+    }
+
+    fn help(&self) -> &str {
+        "Remove the top directory from the directory stack and change to the new top directory. +N and -N remove the Nth directory, counted from the left or right of `dirs`. -n removes the entry below the top without changing directory."
+    }
+
+    fn usage(&self) -> &str {
+        "popd [-n] [+N | -N]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let no_cd = args.iter().any(|arg| arg == "-n");
+        let operands: Vec<&String> = args.iter().filter(|arg| *arg != "-n").collect();
+        let mut stack = get_stack(state);
+        if stack.len() < 2 {
+            return Err(stack_error("popd", "directory stack empty"));
+        }
+        let index = match operands.first() {
+            None => if no_cd { 1 } else { 0 },
+            Some(arg) => match stack_index(arg, stack.len()) {
+                Some(index) => index,
+                None => return Err(stack_error("popd", &format!("{}: directory stack index out of range", arg)))
+            }
+        };
+        stack.remove(index);
+        if index == 0 {
+            switch_to(state, "popd", stack)?;
+        } else {
+            state.dirstack = stack[1..].to_vec();
+        }
+        print_stack(state, stdout);
+        Ok(0)
+    }
+}
+
+pub struct Dirs;
+
+impl Builtin for Dirs {
+    fn name(&self) -> &str {
+        "dirs"
+    }
+
+    fn help(&self) -> &str {
+        "Display the directory stack, most recent first. -c clears it, -l prints full paths instead of using ~, -p prints one entry per line and -v also shows the position of each entry. +N and -N show only the Nth entry."
+    }
+
+    fn usage(&self) -> &str {
+        "dirs [-clpv] [+N | -N]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let index_arg = args.iter().find(|arg| is_stack_index(arg));
+        let option_args: Vec<String> = args.iter().filter(|arg| !is_stack_index(arg)).cloned().collect();
+        let options = parse_options("dirs", &option_args, "clpv", "")?;
+        if options.has('c') {
+            state.dirstack.clear();
+            return Ok(0);
+        }
+        let stack: Vec<String> = get_stack(state).iter()
+                                                 .map(|dir| if options.has('l') { dir.clone() } else { with_tilde(state, dir) })
+                                                 .collect();
+        if let Some(arg) = index_arg {
+            match stack_index(arg, stack.len()) {
+                Some(index) => {
+                    stdout.write_all(&word_to_bytes(&format!("{}\n", stack[index]))).unwrap();
+                    return Ok(0);
+                },
+                None => return Err(stack_error("dirs", &format!("{}: directory stack index out of range", arg)))
+            }
+        }
+        if options.has('v') {
+            for (index, dir) in stack.iter().enumerate() {
+                stdout.write_all(&word_to_bytes(&format!("{:2}  {}\n", index, dir))).unwrap();
+            }
+        } else if options.has('p') {
+            for dir in stack.iter() {
+                stdout.write_all(&word_to_bytes(&format!("{}\n", dir))).unwrap();
+            }
+        } else {
+            stdout.write_all(&word_to_bytes(&format!("{}\n", stack.join(" ")))).unwrap();
+        }
+        Ok(0)
+    }
+}
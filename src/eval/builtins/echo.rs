@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::osstr::{push_char_bytes, word_to_bytes};
+use crate::eval::builtins::Builtin;
+
+pub struct Echo;
+
+impl Builtin for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn help(&self) -> &str {
+        "Write arguments to the standard output, separated by spaces. -n omits the trailing newline, -e interprets backslash escapes and -E disables them."
+    }
+
+    fn usage(&self) -> &str {
+        "echo [-neE] [arg ...]"
+    }
+
+    fn run(&self, _state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let mut newline = true;
+        let mut escapes = false;
+        let mut first_word = 0;
+        // echo has no `--`: options stop at the first word that is not made of n, e, E
+        for arg in args {
+            if arg.len() < 2 || !arg.starts_with('-') || !arg[1..].chars().all(|c| "neE".contains(c)) {
+                break;
+            }
+            for flag in arg[1..].chars() {
+                match flag {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false
+                }
+            }
+            first_word += 1;
+        }
+        let text = args[first_word..].join(" ");
+        let mut output: Vec<u8> = Vec::new();
+        if escapes {
+            let (bytes, stop) = interpret_escapes(&text, true);
+            output.extend(bytes);
+            if stop {
+                newline = false;
+            }
+        } else {
+            output.extend(word_to_bytes(&text));
+        }
+        if newline {
+            output.push(b'\n');
+        }
+        stdout.write_all(&output).unwrap();
+        Ok(0)
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: usize, initial: Option<u32>) -> Option<u32> {
+    let mut value: Option<u32> = initial;
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            Some(digit) => {
+                value = Some(value.unwrap_or(0) * radix + digit);
+                chars.next();
+            },
+            None => break
+        }
+    }
+    value
+}
+
+// Expands backslash escapes as done by `echo -e`, `printf %b` (echo_octal: octal is `\0nnn`)
+// and printf formats (`\nnn`). Returns the bytes and whether `\c` asked to stop all output.
+pub fn interpret_escapes(input: &str, echo_octal: bool) -> (Vec<u8>, bool) {
+    let mut output: Vec<u8> = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            push_char_bytes(&mut output, c);
+            continue;
+        }
+        let Some(&escaped) = chars.peek() else {
+            output.push(b'\\');
+            break;
+        };
+        chars.next();
+        match escaped {
+            '\\' => output.push(b'\\'),
+            'a' => output.push(0x07),
+            'b' => output.push(0x08),
+            'e' | 'E' => output.push(0x1b),
+            'f' => output.push(0x0c),
+            'n' => output.push(b'\n'),
+            'r' => output.push(b'\r'),
+            't' => output.push(b'\t'),
+            'v' => output.push(0x0b),
+            '"' if !echo_octal => output.push(b'"'),
+            'c' => return (output, true),
+            '0'..='7' if !echo_octal || escaped == '0' => {
+                let value = if echo_octal {
+                    take_digits(&mut chars, 8, 3, None).unwrap_or(0)
+                } else {
+                    take_digits(&mut chars, 8, 2, escaped.to_digit(8)).unwrap_or(0)
+                };
+                output.push((value & 0xff) as u8);
+            },
+            'x' => match take_digits(&mut chars, 16, 2, None) {
+                Some(value) => output.push(value as u8),
+                None => output.extend_from_slice(b"\\x")
+            },
+            'u' | 'U' => match take_digits(&mut chars, 16, if escaped == 'u' { 4 } else { 8 }, None).and_then(char::from_u32) {
+                Some(unicode) => {
+                    let mut buffer = [0; 4];
+                    output.extend_from_slice(unicode.encode_utf8(&mut buffer).as_bytes());
+                },
+                None => {
+                    output.push(b'\\');
+                    output.push(escaped as u8);
+                }
+            },
+            other => {
+                output.push(b'\\');
+                let mut buffer = [0; 4];
+                output.extend_from_slice(other.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+    (output, false)
+}
@@ -0,0 +1,54 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Enable;
+
+impl Builtin for Enable {
+    fn name(&self) -> &str {
+        "enable"
+    }
+
+    fn help(&self) -> &str {
+        "Enable and disable builtin commands. With -n, each NAME is disabled; with -a, every builtin is listed with its status."
+    }
+
+    fn usage(&self) -> &str {
+        "enable [-a] [-n] [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let mut disable = false;
+        let mut all = false;
+        let mut names: Vec<&String> = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                "-n" => disable = true,
+                "-a" => all = true,
+                _ => names.push(arg)
+            }
+        }
+        if names.is_empty() {
+            for builtin in state.builtins.list() {
+                let enabled = state.builtins.is_enabled(builtin.name());
+                if enabled && !disable {
+                    stdout.queue(Print(format!("enable {}\n", builtin.name()))).unwrap();
+                } else if !enabled && (disable || all) {
+                    stdout.queue(Print(format!("enable -n {}\n", builtin.name()))).unwrap();
+                }
+            }
+            return Ok(0);
+        }
+        for name in names {
+            if !state.builtins.set_enabled(name, !disable) {
+                return Err(ShellError::Builtin(BuiltinError::new(1, format!("enable: {}: not a shell builtin", name))));
+            }
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,55 @@
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::process;
+
+use crossterm::style::Print;
+use crossterm::terminal;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::{io_error_message, ShellError};
+use crate::core::fsio::{flush_output, FSError};
+use crate::core::osstr::word_to_os;
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+use crate::eval::eval::find_program;
+
+pub struct Exec;
+
+impl Builtin for Exec {
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn help(&self) -> &str {
+        "Replace the shell with COMMAND. -a gives the name passed as argument zero and -c runs it with an empty environment. Without a command, the redirections take effect in the current shell."
+    }
+
+    fn usage(&self) -> &str {
+        "exec [-c] [-a name] [command [argument ...]]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("exec", args, "c", "a")?;
+        let Some(name) = options.operands.first() else {
+            return Ok(0);
+        };
+        let Some(path) = find_program(state, name) else {
+            return Err(ShellError::Builtin(BuiltinError::new(127, format!("exec: {}: not found", name))));
+        };
+        let mut command = process::Command::new(word_to_os(&path));
+        command.arg0(word_to_os(options.value('a').unwrap_or(name)))
+               .args(options.operands[1..].iter().map(|arg| word_to_os(arg)));
+        if options.has('c') {
+            command.env_clear();
+        }
+        // Nothing runs after a successful exec, so leave the terminal and history as on exit
+        flush_output(state);
+        if let Err(FSError::IOError { path, message, .. }) = state.history.persist() {
+            state.stderr.queue(Print(format!("exec: {}: {}\n", path, message))).unwrap();
+            state.stderr.flush().unwrap();
+        }
+        let _ = terminal::disable_raw_mode();
+        let error = command.exec();
+        Err(ShellError::Builtin(BuiltinError::new(126, format!("exec: {}: {}", name, io_error_message(&error)))))
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name(&self) -> &str {
+        "exit"
+    }
+
+    fn help(&self) -> &str {
+        "Exit the shell with a status of N. If N is omitted, the exit status is that of the last command executed."
+    }
+
+    fn usage(&self) -> &str {
+        "exit [n]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        match args.len() {
+            0 => Err(ShellError::ExitRequest(state.status)),
+            1 => match args[0].parse::<i64>() {
+                // Only the low byte reaches the parent, like the exit status of any process
+                Ok(code) => Err(ShellError::ExitRequest((code & 0xff) as i32)),
+                Err(_) => {
+                    // The shell exits right away, so this cannot go through the captured stderr
+                    state.stderr.queue(Print(format!("exit: {}: numeric argument required\n", args[0]))).unwrap();
+                    Err(ShellError::ExitRequest(2))
+                }
+            },
+            _ => Err(ShellError::Builtin(BuiltinError::new(1, "exit: too many arguments".to_string())))
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+use crate::eval::builtins::declare::{declare_variable, print_declarations, Declaration};
+
+pub struct Export;
+
+impl Builtin for Export {
+    fn name(&self) -> &str {
+        "export"
+    }
+
+    fn help(&self) -> &str {
+        "Mark each NAME to be passed to child processes in the environment, optionally assigning it first. With -n, NAME is no longer exported. Without NAME or with -p, lists the exported variables."
+    }
+
+    fn usage(&self) -> &str {
+        "export [-np] [name[=value] ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let mut declaration = Declaration::default();
+        let mut remove = false;
+        let mut print = false;
+        let mut operands = args;
+        while let Some(arg) = operands.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+            operands = &operands[1..];
+            if arg == "--" {
+                break;
+            }
+            for flag in arg[1..].chars() {
+                match flag {
+                    'n' => remove = true,
+                    'p' => print = true,
+                    _ => return Err(ShellError::Builtin(BuiltinError::new(2, format!("export: -{}: invalid option", flag))))
+                }
+            }
+        }
+        if print || operands.is_empty() {
+            declaration.add.export = true;
+            return Ok(print_declarations(state, "export", operands, &declaration, stdout, stderr));
+        }
+        if remove {
+            declaration.remove.export = true;
+        } else {
+            declaration.add.export = true;
+        }
+        let arrays = std::mem::take(&mut state.array_arguments);
+        let offset = args.len() - operands.len();
+        let mut status = 0;
+        for (index, operand) in operands.iter().enumerate() {
+            if let Err(message) = declare_variable(state, operand, arrays.get(&(offset + index)).cloned(), &declaration) {
+                stderr.queue(Print(format!("export: {}\n", message))).unwrap();
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+}
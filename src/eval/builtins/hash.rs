@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{parse_options, Builtin};
+
+pub struct Hash;
+
+impl Builtin for Hash {
+    fn name(&self) -> &str {
+        "hash"
+    }
+
+    fn help(&self) -> &str {
+        "Remember or display the full path of commands. Each NAME is searched in PATH and remembered. Without arguments, the table is listed with the number of times each command was used. -r forgets every location, -d forgets the given NAMEs, -t prints their remembered location and -p uses PATHNAME as the location of NAME."
+    }
+
+    fn usage(&self) -> &str {
+        "hash [-rdt] [-p pathname] [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("hash", args, "rdt", "p")?;
+        if options.has('r') {
+            state.path_cache.clear();
+        }
+        if let Some(pathname) = options.value('p') {
+            for name in &options.operands {
+                state.path_cache.insert(name, PathBuf::from(pathname));
+            }
+            return Ok(0);
+        }
+        if options.operands.is_empty() {
+            if options.has('r') {
+                return Ok(0);
+            }
+            let entries = state.path_cache.get_entries();
+            if entries.is_empty() {
+                stderr.queue(Print("hash: hash table empty\n")).unwrap();
+                return Ok(0);
+            }
+            stdout.queue(Print("hits\tcommand\n")).unwrap();
+            for entry in entries.values() {
+                stdout.queue(Print(format!("{:4}\t{}\n", entry.hits, entry.path.display()))).unwrap();
+            }
+            return Ok(0);
+        }
+        let mut status = 0;
+        for name in &options.operands {
+            let found = if options.has('d') {
+                state.path_cache.remove(name)
+            } else if options.has('t') {
+                match state.path_cache.get(name) {
+                    Some(entry) => {
+                        stdout.queue(Print(format!("{}\n", entry.path.display()))).unwrap();
+                        true
+                    },
+                    None => false
+                }
+            } else {
+                name.contains('/') || state.builtins.is_enabled(name) || state.path_cache.add(name)
+            };
+            if !found {
+                stderr.queue(Print(format!("hash: {}: not found\n", name))).unwrap();
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Help;
+
+impl Builtin for Help {
+    fn name(&self) -> &str {
+        "help"
+    }
+
+    fn help(&self) -> &str {
+        "Display information about builtin commands. Without arguments, lists every builtin; with -s, only prints usage."
+    }
+
+    fn usage(&self) -> &str {
+        "help [-s] [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let short = args.first().is_some_and(|arg| arg == "-s");
+        let topics: Vec<&String> = args.iter().skip(if short { 1 } else { 0 }).collect();
+        if topics.is_empty() {
+            for builtin in state.builtins.list() {
+                let marker = if state.builtins.is_enabled(builtin.name()) { ' ' } else { '*' };
+                stdout.queue(Print(format!("{}{}\n", marker, builtin.usage()))).unwrap();
+            }
+            return Ok(0);
+        }
+        for topic in topics {
+            match state.builtins.lookup(topic) {
+                Some(builtin) => {
+                    stdout.queue(Print(format!("{}: {}\n", builtin.name(), builtin.usage()))).unwrap();
+                    if !short {
+                        stdout.queue(Print(format!("    {}\n", builtin.help()))).unwrap();
+                    }
+                },
+                None => {
+                    return Err(ShellError::Builtin(BuiltinError::new(1, format!("help: no help topics match `{}'", topic))));
+                }
+            }
+        }
+        Ok(0)
+    }
+}
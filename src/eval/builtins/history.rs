@@ -0,0 +1,38 @@
+use std::io::Write;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::Builtin;
+
+pub struct History;
+
+impl Builtin for History {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn help(&self) -> &str {
+        "Display the command history. With arguments, only entries matching one of them are shown."
+    }
+
+    fn usage(&self) -> &str {
+        "history [entry ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let history_values = state.history.get_values().clone();
+        match args.len() {
+            0 => {
+                stdout.write_all(history_values.join("\n").as_bytes()).unwrap();
+            }
+            _ => {
+                let matching = history_values.into_iter()
+                                            .filter(|value| args.contains(value))
+                                            .collect::<Vec<String>>()
+                                            .join("\n");
+                stdout.write_all(matching.as_bytes()).unwrap();
+            }
+        }
+        Ok(0)
+    }
+}
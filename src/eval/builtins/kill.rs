@@ -0,0 +1,116 @@
+use std::io::{self, Write};
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::{io_error_message, ShellError};
+use crate::core::signals::{signal_name, signal_number, SIGNALS};
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Kill;
+
+fn kill_error(message: String) -> ShellError {
+    ShellError::Builtin(BuiltinError::new(1, format!("kill: {}", message)))
+}
+
+// Signal 0 only checks that the process exists
+fn parse_signal(spec: &str) -> Result<libc::c_int, ShellError> {
+    if spec == "0" {
+        return Ok(0);
+    }
+    signal_number(spec).ok_or_else(|| kill_error(format!("{}: invalid signal specification", spec)))
+}
+
+fn list_signals(args: &[String], stdout: &mut dyn Write) -> Result<i32, ShellError> {
+    if args.is_empty() {
+        let mut signals = SIGNALS.to_vec();
+        signals.sort_by_key(|(_, number)| *number);
+        for (index, (name, number)) in signals.iter().enumerate() {
+            let separator = if index % 5 == 4 || index == signals.len() - 1 { "\n" } else { "\t" };
+            stdout.queue(Print(format!("{:2}) SIG{}{}", number, name, separator))).unwrap();
+        }
+        return Ok(0);
+    }
+    for arg in args {
+        match arg.parse::<libc::c_int>() {
+            // Exit statuses of killed processes are accepted too: `kill -l $?`
+            Ok(number) => match signal_name(if number > 128 { number - 128 } else { number }) {
+                Some(name) => stdout.queue(Print(format!("{}\n", name))).unwrap(),
+                None => return Err(kill_error(format!("{}: invalid signal specification", arg)))
+            },
+            Err(_) => match signal_number(arg) {
+                Some(number) => stdout.queue(Print(format!("{}\n", number))).unwrap(),
+                None => return Err(kill_error(format!("{}: invalid signal specification", arg)))
+            }
+        };
+    }
+    Ok(0)
+}
+
+impl Builtin for Kill {
+    fn name(&self) -> &str {
+        "kill"
+    }
+
+    fn help(&self) -> &str {
+        "Send a signal to the processes named by PID or JOBSPEC (%N, %%, %+, %-, %string, %?string). The signal defaults to TERM and is given as a name (TERM, SIGTERM) or a number. `kill -l` lists the signal names, or translates the given numbers and names."
+    }
+
+    fn usage(&self) -> &str {
+        "kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let mut signal = libc::SIGTERM;
+        let mut targets = args;
+        match args.first().map(|arg| arg.as_str()) {
+            None => return Err(ShellError::Builtin(BuiltinError::new(2, format!("kill: usage: {}", self.usage())))),
+            Some("-l") | Some("-L") => return list_signals(&args[1..], stdout),
+            Some("-s") | Some("-n") => {
+                let Some(spec) = args.get(1) else {
+                    return Err(ShellError::Builtin(BuiltinError::new(2, format!("kill: {}: option requires an argument", args[0]))));
+                };
+                signal = parse_signal(spec)?;
+                targets = &args[2..];
+            },
+            Some("--") => targets = &args[1..],
+            // A lone negative number is a process group, not a signal
+            Some(arg) if arg.len() > 1 && arg.starts_with('-') && (arg[1..].parse::<i32>().is_err() || args.len() > 1) => {
+                signal = parse_signal(&arg[1..])?;
+                targets = &args[1..];
+            },
+            _ => ()
+        }
+        if targets.first().is_some_and(|arg| arg == "--") {
+            targets = &targets[1..];
+        }
+        let mut status = 0;
+        for target in targets {
+            let pid = if target.starts_with('%') {
+                match state.jobs.find_spec(target) {
+                    Some(job) => job.pid as libc::pid_t,
+                    None => {
+                        stderr.queue(Print(format!("kill: {}: no such job\n", target))).unwrap();
+                        status = 1;
+                        continue;
+                    }
+                }
+            } else {
+                match target.parse::<libc::pid_t>() {
+                    Ok(pid) => pid,
+                    Err(_) => {
+                        stderr.queue(Print(format!("kill: {}: arguments must be process or job IDs\n", target))).unwrap();
+                        status = 1;
+                        continue;
+                    }
+                }
+            };
+            if unsafe { libc::kill(pid, signal) } == -1 {
+                stderr.queue(Print(format!("kill: ({}) - {}\n", pid, io_error_message(&io::Error::last_os_error())))).unwrap();
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::core::error::{ShellError, StatusEnum};
+
+use crate::core::cmdoutput::CmdOutput;
+use crate::core::core::ShellState;
+
+pub mod alias;
+pub mod cd;
+pub mod command;
+pub mod declare;
+pub mod dirstack;
+pub mod echo;
+pub mod enable;
+pub mod exec;
+pub mod exit;
+pub mod export;
+pub mod hash;
+pub mod help;
+pub mod history;
+pub mod kill;
+pub mod printf;
+pub mod pwd;
+pub mod read;
+pub mod r#return;
+pub mod set;
+pub mod source;
+pub mod times;
+pub mod trap;
+pub mod r#type;
+pub mod ulimit;
+pub mod umask;
+pub mod unset;
+pub mod wait;
+pub mod which;
+
+#[derive(Debug)]
+pub struct BuiltinError {
+    pub status: u16,
+    pub message: String
+}
+
+impl BuiltinError {
+    pub fn new(code: u16, msg: String) -> BuiltinError {
+        BuiltinError {
+            status: code,
+            message: msg
+        }
+    }
+}
+
+impl StatusEnum for BuiltinError {
+    fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+pub struct ParsedOptions {
+    pub options: Vec<(char, Option<String>)>,
+    pub operands: Vec<String>
+}
+
+impl ParsedOptions {
+    pub fn has(&self, flag: char) -> bool {
+        self.options.iter().any(|(c, _)| *c == flag)
+    }
+
+    // Last value given for a flag, so that later options override earlier ones
+    pub fn value(&self, flag: char) -> Option<&String> {
+        self.options.iter().rev().find(|(c, _)| *c == flag).and_then(|(_, value)| value.as_ref())
+    }
+}
+
+// Splits leading `-abc` style options from operands. Flags listed in `with_value`
+// take the rest of the word or the next argument as their value.
+pub fn parse_options(name: &str, args: &[String], flags: &str, with_value: &str) -> Result<ParsedOptions, ShellError> {
+    let mut parsed = ParsedOptions {
+        options: Vec::new(),
+        operands: Vec::new()
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            parsed.operands.push(arg.clone());
+            break;
+        }
+        let mut chars = arg.chars().skip(1);
+        while let Some(flag) = chars.next() {
+            if with_value.contains(flag) {
+                let rest: String = chars.by_ref().collect();
+                if !rest.is_empty() {
+                    parsed.options.push((flag, Some(rest)));
+                } else if let Some(value) = iter.next() {
+                    parsed.options.push((flag, Some(value.clone())));
+                } else {
+                    return Err(ShellError::Builtin(BuiltinError::new(2, format!("{}: -{}: option requires an argument", name, flag))));
+                }
+            } else if flags.contains(flag) {
+                parsed.options.push((flag, None));
+            } else {
+                return Err(ShellError::Builtin(BuiltinError::new(2, format!("{}: -{}: invalid option", name, flag))));
+            }
+        }
+    }
+    parsed.operands.extend(iter.cloned());
+    Ok(parsed)
+}
+
+pub trait Builtin {
+    fn name(&self) -> &str;
+    fn help(&self) -> &str;
+    fn usage(&self) -> &str;
+    fn run(&self, state: &mut ShellState, args: &[String], stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError>;
+}
+
+pub struct BuiltinRegistry {
+    builtins: BTreeMap<String, Rc<dyn Builtin>>,
+    disabled: HashSet<String>
+}
+
+impl BuiltinRegistry {
+    pub fn new() -> BuiltinRegistry {
+        let mut registry = BuiltinRegistry {
+            builtins: BTreeMap::new(),
+            disabled: HashSet::new()
+        };
+        registry.register(Rc::new(alias::Alias));
+        registry.register(Rc::new(cd::Cd));
+        registry.register(Rc::new(command::Command));
+        registry.register(Rc::new(declare::Declare { name: "declare" }));
+        registry.register(Rc::new(dirstack::Dirs));
+        registry.register(Rc::new(echo::Echo));
+        registry.register(Rc::new(enable::Enable));
+        registry.register(Rc::new(exec::Exec));
+        registry.register(Rc::new(exit::Exit));
+        registry.register(Rc::new(export::Export));
+        registry.register(Rc::new(hash::Hash));
+        registry.register(Rc::new(help::Help));
+        registry.register(Rc::new(history::History));
+        registry.register(Rc::new(kill::Kill));
+        registry.register(Rc::new(declare::Declare { name: "local" }));
+        registry.register(Rc::new(dirstack::Popd));
+        registry.register(Rc::new(printf::Printf));
+        registry.register(Rc::new(dirstack::Pushd));
+        registry.register(Rc::new(pwd::Pwd));
+        registry.register(Rc::new(read::Read));
+        registry.register(Rc::new(declare::Declare { name: "readonly" }));
+        registry.register(Rc::new(r#return::Return));
+        registry.register(Rc::new(set::Set));
+        registry.register(Rc::new(source::Source { name: "source" }));
+        registry.register(Rc::new(source::Source { name: "." }));
+        registry.register(Rc::new(times::Times));
+        registry.register(Rc::new(trap::Trap));
+        registry.register(Rc::new(r#type::Type));
+        registry.register(Rc::new(declare::Declare { name: "typeset" }));
+        registry.register(Rc::new(ulimit::Ulimit));
+        registry.register(Rc::new(umask::Umask));
+        registry.register(Rc::new(unset::Unset));
+        registry.register(Rc::new(wait::Wait));
+        registry.register(Rc::new(which::Which));
+        registry
+    }
+
+    // Registers a builtin, replacing (and returning) any previous one with the same name
+    pub fn register(&mut self, builtin: Rc<dyn Builtin>) -> Option<Rc<dyn Builtin>> {
+        self.builtins.insert(builtin.name().to_string(), builtin)
+    }
+
+    // Returns the builtin only if it is currently enabled
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Builtin>> {
+        if self.disabled.contains(name) {
+            return None;
+        }
+        self.lookup(name)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Rc<dyn Builtin>> {
+        self.builtins.get(name).cloned()
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.builtins.contains_key(name) && !self.disabled.contains(name)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        if !self.builtins.contains_key(name) {
+            return false;
+        }
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+        true
+    }
+
+    pub fn list(&self) -> Vec<Rc<dyn Builtin>> {
+        self.builtins.values().cloned().collect()
+    }
+}
+
+pub fn run_builtin(state: &mut ShellState, command: &str, args: &[String], input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
+    match state.builtins.get(command) {
+        Some(builtin) => {
+            let mut output = CmdOutput::new();
+            match builtin.run(state, args, input, &mut output.stdout, &mut output.stderr) {
+                Ok(status) => output.status = Some(status),
+                Err(ShellError::Builtin(error)) => {
+                    // Builtin errors are written to stderr so they can be redirected like any program's
+                    output.stderr.extend_from_slice(error.message.as_bytes());
+                    output.stderr.push(b'\n');
+                    output.status = Some(error.status as i32);
+                },
+                Err(error) => return Err(error)
+            }
+            Ok(output)
+        },
+        None => Err(ShellError::NoBuiltin)
+    }
+}
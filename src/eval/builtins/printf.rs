@@ -0,0 +1,433 @@
+use std::io::Write;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::osstr::{bytes_to_word, word_to_bytes};
+use crate::eval::builtins::echo::interpret_escapes;
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+
+pub struct Printf;
+
+impl Builtin for Printf {
+    fn name(&self) -> &str {
+        "printf"
+    }
+
+    fn help(&self) -> &str {
+        "Format and print ARGUMENTS under control of FORMAT. The format is reused until all arguments are consumed. %b expands backslash escapes in its argument, %q quotes it for reuse as shell input. With -v, the output is assigned to VAR instead of being printed."
+    }
+
+    fn usage(&self) -> &str {
+        "printf [-v var] format [arguments ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("printf", args, "", "v")?;
+        let Some(format) = options.operands.first() else {
+            return Err(ShellError::Builtin(BuiltinError::new(2, format!("printf: usage: {}", self.usage()))));
+        };
+        let printer = Printer::print(format, &options.operands[1..]);
+        for error in &printer.errors {
+            stderr.write_all(format!("printf: {}\n", error).as_bytes()).unwrap();
+        }
+        match options.value('v') {
+            Some(var) => {
+                if let Err(message) = state.variables.set(var, &bytes_to_word(&printer.output)) {
+                    return Err(ShellError::Builtin(BuiltinError::new(1, format!("printf: {}", message))));
+                }
+            },
+            None => stdout.write_all(&printer.output).unwrap()
+        }
+        Ok(if printer.errors.is_empty() { 0 } else { 1 })
+    }
+}
+
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>
+}
+
+struct Printer<'a> {
+    args: &'a [String],
+    next_arg: usize,
+    output: Vec<u8>,
+    errors: Vec<String>
+}
+
+impl<'a> Printer<'a> {
+    fn print(format: &str, args: &'a [String]) -> Printer<'a> {
+        let mut printer = Printer {
+            args,
+            next_arg: 0,
+            output: Vec::new(),
+            errors: Vec::new()
+        };
+        loop {
+            let consumed = printer.next_arg;
+            if printer.print_format(format) {
+                break;
+            }
+            // Reuse the format while arguments remain, as long as it consumes some
+            if printer.next_arg == consumed || printer.next_arg >= printer.args.len() {
+                break;
+            }
+        }
+        printer
+    }
+
+    fn next(&mut self) -> Option<&'a String> {
+        let arg = self.args.get(self.next_arg);
+        self.next_arg += 1;
+        arg
+    }
+
+    // Prints the format once. Returns true when `\c` in a %b argument stopped all output.
+    fn print_format(&mut self, format: &str) -> bool {
+        let mut chars = format.chars().peekable();
+        let mut literal = String::new();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                literal.push(c);
+                if let Some(escaped) = chars.next() {
+                    literal.push(escaped);
+                }
+                continue;
+            }
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            self.output.extend(interpret_escapes(&literal, false).0);
+            literal.clear();
+            if chars.peek() == Some(&'%') {
+                chars.next();
+                self.output.push(b'%');
+                continue;
+            }
+            let mut spec = Spec {
+                left: false,
+                plus: false,
+                space: false,
+                alternate: false,
+                zero: false,
+                width: None,
+                precision: None
+            };
+            while let Some(&flag) = chars.peek() {
+                match flag {
+                    '-' => spec.left = true,
+                    '+' => spec.plus = true,
+                    ' ' => spec.space = true,
+                    '#' => spec.alternate = true,
+                    '0' => spec.zero = true,
+                    _ => break
+                }
+                chars.next();
+            }
+            spec.width = self.parse_count(&mut chars, &mut spec.left);
+            if chars.peek() == Some(&'.') {
+                chars.next();
+                let mut ignored = false;
+                spec.precision = Some(self.parse_count(&mut chars, &mut ignored).unwrap_or(0));
+            }
+            let Some(conversion) = chars.next() else {
+                self.errors.push("`%': missing format character".to_string());
+                break;
+            };
+            if conversion == 'b' {
+                let arg = self.next().cloned().unwrap_or_default();
+                let (mut bytes, stop) = interpret_escapes(&arg, true);
+                // The escapes may produce any byte, so it is output as is
+                if let Some(precision) = spec.precision {
+                    bytes.truncate(precision);
+                }
+                let length = String::from_utf8_lossy(&bytes).chars().count();
+                let fill = " ".repeat(spec.width.unwrap_or(0).saturating_sub(length));
+                if spec.left {
+                    self.output.extend(bytes);
+                    self.output.extend(fill.into_bytes());
+                } else {
+                    self.output.extend(fill.into_bytes());
+                    self.output.extend(bytes);
+                }
+                if stop {
+                    return true;
+                }
+                continue;
+            }
+            match self.format_conversion(conversion, &spec) {
+                Some(text) => self.output.extend(word_to_bytes(&text)),
+                None => {
+                    self.errors.push(format!("`{}': invalid format character", conversion));
+                    break;
+                }
+            }
+        }
+        self.output.extend(interpret_escapes(&literal, false).0);
+        false
+    }
+
+    // Width or precision: digits, or `*` to take it from the next argument
+    fn parse_count(&mut self, chars: &mut std::iter::Peekable<std::str::Chars>, left: &mut bool) -> Option<usize> {
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let value = self.next_integer();
+            if value < 0 {
+                *left = true;
+            }
+            return Some(value.unsigned_abs() as usize);
+        }
+        let mut digits = String::new();
+        while let Some(&digit) = chars.peek() {
+            if !digit.is_ascii_digit() {
+                break;
+            }
+            digits.push(digit);
+            chars.next();
+        }
+        digits.parse().ok()
+    }
+
+    fn next_integer(&mut self) -> i64 {
+        let Some(arg) = self.next() else {
+            return 0;
+        };
+        match parse_integer(arg) {
+            Some(value) => value,
+            None => {
+                self.errors.push(format!("{}: invalid number", arg));
+                0
+            }
+        }
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let Some(arg) = self.next() else {
+            return 0.0;
+        };
+        if let Ok(value) = arg.trim().parse::<f64>() {
+            return value;
+        }
+        match parse_integer(arg) {
+            Some(value) => value as f64,
+            None => {
+                self.errors.push(format!("{}: invalid number", arg));
+                0.0
+            }
+        }
+    }
+
+    fn format_conversion(&mut self, conversion: char, spec: &Spec) -> Option<String> {
+        let text = match conversion {
+            's' => {
+                let arg = self.next().cloned().unwrap_or_default();
+                match spec.precision {
+                    Some(precision) => pad(&arg.chars().take(precision).collect::<String>(), spec, false),
+                    None => pad(&arg, spec, false)
+                }
+            },
+            'q' => {
+                let arg = self.next().cloned().unwrap_or_default();
+                pad(&shell_quote(&arg), spec, false)
+            },
+            'c' => {
+                let arg = self.next().cloned().unwrap_or_default();
+                pad(&arg.chars().next().map(String::from).unwrap_or_default(), spec, false)
+            },
+            'd' | 'i' => {
+                let value = self.next_integer();
+                let digits = with_precision(value.unsigned_abs().to_string(), spec.precision);
+                pad(&format!("{}{}", sign(value < 0, spec), digits), spec, spec.precision.is_none())
+            },
+            'u' | 'o' | 'x' | 'X' => {
+                let value = self.next_integer() as u64;
+                let (digits, prefix) = match conversion {
+                    'o' => (format!("{:o}", value), if spec.alternate { "0" } else { "" }),
+                    'x' => (format!("{:x}", value), if spec.alternate && value != 0 { "0x" } else { "" }),
+                    'X' => (format!("{:X}", value), if spec.alternate && value != 0 { "0X" } else { "" }),
+                    _ => (value.to_string(), "")
+                };
+                let digits = with_precision(digits, spec.precision);
+                let prefix = if prefix == "0" && digits.starts_with('0') { "" } else { prefix };
+                pad(&format!("{}{}", prefix, digits), spec, spec.precision.is_none())
+            },
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = self.next_float();
+                let body = format_float(value.abs(), conversion, spec.precision.unwrap_or(6), spec.alternate);
+                pad(&format!("{}{}", sign(value.is_sign_negative() && value != 0.0, spec), body), spec, value.is_finite())
+            },
+            _ => return None
+        };
+        Some(text)
+    }
+}
+
+fn sign(negative: bool, spec: &Spec) -> &'static str {
+    if negative {
+        return "-";
+    } else if spec.plus {
+        return "+";
+    } else if spec.space {
+        return " ";
+    }
+    ""
+}
+
+fn with_precision(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if digits.len() < precision => format!("{}{}", "0".repeat(precision - digits.len()), digits),
+        _ => digits
+    }
+}
+
+// Pads to the field width. Zero padding goes after the sign or base prefix of numbers.
+fn pad(text: &str, spec: &Spec, numeric: bool) -> String {
+    let length = text.chars().count();
+    let width = spec.width.unwrap_or(0);
+    if length >= width {
+        return text.to_string();
+    }
+    let fill = width - length;
+    if spec.left {
+        return format!("{}{}", text, " ".repeat(fill));
+    }
+    if spec.zero && numeric {
+        let prefix_len = if text.starts_with("0x") || text.starts_with("0X") {
+            2
+        } else if text.starts_with(['-', '+', ' ']) {
+            1
+        } else {
+            0
+        };
+        return format!("{}{}{}", &text[..prefix_len], "0".repeat(fill), &text[prefix_len..]);
+    }
+    format!("{}{}", " ".repeat(fill), text)
+}
+
+// Accepts decimal, 0x hexadecimal, 0 octal and 'c character constants
+fn parse_integer(arg: &str) -> Option<i64> {
+    let trimmed = arg.trim();
+    if let Some(quoted) = trimmed.strip_prefix('\'').or_else(|| trimmed.strip_prefix('"')) {
+        return Some(quoted.chars().next().map(|c| c as i64).unwrap_or(0));
+    }
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed))
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8).ok()?
+    } else {
+        digits.parse::<i64>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn format_exponent(value: f64, precision: usize, upper: bool) -> String {
+    // Rust prints `1.5e0`, C wants `1.5e+00`
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let marker = if upper { 'E' } else { 'e' };
+    format!("{}{}{}{:02}", mantissa, marker, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+}
+
+fn format_float(value: f64, conversion: char, precision: usize, alternate: bool) -> String {
+    let upper = conversion.is_ascii_uppercase();
+    if !value.is_finite() {
+        let text = if value.is_nan() { "nan" } else { "inf" };
+        return if upper { text.to_uppercase() } else { text.to_string() };
+    }
+    match conversion {
+        'f' | 'F' => {
+            let text = format!("{:.*}", precision, value);
+            if alternate && precision == 0 { format!("{}.", text) } else { text }
+        },
+        'e' | 'E' => format_exponent(value, precision, upper),
+        _ => {
+            let precision = precision.max(1);
+            let exponent: i32 = if value == 0.0 {
+                0
+            } else {
+                format!("{:.*e}", precision - 1, value).split_once('e').unwrap().1.parse().unwrap()
+            };
+            let mut text = if exponent < -4 || exponent >= precision as i32 {
+                format_exponent(value, precision - 1, upper)
+            } else {
+                format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value)
+            };
+            if !alternate {
+                // %g drops trailing zeros of the fractional part
+                let (mantissa, exponent) = match text.find(['e', 'E']) {
+                    Some(pos) => (text[..pos].to_string(), text[pos..].to_string()),
+                    None => (text.clone(), String::new())
+                };
+                let mantissa = if mantissa.contains('.') {
+                    mantissa.trim_end_matches('0').trim_end_matches('.').to_string()
+                } else {
+                    mantissa
+                };
+                text = format!("{}{}", mantissa, exponent);
+            }
+            text
+        }
+    }
+}
+
+// Quotes a string so that it can be reused as shell input
+pub fn shell_quote(value: &str) -> String {
+    if value.is_empty() {
+        return "''".to_string();
+    }
+    if value.chars().all(|c| c.is_alphanumeric() || "_-./,:=@%+".contains(c)) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(format: &str, args: &[&str]) -> (String, Vec<String>) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let printer = Printer::print(format, &args);
+        (String::from_utf8(printer.output).unwrap(), printer.errors)
+    }
+
+    #[test]
+    fn format_is_reused_for_remaining_arguments() {
+        assert_eq!(print("%s=%d\\n", &["a", "1", "b", "2"]).0, "a=1\nb=2\n");
+        assert_eq!(print("<%s>", &["a", "b", "c"]).0, "<a><b><c>");
+    }
+
+    #[test]
+    fn last_reuse_fills_missing_arguments() {
+        assert_eq!(print("%s,%s;", &["a", "b", "c"]).0, "a,b;c,;");
+        assert_eq!(print("%d|", &[]).0, "0|");
+    }
+
+    #[test]
+    fn format_without_conversions_is_printed_once() {
+        assert_eq!(print("hi\\n", &["a", "b"]).0, "hi\n");
+    }
+
+    #[test]
+    fn escape_c_stops_the_reuse() {
+        assert_eq!(print("%s%b", &["a", "x\\cy", "b", "z"]).0, "ax");
+    }
+
+    #[test]
+    fn invalid_arguments_are_reported() {
+        let (output, errors) = print("%d ", &["1", "x"]);
+        assert_eq!(output, "1 0 ");
+        assert_eq!(errors.len(), 1);
+    }
+}
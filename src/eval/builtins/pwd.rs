@@ -0,0 +1,37 @@
+use std::env;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::cd::get_logical_pwd;
+use crate::eval::builtins::{parse_options, Builtin};
+
+pub struct Pwd;
+
+impl Builtin for Pwd {
+    fn name(&self) -> &str {
+        "pwd"
+    }
+
+    fn help(&self) -> &str {
+        "Print the name of the current working directory. -L (default) keeps symbolic links as they were followed by cd, -P prints the physical directory."
+    }
+
+    fn usage(&self) -> &str {
+        "pwd [-L|-P]"
+    }
+
+    fn run(&self, _state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("pwd", args, "LP", "")?;
+        let directory = if options.options.last().is_some_and(|(flag, _)| *flag == 'P') {
+            env::current_dir().unwrap_or_else(|_| get_logical_pwd())
+        } else {
+            get_logical_pwd()
+        };
+        let mut env_output = directory.as_os_str().as_bytes().to_vec();
+        env_output.push(b'\n');
+        stdout.write_all(&env_output).unwrap();
+        Ok(0)
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, event::{poll, read, Event, KeyCode, KeyModifiers}, style::Print, terminal, QueueableCommand};
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::fsio::flush_output;
+use crate::core::osstr::bytes_to_word;
+use crate::core::readloop::handle_event;
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError, ParsedOptions};
+use crate::features::autocomplete::Autocomplete;
+use crate::features::prompt::Prompt;
+use crate::parser::tokenizer::is_name;
+
+pub struct Read;
+
+enum ReadEnd {
+    Delimiter,
+    Eof,
+    Timeout,
+    Interrupted
+}
+
+impl Builtin for Read {
+    fn name(&self) -> &str {
+        "read"
+    }
+
+    fn help(&self) -> &str {
+        "Read a line from the standard input and split it into fields using IFS. The first field is assigned to the first NAME, the second to the second, and the remaining ones to the last NAME. Without NAME, the line is stored in REPLY. -r keeps backslashes, -p shows a prompt, -s hides typed input, -t gives up after TIMEOUT seconds, -n stops after COUNT characters, -d reads until DELIM instead of newline and -a assigns the fields to the indexed array ARRAY."
+    }
+
+    fn usage(&self) -> &str {
+        "read [-rs] [-a array] [-d delim] [-n count] [-p prompt] [-t timeout] [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("read", args, "rs", "adnpt")?;
+        let delimiter = match options.value('d') {
+            Some(delim) => delim.chars().next().unwrap_or('\0'),
+            None => '\n'
+        };
+        let count = match options.value('n') {
+            Some(count) => match count.parse::<usize>() {
+                Ok(count) => Some(count),
+                Err(_) => return Err(ShellError::Builtin(BuiltinError::new(1, format!("read: {}: invalid number", count))))
+            },
+            None => None
+        };
+        let timeout = match options.value('t') {
+            Some(timeout) => match timeout.parse::<f64>() {
+                Ok(seconds) if seconds >= 0.0 => Some(Duration::from_secs_f64(seconds)),
+                _ => return Err(ShellError::Builtin(BuiltinError::new(1, format!("read: {}: invalid timeout specification", timeout))))
+            },
+            None => None
+        };
+        if let Some(name) = options.operands.iter().chain(options.value('a')).find(|name| !is_name(name)) {
+            return Err(ShellError::Builtin(BuiltinError::new(1, format!("read: `{}': not a valid identifier", name))));
+        }
+        let (line, end) = match stdin {
+            Some(data) => read_from_bytes(&bytes_to_word(data), delimiter, count),
+            None if io::stdin().is_terminal() => read_from_terminal(state, &options, delimiter, count, timeout),
+            None => read_from_stdin(delimiter, count, timeout)
+        };
+        if let ReadEnd::Interrupted = end {
+            return Ok(130);
+        }
+        let line = if options.has('r') { line } else { remove_backslashes(&line) };
+        if let Err(message) = assign_fields(state, &options, &line) {
+            return Err(ShellError::Builtin(BuiltinError::new(1, format!("read: {}", message))));
+        }
+        match end {
+            ReadEnd::Delimiter => Ok(0),
+            ReadEnd::Timeout => Ok(142),
+            _ => Ok(1)
+        }
+    }
+}
+
+fn read_from_bytes(data: &str, delimiter: char, count: Option<usize>) -> (String, ReadEnd) {
+    let mut line = String::new();
+    for (index, c) in data.chars().enumerate() {
+        if c == delimiter {
+            return (line, ReadEnd::Delimiter);
+        }
+        line.push(c);
+        if count.is_some_and(|count| index + 1 >= count) {
+            return (line, ReadEnd::Delimiter);
+        }
+    }
+    (line, ReadEnd::Eof)
+}
+
+// Reads the standard input one byte at a time, so that what follows the line is left for the
+// next command
+fn read_from_stdin(delimiter: char, count: Option<usize>, timeout: Option<Duration>) -> (String, ReadEnd) {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut data: Vec<u8> = Vec::new();
+    let mut char_start = 0;
+    let mut chars = 0;
+    loop {
+        if deadline.is_some_and(|deadline| !wait_for_stdin(deadline)) {
+            return (bytes_to_word(&data), ReadEnd::Timeout);
+        }
+        let mut byte = 0u8;
+        let read = unsafe { libc::read(libc::STDIN_FILENO, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        if read < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        }
+        if read <= 0 {
+            return (bytes_to_word(&data), ReadEnd::Eof);
+        }
+        data.push(byte);
+        // Wait for the rest of a multibyte character before looking at it
+        let current = &data[char_start..];
+        if std::str::from_utf8(current).is_err() && current.len() < 4 && current[0] >= 0xC0 {
+            continue;
+        }
+        if current == delimiter.to_string().as_bytes() {
+            data.truncate(char_start);
+            return (bytes_to_word(&data), ReadEnd::Delimiter);
+        }
+        char_start = data.len();
+        chars += 1;
+        if count.is_some_and(|count| chars >= count) {
+            return (bytes_to_word(&data), ReadEnd::Delimiter);
+        }
+    }
+}
+
+// Waits until the standard input can be read or the deadline passes
+fn wait_for_stdin(deadline: Instant) -> bool {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now()).as_millis();
+        match unsafe { libc::poll(&mut fd, 1, i32::try_from(remaining).unwrap_or(i32::MAX)) } {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            0 => return false,
+            _ => return true
+        }
+    }
+}
+
+// Reads the line with the prompt line editor, or blindly when -s is given
+fn read_from_terminal(state: &mut ShellState, options: &ParsedOptions, delimiter: char, count: Option<usize>, timeout: Option<Duration>) -> (String, ReadEnd) {
+    let silent = options.has('s');
+    let mut prompt = Prompt::new("");
+    let mut autocomplete = Autocomplete::new();
+    let mut history_idx: Option<usize> = None;
+    if let Some(text) = options.value('p') {
+        state.stdout.queue(Print(text)).unwrap();
+        flush_output(state);
+    }
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    if terminal::enable_raw_mode().is_err() {
+        return (String::new(), ReadEnd::Eof);
+    }
+    state.ps1pos = cursor::position().unwrap_or(state.ps1pos);
+    let end;
+    loop {
+        if let Some(deadline) = deadline {
+            if !poll(deadline.saturating_duration_since(Instant::now())).unwrap_or(false) {
+                end = ReadEnd::Timeout;
+                break;
+            }
+        }
+        let Ok(event) = read() else {
+            continue;
+        };
+        if let Event::Key(key) = event {
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Char('c') if ctrl => {
+                    state.stdout.queue(Print("^C")).unwrap();
+                    end = ReadEnd::Interrupted;
+                    break;
+                },
+                KeyCode::Char('d') if ctrl && !prompt.has_input() => {
+                    end = ReadEnd::Eof;
+                    break;
+                },
+                KeyCode::Enter if delimiter == '\n' => {
+                    end = ReadEnd::Delimiter;
+                    break;
+                },
+                KeyCode::Char(c) if c == delimiter && !ctrl => {
+                    end = ReadEnd::Delimiter;
+                    break;
+                },
+                KeyCode::Enter => prompt.add_char('\n'),
+                KeyCode::Char(c) if silent && !ctrl => prompt.add_char(c),
+                KeyCode::Backspace if silent => {
+                    prompt.remove_char(true);
+                },
+                _ if !silent => {
+                    handle_event(state, &mut autocomplete, &mut prompt, &mut history_idx, event);
+                },
+                _ => ()
+            }
+            if count.is_some_and(|count| prompt.get_input().chars().count() >= count) {
+                end = ReadEnd::Delimiter;
+                break;
+            }
+        }
+        // A failure is reported once the line is read
+        let _ = state.stdout.flush();
+    }
+    let _ = terminal::disable_raw_mode();
+    state.stdout.queue(Print("\n")).unwrap()
+                .queue(cursor::MoveToColumn(0)).unwrap();
+    flush_output(state);
+    (prompt.get_input().clone(), end)
+}
+
+// Without -r, a backslash escapes the next character and backslash-newline is a line continuation
+fn remove_backslashes(line: &str) -> String {
+    let mut result = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('\n') | None => (),
+                Some(escaped) => result.push(escaped)
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// Splits on IFS: runs of IFS whitespace are trimmed and act as one separator, every
+// other IFS character delimits a field. The last field receives the rest of the line.
+pub fn split_fields(line: &str, ifs: &str, max_fields: usize) -> Vec<String> {
+    let is_ifs_space = |c: char| c.is_whitespace() && ifs.contains(c);
+    let mut fields: Vec<String> = Vec::new();
+    if ifs.is_empty() {
+        fields.push(line.to_string());
+        return fields;
+    }
+    let mut rest = line.trim_start_matches(is_ifs_space);
+    while !rest.is_empty() {
+        if fields.len() + 1 == max_fields {
+            fields.push(rest.trim_end_matches(is_ifs_space).to_string());
+            break;
+        }
+        match rest.find(|c: char| ifs.contains(c)) {
+            Some(pos) => {
+                fields.push(rest[..pos].to_string());
+                // A single non-whitespace separator may be surrounded by IFS whitespace
+                let mut after = rest[pos..].trim_start_matches(is_ifs_space);
+                if let Some(c) = after.chars().next() {
+                    if ifs.contains(c) && !is_ifs_space(c) {
+                        after = after[c.len_utf8()..].trim_start_matches(is_ifs_space);
+                    }
+                }
+                rest = after;
+            },
+            None => {
+                fields.push(rest.to_string());
+                break;
+            }
+        }
+    }
+    fields
+}
+
+fn assign_fields(state: &mut ShellState, options: &ParsedOptions, line: &str) -> Result<(), String> {
+    let ifs = state.variables.get("IFS").unwrap_or(" \t\n".to_string());
+    if let Some(array) = options.value('a') {
+        let fields = split_fields(line, &ifs, usize::MAX);
+        return state.variables.set_array(array, fields);
+    }
+    if options.operands.is_empty() {
+        return state.variables.set("REPLY", line);
+    }
+    let fields = split_fields(line, &ifs, options.operands.len());
+    for (index, name) in options.operands.iter().enumerate() {
+        state.variables.set(name, fields.get(index).map(|field| field.as_str()).unwrap_or(""))?;
+    }
+    Ok(())
+}
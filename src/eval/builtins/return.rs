@@ -0,0 +1,35 @@
+use std::io::Write;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+
+pub struct Return;
+
+impl Builtin for Return {
+    fn name(&self) -> &str {
+        "return"
+    }
+
+    fn help(&self) -> &str {
+        "Leave the running function or sourced file with a status of N. If N is omitted, the status is that of the last command executed."
+    }
+
+    fn usage(&self) -> &str {
+        "return [n]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        if state.call_depth == 0 {
+            return Err(ShellError::Builtin(BuiltinError::new(1, "return: can only `return' from a function or sourced script".to_string())));
+        }
+        match args.len() {
+            0 => Err(ShellError::ReturnRequest(state.status)),
+            1 => match args[0].parse::<i64>() {
+                Ok(code) => Err(ShellError::ReturnRequest((code & 0xff) as i32)),
+                Err(_) => Err(ShellError::Builtin(BuiltinError::new(2, format!("return: {}: numeric argument required", args[0]))))
+            },
+            _ => Err(ShellError::Builtin(BuiltinError::new(1, "return: too many arguments".to_string())))
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::config::EditMode;
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+use crate::eval::builtins::declare::quote_value;
+
+// Options of `set -o`, with the editing mode each one selects
+const OPTIONS: [(&str, EditMode); 2] = [("emacs", EditMode::Emacs), ("vi", EditMode::Vi)];
+
+pub struct Set;
+
+impl Builtin for Set {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn help(&self) -> &str {
+        "Set shell options and positional parameters. -o OPTION turns OPTION on and +o OPTION turns it off, where OPTION is emacs or vi to choose the line editing keymap. Without OPTION, -o lists the options and +o prints the commands that restore them. The remaining ARGs become the positional parameters $1, $2... Without arguments, lists the shell variables."
+    }
+
+    fn usage(&self) -> &str {
+        "set [-o option] [+o option] [--] [arg ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            for name in state.variables.names() {
+                if let Some(value) = state.variables.get(&name) {
+                    stdout.queue(Print(format!("{}={}\n", name, quote_value(&value)))).unwrap();
+                }
+            }
+            return Ok(0);
+        }
+        let mut index = 0;
+        while let Some(arg) = args.get(index) {
+            match arg.as_str() {
+                "--" => {
+                    index += 1;
+                    break;
+                },
+                "-o" | "+o" => {
+                    let enable = arg == "-o";
+                    let Some(option) = args.get(index + 1) else {
+                        for (name, mode) in OPTIONS {
+                            let on = state.edit_mode == mode;
+                            if enable {
+                                stdout.queue(Print(format!("{:<15}\t{}\n", name, if on { "on" } else { "off" }))).unwrap();
+                            } else {
+                                stdout.queue(Print(format!("set {}o {}\n", if on { '-' } else { '+' }, name))).unwrap();
+                            }
+                        }
+                        return Ok(0);
+                    };
+                    let Some((_, mode)) = OPTIONS.iter().find(|(name, _)| name == option) else {
+                        return Err(ShellError::Builtin(BuiltinError::new(2, format!("set: {}: invalid option name", option))));
+                    };
+                    // Turning a keymap off selects the other one
+                    state.edit_mode = match (enable, mode) {
+                        (true, mode) => *mode,
+                        (false, EditMode::Vi) => EditMode::Emacs,
+                        (false, EditMode::Emacs) => EditMode::Vi
+                    };
+                    index += 2;
+                },
+                _ if arg.len() > 1 && (arg.starts_with('-') || arg.starts_with('+')) => {
+                    return Err(ShellError::Builtin(BuiltinError::new(2, format!("set: {}: invalid option", arg))));
+                },
+                _ => break
+            }
+        }
+        // `set -o vi` alone keeps the positional parameters, `set --` clears them
+        if index < args.len() || args.get(index.wrapping_sub(1)).is_some_and(|arg| arg == "--") {
+            let arg0 = state.positional.first().cloned().unwrap_or_default();
+            state.positional = [vec![arg0], args[index..].to_vec()].concat();
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::io::Write;
+
+use crate::core::cmdoutput::CmdOutput;
+use crate::core::core::ShellState;
+use crate::core::error::{io_error_message, ShellError};
+use crate::core::osstr::{bytes_to_word, word_to_os};
+use crate::eval::builtins::{Builtin, BuiltinError};
+use crate::eval::eval::eval_script;
+use crate::eval::traps::run_trap;
+
+// Registered both as `source` and `.`
+pub struct Source {
+    pub name: &'static str
+}
+
+impl Builtin for Source {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn help(&self) -> &str {
+        "Read and execute commands from FILENAME in the current shell. ARGUMENTS become the positional parameters while it runs. The RETURN trap runs once the file has been executed."
+    }
+
+    fn usage(&self) -> &str {
+        if self.name == "." {
+            return ". filename [arguments]";
+        }
+        "source filename [arguments]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let Some(filename) = args.first() else {
+            return Err(ShellError::Builtin(BuiltinError::new(2, format!("{}: filename argument required", self.name))));
+        };
+        let script = match fs::read(word_to_os(filename)) {
+            Ok(script) => bytes_to_word(&script),
+            Err(error) => return Err(ShellError::Builtin(BuiltinError::new(1, format!("{}: {}: {}", self.name, filename, io_error_message(&error)))))
+        };
+        // Extra arguments become the positional parameters while the file runs
+        let saved_positional = if args.len() > 1 {
+            let arg0 = state.positional.first().cloned().unwrap_or_default();
+            Some(std::mem::replace(&mut state.positional, [vec![arg0], args[1..].to_vec()].concat()))
+        } else {
+            None
+        };
+        // The output is captured so that redirections of the command apply to it
+        state.captures.push(CmdOutput::new());
+        state.call_depth += 1;
+        let result = match eval_script(state, &script, filename) {
+            // `return` leaves the file early
+            Err(ShellError::ReturnRequest(code)) => {
+                state.status = code;
+                Ok(())
+            },
+            result => result
+        };
+        state.call_depth -= 1;
+        if let Some(positional) = saved_positional {
+            state.positional = positional;
+        }
+        let trap_result = run_trap(state, "RETURN");
+        let output = state.captures.pop().unwrap_or_else(CmdOutput::new);
+        if trap_result.is_ok() && result.is_ok() {
+            let _ = stdout.write_all(&output.stdout);
+            let _ = stderr.write_all(&output.stderr);
+        } else if let Some(capture) = state.captures.last_mut() {
+            // An error such as `exit` discards the command's output: what was printed goes on directly
+            capture.stdout.extend(output.stdout);
+            capture.stderr.extend(output.stderr);
+        } else {
+            let _ = state.stdout.write_all(&output.stdout);
+            let _ = state.stderr.write_all(&output.stderr);
+        }
+        trap_result?;
+        result?;
+        Ok(state.status)
+    }
+}
@@ -0,0 +1,46 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::Builtin;
+
+pub struct Times;
+
+// `0m1.250s`
+fn format_time(time: &libc::timeval) -> String {
+    let millis = time.tv_sec * 1000 + time.tv_usec / 1000;
+    format!("{}m{}.{:03}s", millis / 60000, (millis % 60000) / 1000, millis % 1000)
+}
+
+fn get_usage(who: libc::c_int) -> libc::rusage {
+    unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        libc::getrusage(who, &mut usage);
+        usage
+    }
+}
+
+impl Builtin for Times {
+    fn name(&self) -> &str {
+        "times"
+    }
+
+    fn help(&self) -> &str {
+        "Print the user and system times used by the shell, then by the processes it ran."
+    }
+
+    fn usage(&self) -> &str {
+        "times"
+    }
+
+    fn run(&self, _state: &mut ShellState, _args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        for who in [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN] {
+            let usage = get_usage(who);
+            stdout.queue(Print(format!("{} {}\n", format_time(&usage.ru_utime), format_time(&usage.ru_stime)))).unwrap();
+        }
+        Ok(0)
+    }
+}
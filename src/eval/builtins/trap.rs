@@ -0,0 +1,98 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::signals::{set_signal_action, signal_number, SignalAction, SIGNALS};
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+use crate::eval::traps::trap_name;
+
+pub struct Trap;
+
+fn format_trap(name: &str, command: &str) -> String {
+    format!("trap -- '{}' {}\n", command.replace('\'', "'\\''"), name)
+}
+
+impl Builtin for Trap {
+    fn name(&self) -> &str {
+        "trap"
+    }
+
+    fn help(&self) -> &str {
+        "Run ACTION when the shell receives one of the signals SIGSPEC, or on the EXIT, ERR (a command failed), DEBUG (before each command) and RETURN (a function or a sourced script finished) conditions. An empty ACTION ignores the signal, `-` resets it to its default behavior. -p prints the traps in a reusable form, -l lists signal names."
+    }
+
+    fn usage(&self) -> &str {
+        "trap [-lp] [[action] sigspec ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        // `trap - INT` resets, so a lone `-` is not an option here
+        let (options, operands) = if args.first().is_some_and(|arg| arg == "-") {
+            (parse_options("trap", &[], "lp", "")?, args.to_vec())
+        } else {
+            let options = parse_options("trap", args, "lp", "")?;
+            let operands = options.operands.clone();
+            (options, operands)
+        };
+        if options.has('l') {
+            for (name, number) in SIGNALS.iter() {
+                stdout.queue(Print(format!("{:2}) SIG{}\n", number, name))).unwrap();
+            }
+            return Ok(0);
+        }
+        if operands.is_empty() || options.has('p') {
+            let mut names: Vec<String> = Vec::new();
+            for spec in &operands {
+                match trap_name(spec) {
+                    Some(name) => names.push(name),
+                    None => return Err(ShellError::Builtin(BuiltinError::new(1, format!("trap: {}: invalid signal specification", spec))))
+                }
+            }
+            if names.is_empty() {
+                names = state.traps.keys().cloned().collect();
+                names.sort();
+            }
+            for name in names {
+                if let Some(command) = state.traps.get(&name) {
+                    stdout.queue(Print(format_trap(&name, command))).unwrap();
+                }
+            }
+            return Ok(0);
+        }
+        // A lone signal spec, or a leading signal number, means reset like `trap - spec`
+        let (action, specs) = if operands.len() == 1 || operands[0].parse::<u32>().is_ok() {
+            (None, &operands[..])
+        } else if operands[0] == "-" {
+            (None, &operands[1..])
+        } else {
+            (Some(operands[0].clone()), &operands[1..])
+        };
+        for spec in specs {
+            let Some(name) = trap_name(spec) else {
+                return Err(ShellError::Builtin(BuiltinError::new(1, format!("trap: {}: invalid signal specification", spec))));
+            };
+            if let Some(signal) = signal_number(&name) {
+                let signal_action = match &action {
+                    None => SignalAction::Default,
+                    Some(command) if command.is_empty() => SignalAction::Ignore,
+                    Some(_) => SignalAction::Trap
+                };
+                if signal == libc::SIGKILL || signal == libc::SIGSTOP || !set_signal_action(signal, signal_action) {
+                    return Err(ShellError::Builtin(BuiltinError::new(1, format!("trap: {}: cannot trap signal", spec))));
+                }
+            }
+            match &action {
+                Some(command) => {
+                    state.traps.insert(name, command.clone());
+                },
+                None => {
+                    state.traps.remove(&name);
+                }
+            }
+        }
+        Ok(0)
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{parse_options, Builtin};
+use crate::eval::functions::format_function;
+use crate::eval::resolve::{classify_command, CommandKind};
+
+pub struct Type;
+
+pub fn describe_command(state: &mut ShellState, name: &str, kind: &CommandKind) -> String {
+    match kind {
+        CommandKind::Alias(value) => format!("{} is aliased to `{}'", name, value),
+        CommandKind::Keyword => format!("{} is a shell keyword", name),
+        CommandKind::Function(body) => format!("{} is a function\n{}", name, format_function(name, body)),
+        CommandKind::Builtin => format!("{} is a shell builtin", name),
+        CommandKind::File(path) => {
+            let hashed = state.path_cache.get(name).is_some_and(|entry| entry.path == *path && entry.hits > 0);
+            if hashed {
+                format!("{} is hashed ({})", name, path.display())
+            } else {
+                format!("{} is {}", name, path.display())
+            }
+        }
+    }
+}
+
+impl Builtin for Type {
+    fn name(&self) -> &str {
+        "type"
+    }
+
+    fn help(&self) -> &str {
+        "Display how each NAME would be interpreted if used as a command name. -t prints a single word (alias, keyword, function, builtin or file), -p prints the path of files only, -P forces a PATH search, -f skips shell functions and -a shows every match instead of the first one."
+    }
+
+    fn usage(&self) -> &str {
+        "type [-aftpP] name [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("type", args, "aftpP", "")?;
+        let path_only = options.has('P');
+        let mut status = 0;
+        for name in &options.operands {
+            let kinds = classify_command(state, name, options.has('a'), path_only, options.has('f'));
+            if kinds.is_empty() {
+                if !options.has('t') && !options.has('p') && !path_only {
+                    stderr.queue(Print(format!("type: {}: not found\n", name))).unwrap();
+                }
+                status = 1;
+                continue;
+            }
+            for kind in kinds {
+                if options.has('t') {
+                    stdout.queue(Print(format!("{}\n", kind.kind_name()))).unwrap();
+                } else if options.has('p') || path_only {
+                    if let CommandKind::File(path) = kind {
+                        stdout.queue(Print(format!("{}\n", path.display()))).unwrap();
+                    }
+                } else {
+                    let description = describe_command(state, name, &kind);
+                    stdout.queue(Print(format!("{}\n", description))).unwrap();
+                }
+            }
+        }
+        Ok(status)
+    }
+}
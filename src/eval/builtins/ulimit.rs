@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::{io_error_message, ShellError};
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+
+pub struct Ulimit;
+
+// getrlimit takes an enum on glibc and a plain int elsewhere
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+struct Limit {
+    flag: char,
+    description: &'static str,
+    unit: &'static str,
+    // Values are shown and given in multiples of this many bytes (or units)
+    scale: libc::rlim_t,
+    resource: Resource
+}
+
+const LIMITS: [Limit; 15] = [
+    Limit { flag: 'c', description: "core file size", unit: "blocks", scale: 1024, resource: libc::RLIMIT_CORE },
+    Limit { flag: 'd', description: "data seg size", unit: "kbytes", scale: 1024, resource: libc::RLIMIT_DATA },
+    Limit { flag: 'e', description: "scheduling priority", unit: "", scale: 1, resource: libc::RLIMIT_NICE },
+    Limit { flag: 'f', description: "file size", unit: "blocks", scale: 1024, resource: libc::RLIMIT_FSIZE },
+    Limit { flag: 'i', description: "pending signals", unit: "", scale: 1, resource: libc::RLIMIT_SIGPENDING },
+    Limit { flag: 'l', description: "max locked memory", unit: "kbytes", scale: 1024, resource: libc::RLIMIT_MEMLOCK },
+    Limit { flag: 'm', description: "max memory size", unit: "kbytes", scale: 1024, resource: libc::RLIMIT_RSS },
+    Limit { flag: 'n', description: "open files", unit: "", scale: 1, resource: libc::RLIMIT_NOFILE },
+    Limit { flag: 'q', description: "POSIX message queues", unit: "bytes", scale: 1, resource: libc::RLIMIT_MSGQUEUE },
+    Limit { flag: 'r', description: "real-time priority", unit: "", scale: 1, resource: libc::RLIMIT_RTPRIO },
+    Limit { flag: 's', description: "stack size", unit: "kbytes", scale: 1024, resource: libc::RLIMIT_STACK },
+    Limit { flag: 't', description: "cpu time", unit: "seconds", scale: 1, resource: libc::RLIMIT_CPU },
+    Limit { flag: 'u', description: "max user processes", unit: "", scale: 1, resource: libc::RLIMIT_NPROC },
+    Limit { flag: 'v', description: "virtual memory", unit: "kbytes", scale: 1024, resource: libc::RLIMIT_AS },
+    Limit { flag: 'x', description: "file locks", unit: "", scale: 1, resource: libc::RLIMIT_LOCKS }
+];
+
+fn ulimit_error(message: String) -> ShellError {
+    ShellError::Builtin(BuiltinError::new(1, format!("ulimit: {}", message)))
+}
+
+fn get_limit(limit: &Limit) -> Result<libc::rlimit, ShellError> {
+    let mut rlimit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(limit.resource, &mut rlimit) } == -1 {
+        return Err(ulimit_error(format!("{}: cannot get limit: {}", limit.description, io_error_message(&io::Error::last_os_error()))));
+    }
+    Ok(rlimit)
+}
+
+fn format_value(value: libc::rlim_t, limit: &Limit) -> String {
+    if value == libc::RLIM_INFINITY {
+        return "unlimited".to_string();
+    }
+    (value / limit.scale).to_string()
+}
+
+fn format_label(limit: &Limit) -> String {
+    let unit = if limit.unit.is_empty() { format!("-{}", limit.flag) } else { format!("{}, -{}", limit.unit, limit.flag) };
+    format!("{:<28}({}) ", limit.description, unit)
+}
+
+impl Builtin for Ulimit {
+    fn name(&self) -> &str {
+        "ulimit"
+    }
+
+    fn help(&self) -> &str {
+        "Show or set the resource limits of the shell and the processes it starts. -S and -H select the soft or hard limit (setting changes both by default), -a shows every limit. Without a resource option, acts on the file size (-f). LIMIT is a number in the unit of the resource, `unlimited`, `soft` or `hard`."
+    }
+
+    fn usage(&self) -> &str {
+        "ulimit [-SHa] [-cdefilmnqrstuvx] [limit]"
+    }
+
+    fn run(&self, _state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("ulimit", args, "SHacdefilmnqrstuvx", "")?;
+        let hard = options.has('H');
+        let soft = options.has('S') || !hard;
+        let mut selected: Vec<&Limit> = LIMITS.iter().filter(|limit| options.has(limit.flag)).collect();
+        if options.has('a') {
+            selected = LIMITS.iter().collect();
+        } else if selected.is_empty() {
+            selected.push(&LIMITS[3]);
+        }
+        let Some(value) = options.operands.first() else {
+            let labelled = selected.len() > 1;
+            for limit in selected {
+                let rlimit = get_limit(limit)?;
+                let current = if soft { rlimit.rlim_cur } else { rlimit.rlim_max };
+                let label = if labelled { format_label(limit) } else { String::new() };
+                stdout.queue(Print(format!("{}{}\n", label, format_value(current, limit)))).unwrap();
+            }
+            return Ok(0);
+        };
+        if options.has('a') || selected.len() > 1 {
+            return Err(ulimit_error("only one resource can be set at a time".to_string()));
+        }
+        let limit = selected[0];
+        let mut rlimit = get_limit(limit)?;
+        let new_value = match value.as_str() {
+            "unlimited" => libc::RLIM_INFINITY,
+            "soft" => rlimit.rlim_cur,
+            "hard" => rlimit.rlim_max,
+            _ => match value.parse::<libc::rlim_t>() {
+                Ok(number) => number.saturating_mul(limit.scale),
+                Err(_) => return Err(ulimit_error(format!("{}: invalid number", value)))
+            }
+        };
+        // Without -S or -H, both limits are set
+        if soft {
+            rlimit.rlim_cur = new_value;
+        }
+        if hard || !options.has('S') {
+            rlimit.rlim_max = new_value;
+        }
+        if unsafe { libc::setrlimit(limit.resource, &rlimit) } == -1 {
+            return Err(ulimit_error(format!("{}: cannot modify limit: {}", limit.description, io_error_message(&io::Error::last_os_error()))));
+        }
+        Ok(0)
+    }
+}
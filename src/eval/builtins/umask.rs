@@ -0,0 +1,128 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{parse_options, Builtin, BuiltinError};
+
+pub struct Umask;
+
+// umask(2) can only be read by setting it, so put it back right away
+fn get_umask() -> libc::mode_t {
+    unsafe {
+        let mask = libc::umask(0);
+        libc::umask(mask);
+        mask
+    }
+}
+
+// `u=rwx,g=rx,o=rx` for a mask of 022
+fn format_symbolic(mask: libc::mode_t) -> String {
+    let allowed = !mask & 0o777;
+    let classes: Vec<String> = [('u', 6), ('g', 3), ('o', 0)].iter().map(|(class, shift)| {
+        let bits = (allowed >> shift) & 0o7;
+        let perms: String = [('r', 0o4), ('w', 0o2), ('x', 0o1)].iter()
+                                                                  .filter(|(_, bit)| bits & bit != 0)
+                                                                  .map(|(perm, _)| *perm)
+                                                                  .collect();
+        format!("{}={}", class, perms)
+    }).collect();
+    classes.join(",")
+}
+
+// Applies a chmod-like mode (`u+w,go-rx`, `a=r`) to the permissions the mask allows
+fn parse_symbolic(mode: &str, mask: libc::mode_t) -> Option<libc::mode_t> {
+    let mut allowed = !mask & 0o777;
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who: libc::mode_t = 0;
+        while let Some(class) = chars.peek() {
+            who |= match class {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => break
+            };
+            chars.next();
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+        // Each operator applies to the permissions that follow it: `u+r-w`
+        let mut op = chars.next()?;
+        loop {
+            if !matches!(op, '+' | '-' | '=') {
+                return None;
+            }
+            let mut perms: libc::mode_t = 0;
+            let mut next_op = None;
+            for perm in chars.by_ref() {
+                perms |= match perm {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    _ => {
+                        next_op = Some(perm);
+                        break;
+                    }
+                };
+            }
+            match op {
+                '+' => allowed |= perms & who,
+                '-' => allowed &= !(perms & who),
+                _ => allowed = (allowed & !who) | (perms & who)
+            }
+            match next_op {
+                Some(next) => op = next,
+                None => break
+            }
+        }
+    }
+    Some(!allowed & 0o777)
+}
+
+impl Builtin for Umask {
+    fn name(&self) -> &str {
+        "umask"
+    }
+
+    fn help(&self) -> &str {
+        "Set the file creation mask to MODE, given in octal or symbolically like chmod (u=rwx,g=rx,o=). Without MODE, prints the current mask; -S prints it symbolically and -p in a form that can be reused as input."
+    }
+
+    fn usage(&self) -> &str {
+        "umask [-p] [-S] [mode]"
+    }
+
+    fn run(&self, _state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("umask", args, "pS", "")?;
+        let mask = get_umask();
+        let Some(mode) = options.operands.first() else {
+            let value = if options.has('S') { format_symbolic(mask) } else { format!("{:04o}", mask) };
+            let prefix = if options.has('p') { if options.has('S') { "umask -S " } else { "umask " } } else { "" };
+            stdout.queue(Print(format!("{}{}\n", prefix, value))).unwrap();
+            return Ok(0);
+        };
+        let new_mask = if mode.starts_with(|c: char| c.is_ascii_digit()) {
+            match libc::mode_t::from_str_radix(mode, 8) {
+                Ok(value) if value <= 0o777 => value,
+                _ => return Err(ShellError::Builtin(BuiltinError::new(1, format!("umask: {}: octal number out of range", mode))))
+            }
+        } else {
+            match parse_symbolic(mode, mask) {
+                Some(value) => value,
+                None => return Err(ShellError::Builtin(BuiltinError::new(1, format!("umask: `{}': invalid symbolic mode", mode))))
+            }
+        };
+        unsafe {
+            libc::umask(new_mask);
+        }
+        if options.has('S') {
+            stdout.queue(Print(format!("{}\n", format_symbolic(new_mask)))).unwrap();
+        }
+        Ok(0)
+    }
+}
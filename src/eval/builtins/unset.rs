@@ -0,0 +1,60 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::{Builtin, BuiltinError};
+use crate::parser::tokenizer::is_name;
+
+pub struct Unset;
+
+impl Builtin for Unset {
+    fn name(&self) -> &str {
+        "unset"
+    }
+
+    fn help(&self) -> &str {
+        "Remove each variable NAME, or a single element of an array with NAME[subscript]. With -f, NAME is a function. Readonly variables cannot be unset."
+    }
+
+    fn usage(&self) -> &str {
+        "unset [-fv] [name | name[subscript] ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let mut functions = false;
+        let mut operands = args;
+        while let Some(arg) = operands.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+            operands = &operands[1..];
+            if arg == "--" {
+                break;
+            }
+            for flag in arg[1..].chars() {
+                match flag {
+                    'f' => functions = true,
+                    'v' => functions = false,
+                    _ => return Err(ShellError::Builtin(BuiltinError::new(2, format!("unset: -{}: invalid option", flag))))
+                }
+            }
+        }
+        let mut status = 0;
+        for arg in operands {
+            if functions {
+                state.functions.remove(arg);
+                continue;
+            }
+            let result = match arg.find('[') {
+                Some(pos) if arg.ends_with(']') && is_name(&arg[..pos]) => state.variables.unset_element(&arg[..pos], &arg[pos + 1..arg.len() - 1]),
+                None if is_name(arg) => state.variables.unset(arg),
+                _ => Err(format!("`{}': not a valid identifier", arg))
+            };
+            if let Err(message) = result {
+                stderr.queue(Print(format!("unset: {}\n", message))).unwrap();
+                status = 1;
+            }
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,61 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::builtins::Builtin;
+
+pub struct Wait;
+
+impl Builtin for Wait {
+    fn name(&self) -> &str {
+        "wait"
+    }
+
+    fn help(&self) -> &str {
+        "Wait for the background jobs given by PID or JOBSPEC and return the exit status of the last one. Without arguments, waits for every background job and returns 0."
+    }
+
+    fn usage(&self) -> &str {
+        "wait [pid | jobspec ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, _stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            let pids: Vec<u32> = state.jobs.get_jobs().iter().map(|job| job.pid).collect();
+            for pid in pids {
+                state.jobs.wait(pid);
+            }
+            return Ok(0);
+        }
+        let mut status = 0;
+        for arg in args {
+            let job = if arg.starts_with('%') {
+                state.jobs.find_spec(arg)
+            } else {
+                match arg.parse::<u32>() {
+                    Ok(pid) => state.jobs.find_pid(pid),
+                    Err(_) => {
+                        stderr.queue(Print(format!("wait: `{}': not a pid or valid job spec\n", arg))).unwrap();
+                        status = 2;
+                        continue;
+                    }
+                }
+            };
+            match job.map(|job| job.pid) {
+                Some(pid) => status = state.jobs.wait(pid).unwrap_or(127),
+                None => {
+                    if arg.starts_with('%') {
+                        stderr.queue(Print(format!("wait: {}: no such job\n", arg))).unwrap();
+                    } else {
+                        stderr.queue(Print(format!("wait: pid {} is not a child of this shell\n", arg))).unwrap();
+                    }
+                    status = 127;
+                }
+            }
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,41 @@
+use std::io::Write;
+
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::pathcache::search_path;
+use crate::eval::builtins::{parse_options, Builtin};
+
+pub struct Which;
+
+impl Builtin for Which {
+    fn name(&self) -> &str {
+        "which"
+    }
+
+    fn help(&self) -> &str {
+        "Print the full path of each command NAME found in PATH. With -a, every matching executable is printed."
+    }
+
+    fn usage(&self) -> &str {
+        "which [-a] name [name ...]"
+    }
+
+    fn run(&self, state: &mut ShellState, args: &[String], _stdin: &Option<Vec<u8>>, stdout: &mut dyn Write, _stderr: &mut dyn Write) -> Result<i32, ShellError> {
+        let options = parse_options("which", args, "a", "")?;
+        let path_var = state.path_cache.get_path_var();
+        let mut status = 0;
+        for name in &options.operands {
+            let paths = search_path(name, &path_var, options.has('a'));
+            if paths.is_empty() {
+                status = 1;
+            }
+            for path in paths {
+                stdout.queue(Print(format!("{}\n", path.display()))).unwrap();
+            }
+        }
+        Ok(status)
+    }
+}
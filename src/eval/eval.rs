@@ -1,85 +1,296 @@
+use std::env;
+use std::ffi::OsString;
+use std::time::Duration;
+
 use crate::core::error::ShellError;
+use crate::eval::traps::{run_pending_traps, run_trap};
 use crate::crossterm::QueueableCommand;
 use crate::crossterm::style::Print;
 
-use crate::eval::execute::execute_program;
-use crate::parser::expand::expand_tokens;
-use crate::eval::expression::parse_tokens;
+use crate::eval::execute::{execute_program, spawn_background, ExecutionError};
+use crate::parser::expand::{expand_aliases, expand_tokens};
+use crate::eval::expression::{check_syntax, parse_tokens};
 use crate::core::cmdoutput::CmdOutput;
+use crate::core::fsio::{flush_output, report_write_error};
+use crate::core::osstr::{os_to_word, word_to_os};
 use crate::core::core::ShellState;
-use crate::eval::expression::ExpressionGroup;
+use crate::core::variables::{parse_assignment, Assignment};
+use crate::eval::expression::{Expression, ExpressionGroup};
+use crate::eval::functions::{call_function, define_function};
+use crate::eval::timing::Timer;
+use crate::features::suggestions::suggest_command;
+use crate::eval::redirections::{apply_shell_redirections, background_stdio, restore_shell_descriptors, save_shell_descriptors};
 use crate::eval::redirections::handle_input_redirections;
-use crate::eval::redirections::handle_output_redirections;
-use crate::parser::tokenizer::tokenize;
-use crate::eval::builtins::match_builtin;
+use crate::eval::redirections::{check_output_redirections, handle_output_redirections};
+use crate::parser::tokenizer::{tokenize_with_spans, Token, TokenizationErrorKind};
+use crate::eval::builtins::run_builtin;
+
+// Path to run for an external command: names containing a slash are used as is
+pub fn find_program(state: &mut ShellState, program: &str) -> Option<String> {
+    if program.contains('/') {
+        return Some(program.to_string());
+    }
+    state.path_cache.lookup(program).map(|path| os_to_word(path.as_os_str()))
+}
+
+// Starts the last stage of a `cmd &` line as a job. Builtins still run in the foreground.
+fn run_background(state: &mut ShellState, group: &ExpressionGroup, expr: &Expression, words: &[String], input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
+    let program = &words[0];
+    let args = words[1..].to_vec();
+    if state.functions.contains_key(program) || state.builtins.get(program).is_some() {
+        let mut out = run_function_or_program(state, program, &args, input)?;
+        handle_output_redirections(&expr.outputs, &mut out)?;
+        return Ok(out);
+    }
+    let Some(path) = find_program(state, program) else {
+        return command_not_found(state, program, &args);
+    };
+    let (stdout, stderr) = background_stdio(&expr.outputs)?;
+    let child = spawn_background(&path, program, &args, input, stdout, stderr)?;
+    let command: Vec<String> = group.expressions.iter().map(|stage| stage.words.join(" ")).collect();
+    let job = state.jobs.add(child, &command.join(" | "));
+    let mut out = CmdOutput::from_status(0);
+    out.stderr = format!("[{}] {}\n", job.id, job.pid).into_bytes();
+    Ok(out)
+}
+
+// Like bash, a `command_not_found_handle` function runs in place of the error, with the command as arguments
+fn command_not_found(state: &mut ShellState, program: &str, args: &[String]) -> Result<CmdOutput, ShellError> {
+    if state.functions.contains_key("command_not_found_handle") && !state.in_not_found_handler {
+        state.in_not_found_handler = true;
+        let result = call_function(state, "command_not_found_handle", &[vec![program.to_string()], args.to_vec()].concat());
+        state.in_not_found_handler = false;
+        return result;
+    }
+    Err(ShellError::Execution(ExecutionError::CommandNotFound(program.to_string(), suggest_command(state, program))))
+}
+
+// Runs a builtin or a program found in PATH, bypassing aliases and functions
+pub fn run_program(state: &mut ShellState, program: &str, args: &[String], input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
+    match run_builtin(state, program, args, input) {
+        Err(ShellError::NoBuiltin) => match find_program(state, program) {
+            Some(path) => execute_program(&path, program, args, input),
+            None => command_not_found(state, program, args)
+        },
+        result => result
+    }
+}
+
+// Functions take precedence over builtins and programs
+fn run_function_or_program(state: &mut ShellState, program: &str, args: &[String], input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
+    if state.functions.contains_key(program) {
+        return call_function(state, program, args);
+    }
+    run_program(state, program, args, input)
+}
+
+// `name=value` words on their own set shell variables
+fn run_assignments(state: &mut ShellState, assignments: &[Assignment]) -> CmdOutput {
+    let mut out = CmdOutput::from_status(0);
+    for assignment in assignments {
+        if let Err(message) = state.assign(assignment) {
+            out.stderr.extend_from_slice(format!("{}\n", message).as_bytes());
+            out.status = Some(1);
+        }
+    }
+    out
+}
+
+// `name=value cmd`: the variables are only in the environment of cmd. Returns the previous values.
+fn push_temporary_env(assignments: &[Assignment]) -> Vec<(String, Option<OsString>)> {
+    let mut saved = Vec::new();
+    for assignment in assignments {
+        saved.push((assignment.name.clone(), env::var_os(&assignment.name)));
+        env::set_var(&assignment.name, word_to_os(&assignment.value));
+    }
+    saved
+}
+
+fn pop_temporary_env(saved: Vec<(String, Option<OsString>)>) {
+    for (name, value) in saved.into_iter().rev() {
+        match value {
+            Some(value) => env::set_var(name, value),
+            None => env::remove_var(name)
+        }
+    }
+}
+
+// Ends a pipeline early, the failing stage's status is the last one in PIPESTATUS
+fn stage_failed(state: &mut ShellState, mut statuses: Vec<String>, error: ShellError) -> Result<Option<CmdOutput>, ShellError> {
+    statuses.push(error.status().to_string());
+    let _ = state.variables.set_array("PIPESTATUS", statuses);
+    Err(error)
+}
 
 pub fn run_command(state: &mut ShellState, group: &ExpressionGroup) -> Result<Option<CmdOutput>, ShellError> {
     let mut output: Option<CmdOutput> = None;
+    // stderr of every stage but the last, which would otherwise be lost in the pipe
+    let mut pipeline_stderr: Vec<u8> = Vec::new();
+    // Exit status of every stage, kept in the PIPESTATUS array
+    let mut statuses: Vec<String> = Vec::new();
     for expr in &group.expressions {
-        let program = &expr.words[0];
-        let args = expr.words[1..].to_vec();
-        let mut input:  Option<Vec<u8>> = None;
-        if let Ok(res) = handle_input_redirections(&expr.inputs) {
-            if res.is_some() {
-                input = res;
-            } else if let Some(out) = &output {
-                input = Some(out.stdout.clone());
+        let assignments: Vec<Assignment> = expr.words.iter().enumerate().map_while(|(index, word)| {
+            let mut assignment = parse_assignment(word)?;
+            assignment.elements = expr.arrays.get(&index).cloned();
+            Some(assignment)
+        }).collect();
+        let words = &expr.words[assignments.len()..];
+        let input: Option<Vec<u8>> = match handle_input_redirections(&expr.inputs) {
+            Ok(Some(res)) => Some(res),
+            Ok(None) => output.as_ref().map(|out| out.stdout.clone()),
+            Err(error) => return stage_failed(state, statuses, error.into())
+        };
+        // Output files are created before the command runs, which does not run if one cannot be
+        if !expr.background && (words.len() != 1 || words[0] != "exec") {
+            if let Err(error) = check_output_redirections(&expr.outputs) {
+                return stage_failed(state, statuses, error.into());
             }
         }
-        match match_builtin(state, program, &args, &input) {
-            Ok(out) => {
-                output = Some(out);
+        let mut out = if words.is_empty() {
+            run_assignments(state, &assignments)
+        } else if words.len() == 1 && words[0] == "exec" && state.builtins.get("exec").is_some() {
+            // Nothing to replace the shell with, the redirections apply to the shell itself
+            if let Err(error) = apply_shell_redirections(&expr.inputs, &expr.outputs) {
+                return stage_failed(state, statuses, error.into());
             }
-            Err(error) => {
-                match error {
-                    ShellError::NoBuiltin => {
-                        match execute_program(program, &args, &input) {
-                            Ok(out) => {
-                                if let Ok(res) = handle_output_redirections(&expr.outputs, &out.stdout) {
-                                    if !res {
-                                        output = Some(out);
-                                    }
-                                }
-                            },
-                            Err(err) => return Err(err)
-                        }
-                    },
-                    error => return Err(error)
-                }
+            CmdOutput::from_status(0)
+        } else if let Some(assignment) = assignments.iter().find(|assignment| state.variables.get_attributes(&assignment.name).readonly) {
+            // Like bash, the command does not run when a prefix assignment fails
+            let mut out = CmdOutput::from_status(1);
+            out.stderr.extend_from_slice(format!("{}: readonly variable\n", assignment.name).as_bytes());
+            out
+        } else {
+            let saved_env = push_temporary_env(&assignments);
+            state.array_arguments = expr.arrays.iter().filter(|(index, _)| **index > assignments.len())
+                .map(|(index, elements)| (index - assignments.len() - 1, elements.clone())).collect();
+            let result = if expr.background {
+                run_background(state, group, expr, words, &input)
+            } else if words[0] == "exec" && state.builtins.get("exec").is_some() {
+                // The program replaces the shell, so the redirections go to the shell's own descriptors
+                flush_output(state);
+                let saved = save_shell_descriptors();
+                let result = match apply_shell_redirections(&expr.inputs, &expr.outputs) {
+                    Ok(()) => run_function_or_program(state, &words[0], &words[1..], &input),
+                    Err(error) => Err(error.into())
+                };
+                restore_shell_descriptors(saved);
+                result
+            } else {
+                run_function_or_program(state, &words[0], &words[1..], &input)
+            };
+            pop_temporary_env(saved_env);
+            state.array_arguments.clear();
+            match result {
+                Ok(out) => out,
+                Err(error) => return stage_failed(state, statuses, error)
             }
+        };
+        if let Some(last) = words.last() {
+            state.last_argument = last.clone();
         }
+        if !expr.background {
+            if let Err(error) = handle_output_redirections(&expr.outputs, &mut out) {
+                return stage_failed(state, statuses, error.into());
+            }
+        }
+        statuses.push(out.status.unwrap_or(0).to_string());
+        if let Some(previous) = output.take() {
+            pipeline_stderr.extend(previous.stderr);
+            out.user_time += previous.user_time;
+            out.system_time += previous.system_time;
+        }
+        output = Some(out);
     }
+    if let Some(out) = output.as_mut() {
+        pipeline_stderr.append(&mut out.stderr);
+        out.stderr = pipeline_stderr;
+    }
+    let _ = state.variables.set_array("PIPESTATUS", statuses);
     return Ok(output);
 }
 
 // Eval
 
 pub fn eval_expr(state: &mut ShellState, expr: &String) -> Result<(), ShellError> {
-    match tokenize(expr) {
-        Ok(mut tokens) => {
-            if tokens.len() > 0 {
-                expand_tokens(state, &mut tokens);
-                match parse_tokens(&tokens) {
+    match tokenize_with_spans(expr) {
+        Ok((tokens, spans)) => {
+            check_syntax(&tokens, &spans).map_err(ShellError::Parser)?;
+            let mut start = 0;
+            while start < tokens.len() {
+                if let Some(length) = define_function(state, expr, &tokens[start..], &spans[start..])? {
+                    start += length;
+                    continue;
+                }
+                // Expand each command right before it runs, so that it sees variables set by the previous ones
+                let end = tokens[start..].iter().position(|token| matches!(token, Token::CommandSeparator | Token::Background))
+                                         .map_or(tokens.len(), |pos| start + pos + 1);
+                let mut command_tokens = tokens[start..end].to_vec();
+                start = end;
+                expand_aliases(state, &mut command_tokens);
+                expand_tokens(state, &mut command_tokens);
+                match parse_tokens(&command_tokens) {
                     Ok(groups) => {
                         for group in groups {
+                            run_trap(state, "DEBUG")?;
+                            let timer = group.time.map(Timer::start);
                             match run_command(state, &group) {
                                 Ok(out) => {
+                                    let mut report = None;
                                     if let Some(cmd_output) = out {
+                                        report = timer.as_ref().map(|timer| timer.report(state, cmd_output.user_time, cmd_output.system_time));
                                         if let Some(status) = cmd_output.status {
                                             state.status = status;
                                         }
-                                        if let Ok(cmd_out) = String::from_utf8(cmd_output.stdout) {
-                                            state.stdout.queue(Print(cmd_out)).unwrap();
+                                        if let Some(capture) = state.captures.last_mut() {
+                                            // Within a function, the output becomes the function's
+                                            capture.stdout.extend(cmd_output.stdout);
+                                            capture.stderr.extend(cmd_output.stderr);
+                                        } else {
+                                            // Output is passed through as is, whatever its encoding
+                                            let written = state.stdout.write_all(&cmd_output.stdout)
+                                                .and_then(|_| state.stdout.flush())
+                                                .and_then(|_| state.stderr.write_all(&cmd_output.stderr));
+                                            if let Err(error) = written {
+                                                report_write_error(state, &error);
+                                            }
                                         }
-                                        if let Ok(cmd_err) = String::from_utf8(cmd_output.stderr) {
-                                            state.stderr.queue(Print(cmd_err)).unwrap();
+                                    }
+                                    if let Some(report) = report.or_else(|| timer.map(|timer| timer.report(state, Duration::ZERO, Duration::ZERO))) {
+                                        let _ = state.stderr.queue(Print(report));
+                                    }
+                                    if group.negated {
+                                        state.status = if state.status == 0 { 1 } else { 0 };
+                                    } else if state.status != 0 {
+                                        run_trap(state, "ERR")?;
+                                    }
+                                }
+                                Err(error) => {
+                                    if let Some(timer) = timer {
+                                        let report = timer.report(state, Duration::ZERO, Duration::ZERO);
+                                        let _ = state.stderr.queue(Print(report));
+                                    }
+                                    if !matches!(error, ShellError::FileSystem(_) | ShellError::Execution(_)) {
+                                        return Err(error);
+                                    }
+                                    // Like a failing command, a redirection that cannot be done or a program
+                                    // that cannot start does not stop the line
+                                    state.status = error.status() as i32;
+                                    let message = format!("{}\n", String::from_utf8_lossy(&error.to_output(expr)));
+                                    match state.captures.last_mut() {
+                                        Some(capture) => capture.stderr.extend(message.into_bytes()),
+                                        None => {
+                                            let _ = state.stderr.queue(Print(message));
                                         }
                                     }
+                                    if !group.negated {
+                                        run_trap(state, "ERR")?;
+                                    } else {
+                                        state.status = 0;
+                                    }
                                 }
-                                Err(error) => return Err(error)
                             }
+                            run_pending_traps(state)?;
                         }
-                        return Ok(());
                     },
                     Err(error) => return Err(ShellError::Parser(error))
                 }
@@ -88,4 +299,42 @@ pub fn eval_expr(state: &mut ShellState, expr: &String) -> Result<(), ShellError
         },
         Err(error) => return Err(ShellError::Tokenization(error))
     };
-}
\ No newline at end of file
+}
+
+// Evaluates a script line by line. A line with unterminated quotes continues on the next one.
+// `name` is the script file, syntax errors are located with it
+pub fn eval_script(state: &mut ShellState, script: &str, name: &str) -> Result<(), ShellError> {
+    // $LINENO counts the lines of the script while it runs
+    let saved_lineno = state.lineno;
+    let result = eval_lines(state, script, name);
+    state.lineno = saved_lineno;
+    result
+}
+
+fn eval_lines(state: &mut ShellState, script: &str, name: &str) -> Result<(), ShellError> {
+    let mut expr = String::new();
+    let mut lines = script.lines().enumerate().peekable();
+    while let Some((index, line)) = lines.next() {
+        if !expr.is_empty() {
+            expr.push('\n');
+        } else {
+            state.lineno = index + 1;
+        }
+        expr.push_str(line);
+        match eval_expr(state, &expr) {
+            Ok(_) => (),
+            Err(ShellError::Tokenization(error)) if error.kind == TokenizationErrorKind::UnmatchedCharacter && lines.peek().is_some() => continue,
+            Err(ShellError::ExitRequest(code)) => return Err(ShellError::ExitRequest(code)),
+            Err(ShellError::ReturnRequest(code)) => return Err(ShellError::ReturnRequest(code)),
+            Err(error) => {
+                state.status = error.status() as i32;
+                if let Ok(error_str) = String::from_utf8(error.to_located_output(&expr, Some((name, state.lineno)))) {
+                    let _ = state.stderr.queue(Print(format!("{}\n", error_str)));
+                }
+                run_trap(state, "ERR")?;
+            }
+        }
+        expr.clear();
+    }
+    Ok(())
+}
@@ -1,53 +1,134 @@
-use std::{io::Write, process::{self, Child, Stdio}};
+use std::{io::{self, Read, Write}, os::unix::process::{CommandExt, ExitStatusExt}, path::Path, process::{self, Child, ExitStatus, Output, Stdio}, thread, time::Duration};
 
-use crate::core::{cmdoutput::CmdOutput, error::{ShellError, StatusEnum}};
+use crate::core::{cmdoutput::CmdOutput, error::{ShellError, StatusEnum}, osstr::word_to_os};
 
-#[derive(Debug, Copy, Clone)]
+// The program name the user typed is kept for the error message
+#[derive(Debug, Clone)]
 pub enum ExecutionError {
-    CommandNotFound = 127,
-    ExecutionFailed = 128,
-    FailedToWriteStdin = 129
+    CommandNotFound(String, Option<String>),  // along with a similar command name, if any
+    PermissionDenied(String),
+    NotExecutable(String),
+    IsADirectory(String),
+    ExecutionFailed,
+    FailedToWriteStdin
 }
 
 impl StatusEnum for ExecutionError {
     fn status(&self) -> u16 {
-        *self as u16
+        match self {
+            ExecutionError::CommandNotFound(_, _) => 127,
+            ExecutionError::PermissionDenied(_) | ExecutionError::NotExecutable(_) | ExecutionError::IsADirectory(_) => 126,
+            ExecutionError::ExecutionFailed => 128,
+            ExecutionError::FailedToWriteStdin => 129
+        }
     }
 }
 
-pub fn spawn_program(program: &str, args: &Vec<String>, input: &Option<Vec<u8>>) -> Result<Child, ShellError> {
-  let mut process = process::Command::new(program);
-  process.args(args)
+// Why `program` could not be started, `arg0` being the name it was invoked as
+pub fn spawn_error(program: &str, arg0: &str, error: &io::Error) -> ExecutionError {
+  if Path::new(program).is_dir() {
+      return ExecutionError::IsADirectory(arg0.to_string());
+  }
+  match error.raw_os_error() {
+      Some(libc::ENOENT) => ExecutionError::CommandNotFound(arg0.to_string(), None),
+      Some(libc::EACCES) | Some(libc::EPERM) => ExecutionError::PermissionDenied(arg0.to_string()),
+      Some(libc::ENOEXEC) => ExecutionError::NotExecutable(arg0.to_string()),
+      _ => ExecutionError::ExecutionFailed
+  }
+}
+
+// `program` is the path to run, `arg0` the name it is invoked as
+pub fn spawn_program(program: &str, arg0: &str, args: &[String]) -> Result<Child, ShellError> {
+  let mut process = process::Command::new(word_to_os(program));
+  process.arg0(word_to_os(arg0))
+      .args(args.iter().map(|arg| word_to_os(arg)))
       .stdin(Stdio::piped()) // Allow piping input
       .stdout(Stdio::piped()) // Capture stdout
       .stderr(Stdio::piped()); // Capture stderr
 
   // Spawn the process
+  match process.spawn() {
+      Ok(child) => Ok(child),
+      Err(error) => Err(ShellError::Execution(spawn_error(program, arg0, &error)))
+  }
+}
+
+// Writes the input to the program from a thread, so that neither side blocks on a full pipe
+fn feed_stdin(child: &mut Child, input: &Option<Vec<u8>>) -> Option<thread::JoinHandle<io::Result<()>>> {
+  let (Some(input_data), Some(mut stdin)) = (input.clone(), child.stdin.take()) else {
+      return None;
+  };
+  Some(thread::spawn(move || match stdin.write_all(&input_data) {
+      // The program may stop reading before the end, like `head`
+      Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+      result => result
+  }))
+}
+
+// Starts a program that keeps running once the command line returns, writing straight to the given streams
+pub fn spawn_background(program: &str, arg0: &str, args: &[String], input: &Option<Vec<u8>>, stdout: Stdio, stderr: Stdio) -> Result<Child, ShellError> {
+  let mut process = process::Command::new(word_to_os(program));
+  process.arg0(word_to_os(arg0))
+      .args(args.iter().map(|arg| word_to_os(arg)))
+      .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+      .stdout(stdout)
+      .stderr(stderr);
+
   let mut child = match process.spawn() {
       Ok(child) => child,
-      Err(_error) => {
-          return Err(ShellError::Execution(ExecutionError::CommandNotFound));
+      Err(error) => {
+          return Err(ShellError::Execution(spawn_error(program, arg0, &error)));
       }
   };
 
-  // If there's input, write it to the child's stdin
-  if let Some(input_data) = input {
-      if let Some(stdin) = child.stdin.as_mut() {
-          if let Err(_) = stdin.write_all(&input_data) {
-              return Err(ShellError::Execution(ExecutionError::FailedToWriteStdin));
-          }
+  // Nothing waits for the input to be written, a large one does not block the shell
+  feed_stdin(&mut child, input);
+  Ok(child)
+}
+
+pub fn timeval_duration(time: &libc::timeval) -> Duration {
+  Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+  thread::spawn(move || {
+      let mut data = Vec::new();
+      if let Some(mut pipe) = pipe {
+          let _ = pipe.read_to_end(&mut data);
       }
-  }
+      data
+  })
+}
 
-  return Ok(child)
+// Like Child::wait_with_output, but waits with wait4 to also get the CPU time the program used
+fn wait_with_usage(mut child: Child) -> Result<CmdOutput, ShellError> {
+  drop(child.stdin.take());
+  let stdout = read_pipe(child.stdout.take());
+  let stderr = read_pipe(child.stderr.take());
+  let mut status: libc::c_int = 0;
+  let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+  while unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage) } < 0 {
+      if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+          return Err(ShellError::Execution(ExecutionError::ExecutionFailed));
+      }
+  }
+  let output = Output {
+      status: ExitStatus::from_raw(status),
+      stdout: stdout.join().unwrap_or_default(),
+      stderr: stderr.join().unwrap_or_default()
+  };
+  let mut out = CmdOutput::from_output(&output);
+  out.user_time = timeval_duration(&usage.ru_utime);
+  out.system_time = timeval_duration(&usage.ru_stime);
+  Ok(out)
 }
 
-pub fn execute_program(program: &str, args: &Vec<String>, input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
-  match spawn_program(program, args, input) {
-      Ok(child) => match child.wait_with_output() {
-          Ok(output) => Ok(CmdOutput::from_output(&output)),
-          Err(_) => Err(ShellError::Execution(ExecutionError::ExecutionFailed)),
-      },
-      Err(error) => Err(error)
+pub fn execute_program(program: &str, arg0: &str, args: &[String], input: &Option<Vec<u8>>) -> Result<CmdOutput, ShellError> {
+  let mut child = spawn_program(program, arg0, args)?;
+  let writer = feed_stdin(&mut child, input);
+  let output = wait_with_usage(child)?;
+  match writer.map(|writer| writer.join()) {
+      Some(Ok(Err(_))) | Some(Err(_)) => Err(ShellError::Execution(ExecutionError::FailedToWriteStdin)),
+      _ => Ok(output)
   }
 }
@@ -1,22 +1,147 @@
+use std::collections::HashMap;
 use std::vec::Vec;
 use core::slice::Iter;
 use std::iter::Peekable;
 
-use crate::{core::error::StatusEnum, parser::tokenizer::{RedirectionType, Token}};
+use crate::{core::error::StatusEnum, eval::builtins::printf::shell_quote, parser::tokenizer::{ConditionType, RedirectionType, Span, Token}};
 
 #[derive(Debug, Copy, Clone)]
-pub enum ParserError {
+pub enum ParserErrorKind {
     InvalidBackground = 228,
     InvalidPipe = 229,
-    InvalidRedirection = 230
+    InvalidRedirection = 230,
+    InvalidOperator = 231,
+    InvalidSeparator = 232,
+    InvalidToken = 233
+}
+
+// Errors found before expansion know where they are in the input
+#[derive(Debug, Clone)]
+pub struct ParserError {
+    pub kind: ParserErrorKind,
+    pub span: Option<Span>,
+    pub message: String
+}
+
+impl ParserError {
+    fn new(kind: ParserErrorKind, message: String) -> ParserError {
+        ParserError { kind, span: None, message }
+    }
+
+    fn at(mut self, span: Span) -> ParserError {
+        self.span = Some(span);
+        self
+    }
 }
 
 impl StatusEnum for ParserError {
     fn status(&self) -> u16 {
-        *self as u16
+        self.kind as u16
     }
 }
 
+// How a token is named in error messages
+fn describe(token: &Token) -> String {
+    let text = match token {
+        Token::Word(word) => word.as_str(),
+        Token::CompoundWord(_) | Token::ArrayAssignment(_, _) => "word",
+        Token::Pipe => "|",
+        Token::Background => "&",
+        Token::Negate => "!",
+        Token::Subexpression(_) => "(",
+        Token::Redirection(rtype) => match rtype {
+            RedirectionType::Input => "<",
+            RedirectionType::Output => ">",
+            RedirectionType::Append => ">>",
+            RedirectionType::Heredoc => "<<",
+            RedirectionType::ErrOutput => "2>",
+            RedirectionType::ErrAppend => "2>>",
+            RedirectionType::ErrToOutput => "2>&1",
+            RedirectionType::OutputToError => ">&2"
+        },
+        Token::Operator(ConditionType::And) => "&&",
+        Token::Operator(ConditionType::Or) => "||",
+        Token::CommandSeparator => ";"
+    };
+    format!("`{}`", text)
+}
+
+fn is_word(token: &Token) -> bool {
+    matches!(token, Token::Word(_) | Token::CompoundWord(_) | Token::ArrayAssignment(_, _) | Token::Subexpression(_))
+}
+
+// Checks the structure of a command line before expansion, so that errors point into the input:
+// operators must follow a command, and file redirections must have a target
+pub fn check_syntax(tokens: &[Token], spans: &[Span]) -> Result<(), ParserError> {
+    // Operator after which a command is expected
+    let mut pending: Option<usize> = None;
+    let mut in_command = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let unexpected = || {
+            let after = match i.checked_sub(1).map(|previous| &tokens[previous]) {
+                Some(previous) if !is_word(previous) => format!(" after {}", describe(previous)),
+                _ => String::new()
+            };
+            format!("unexpected {}{}", describe(token), after)
+        };
+        match token {
+            Token::Pipe | Token::Operator(_) | Token::Background if !in_command => {
+                let kind = match token {
+                    Token::Pipe => ParserErrorKind::InvalidPipe,
+                    Token::Background => ParserErrorKind::InvalidBackground,
+                    _ => ParserErrorKind::InvalidOperator
+                };
+                return Err(ParserError::new(kind, unexpected()).at(spans[i]));
+            },
+            Token::CommandSeparator if pending.is_some() && !in_command => {
+                return Err(ParserError::new(ParserErrorKind::InvalidSeparator, unexpected()).at(spans[i]));
+            },
+            Token::Pipe | Token::Operator(_) => {
+                pending = Some(i);
+                in_command = false;
+            },
+            Token::Background | Token::CommandSeparator => {
+                pending = None;
+                in_command = false;
+            },
+            Token::Redirection(rtype) => {
+                if !in_command {
+                    let message = format!("expected a command before {}", describe(token));
+                    return Err(ParserError::new(ParserErrorKind::InvalidRedirection, message).at(spans[i]));
+                }
+                if *rtype != RedirectionType::ErrToOutput && *rtype != RedirectionType::OutputToError {
+                    match tokens.get(i + 1) {
+                        Some(target) if is_word(target) => i += 1,
+                        Some(target) => {
+                            let message = format!("unexpected {} after {}", describe(target), describe(token));
+                            return Err(ParserError::new(ParserErrorKind::InvalidRedirection, message).at(spans[i + 1]));
+                        },
+                        None => {
+                            let message = format!("expected a file name after {}", describe(token));
+                            return Err(ParserError::new(ParserErrorKind::InvalidRedirection, message).at(spans[i]));
+                        }
+                    }
+                }
+            },
+            // `!` in command position is a keyword, a command still has to follow
+            Token::Negate if !in_command => (),
+            _ => in_command = true
+        }
+        i += 1;
+    }
+    if let Some(operator) = pending.filter(|_| !in_command) {
+        let kind = match tokens[operator] {
+            Token::Pipe => ParserErrorKind::InvalidPipe,
+            _ => ParserErrorKind::InvalidOperator
+        };
+        let message = format!("expected a command after {}", describe(&tokens[operator]));
+        return Err(ParserError::new(kind, message).at(spans[operator]));
+    }
+    Ok(())
+}
+
 pub struct Redirection {
     pub rtype: RedirectionType,
     pub target: String
@@ -31,61 +156,107 @@ pub enum ExpressionGroupType {
 
 pub struct Expression {
     pub words: Vec<String>,
+    // Elements of the `name=(...)` words, by index in `words`
+    pub arrays: HashMap<usize, Vec<String>>,
     pub inputs: Vec<Redirection>,
     pub outputs: Vec<Redirection>,
     pub background: bool
 }
 
+// Report layout of a `time` pipeline: bash's, or the POSIX one with -p
+#[derive(Copy, Clone, PartialEq)]
+pub enum TimeFormat {
+    Default,
+    Posix
+}
+
 pub struct ExpressionGroup {
     pub expressions: Vec<Expression>,
-    pub gtype: ExpressionGroupType
+    pub gtype: ExpressionGroupType,
+    pub negated: bool,
+    pub time: Option<TimeFormat>
+}
+
+fn push_word(group: &mut ExpressionGroup, word: &str) {
+    if let Some(cmd) = group.expressions.last_mut() {
+        cmd.words.push(word.to_string());
+    } else {
+        group.expressions.push(Expression{
+            words: vec![word.to_string()],
+            arrays: HashMap::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            background: false
+        })
+    }
 }
 
+// `name=(...)` is shown as such but its elements are kept apart, for the assignment
+fn push_array_word(group: &mut ExpressionGroup, prefix: &str, elements: &[Token]) {
+    let elements: Vec<String> = elements.iter().filter_map(|element| match element {
+        Token::Word(word) => Some(word.clone()),
+        _ => None
+    }).collect();
+    let quoted: Vec<String> = elements.iter().map(|element| shell_quote(element)).collect();
+    push_word(group, &format!("{}({})", prefix, quoted.join(" ")));
+    if let Some(cmd) = group.expressions.last_mut() {
+        cmd.arrays.insert(cmd.words.len() - 1, elements);
+    }
+}
 
 pub fn parse_command(tokens_iter: &mut Peekable<Iter<Token>>) -> Result<ExpressionGroup, ParserError>  {
     let mut group: ExpressionGroup = ExpressionGroup{
         expressions: Vec::new(),
-        gtype: ExpressionGroupType::Single
+        gtype: ExpressionGroupType::Single,
+        negated: false,
+        time: None
     };
     while let Some(token) = tokens_iter.next() {
         match token {
-            Token::Word(word) => {
-                if let Some(cmd) = group.expressions.last_mut() {
-                    cmd.words.push(word.clone());
-                } else {
-                    group.expressions.push(Expression{
-                        words: vec![word.clone()],
-                        inputs: Vec::new(),
-                        outputs: Vec::new(),
-                        background: false
-                    })
+            // `!` and `time` are keywords in command position only
+            Token::Negate if group.expressions.is_empty() => {
+                group.negated = !group.negated;
+            },
+            Token::Negate => push_word(&mut group, "!"),
+            Token::Word(word) if word == "time" && group.expressions.is_empty() && group.time.is_none() => {
+                group.time = Some(TimeFormat::Default);
+                if matches!(tokens_iter.peek(), Some(Token::Word(option)) if option == "-p") {
+                    tokens_iter.next();
+                    group.time = Some(TimeFormat::Posix);
                 }
             },
+            Token::Word(word) => push_word(&mut group, word),
+            Token::ArrayAssignment(prefix, elements) => push_array_word(&mut group, prefix, elements),
             Token::Pipe => {
                 if let Some(_) = group.expressions.last() {
-                    if let Some(next_token) = tokens_iter.next() {
-                        match next_token {
-                            Token::Word(word) => {
-                                // Insert new command
-                                group.expressions.push(Expression{
-                                    words: vec![word.clone()],
-                                    inputs: Vec::new(),
-                                    outputs: Vec::new(),
-                                    background: false
-                                })
-                            },
-                            _ => return Err(ParserError::InvalidPipe)
+                    if let Some(next_token) = tokens_iter.peek() {
+                        if !is_word(next_token) {
+                            return Err(ParserError::new(ParserErrorKind::InvalidPipe, format!("unexpected {} after `|`", describe(next_token))));
                         }
+                        // Insert new command, its words follow
+                        group.expressions.push(Expression{
+                            words: Vec::new(),
+                            arrays: HashMap::new(),
+                            inputs: Vec::new(),
+                            outputs: Vec::new(),
+                            background: false
+                        })
                     } else {
-                        return Err(ParserError::InvalidPipe);
+                        return Err(ParserError::new(ParserErrorKind::InvalidPipe, "expected a command after `|`".to_string()));
                     }
                 } else {
-                    return Err(ParserError::InvalidPipe);
+                    return Err(ParserError::new(ParserErrorKind::InvalidPipe, "unexpected `|`".to_string()));
                 }
             },
             Token::Redirection(rtype) => {
                 if let Some(cmd) = group.expressions.last_mut() {
-                    if let Some(next_token) = tokens_iter.next() {
+                    if *rtype == RedirectionType::ErrToOutput || *rtype == RedirectionType::OutputToError {
+                        // Descriptor duplications have no target word
+                        cmd.outputs.push(Redirection{
+                            rtype: rtype.clone(),
+                            target: String::new()
+                        });
+                    } else if let Some(next_token) = tokens_iter.next() {
                         match next_token {
                             Token::Word(word) => {
                                 // Set current command redirection
@@ -94,21 +265,21 @@ pub fn parse_command(tokens_iter: &mut Peekable<Iter<Token>>) -> Result<Expressi
                                     target: (word).clone()
                                 };
                                 match rtype {
-                                    RedirectionType::Output | RedirectionType::Append => {
-                                        cmd.outputs.push(redirection);
-                                    },
                                     RedirectionType::Input | RedirectionType::Heredoc => {
                                         cmd.inputs.push(redirection);
+                                    },
+                                    _ => {
+                                        cmd.outputs.push(redirection);
                                     }
                                 }
                             },
-                            _ => return Err(ParserError::InvalidPipe)
+                            next_token => return Err(ParserError::new(ParserErrorKind::InvalidRedirection, format!("unexpected {} after {}", describe(next_token), describe(token))))
                         }
                     } else {
-                        return Err(ParserError::InvalidRedirection);
+                        return Err(ParserError::new(ParserErrorKind::InvalidRedirection, format!("expected a file name after {}", describe(token))));
                     }
                 } else {
-                    return Err(ParserError::InvalidRedirection);
+                    return Err(ParserError::new(ParserErrorKind::InvalidRedirection, format!("expected a command before {}", describe(token))));
                 }
             },
             Token::Background => {
@@ -116,17 +287,17 @@ pub fn parse_command(tokens_iter: &mut Peekable<Iter<Token>>) -> Result<Expressi
                     cmd.background = true;
                     break;
                 } else {
-                    return Err(ParserError::InvalidBackground)
+                    return Err(ParserError::new(ParserErrorKind::InvalidBackground, "unexpected `&`".to_string()))
                 }
             },
             Token::CommandSeparator => {
                 break;
             },
-            Token::Operator(_op) => {
-                unimplemented!()
+            Token::Operator(_) => {
+                return Err(ParserError::new(ParserErrorKind::InvalidOperator, format!("{} lists are not supported yet", describe(token))))
             }
-            _ => {
-                println!("undefined token encountered");
+            token => {
+                return Err(ParserError::new(ParserErrorKind::InvalidToken, format!("unexpected {}", describe(token))))
             }
         }
     }
@@ -140,8 +311,9 @@ pub fn parse_tokens(tokens: &Vec<Token>) -> Result<Vec<ExpressionGroup>, ParserE
     while let Some(_) = tokens_iter.peek() {
         match parse_command(&mut tokens_iter) {
             Ok(group) => {
+                // A lone `time` still reports the (null) time taken
                 match group.expressions.len() {
-                    0 => (),
+                    0 if group.time.is_none() => (),
                     _ => parsed_groups.push(group)
                 }
             },
@@ -149,4 +321,86 @@ pub fn parse_tokens(tokens: &Vec<Token>) -> Result<Vec<ExpressionGroup>, ParserE
         }
     }
     return Ok(parsed_groups);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize_with_spans;
+
+    fn syntax_error(input: &str) -> (String, Span) {
+        let (tokens, spans) = tokenize_with_spans(input).unwrap();
+        let error = check_syntax(&tokens, &spans).unwrap_err();
+        (error.message, error.span.unwrap())
+    }
+
+    #[test]
+    fn valid_lines_pass() {
+        for input in ["ls | wc -l", "! true && echo x > out", "a=(1 2); echo $a 2>&1 &", "cat < in >> out"] {
+            let (tokens, spans) = tokenize_with_spans(input).unwrap();
+            assert!(check_syntax(&tokens, &spans).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn operator_without_command_points_at_it() {
+        assert_eq!(syntax_error("| ls"), ("unexpected `|`".to_string(), Span::new(0, 1)));
+        assert_eq!(syntax_error("ls && && wc"), ("unexpected `&&` after `&&`".to_string(), Span::new(6, 8)));
+        assert_eq!(syntax_error("echo a | ;"), ("unexpected `;` after `|`".to_string(), Span::new(9, 10)));
+    }
+
+    #[test]
+    fn trailing_operator_points_at_it() {
+        assert_eq!(syntax_error("ls |"), ("expected a command after `|`".to_string(), Span::new(3, 4)));
+        assert_eq!(syntax_error("ls &&  "), ("expected a command after `&&`".to_string(), Span::new(3, 5)));
+    }
+
+    #[test]
+    fn redirection_errors_point_at_the_culprit() {
+        assert_eq!(syntax_error("> out"), ("expected a command before `>`".to_string(), Span::new(0, 1)));
+        assert_eq!(syntax_error("ls > | wc"), ("unexpected `|` after `>`".to_string(), Span::new(5, 6)));
+        assert_eq!(syntax_error("ls >"), ("expected a file name after `>`".to_string(), Span::new(3, 4)));
+    }
+
+    #[test]
+    fn spans_count_characters() {
+        assert_eq!(syntax_error("echo \u{e9}t\u{e9} |").1, Span::new(9, 10));
+    }
+
+    fn parse(input: &str) -> Vec<ExpressionGroup> {
+        let (tokens, _) = tokenize_with_spans(input).unwrap();
+        parse_tokens(&tokens).unwrap()
+    }
+
+    #[test]
+    fn negation_applies_to_the_whole_pipeline() {
+        let groups = parse("! grep x | wc -l");
+        assert!(groups[0].negated);
+        assert_eq!(groups[0].expressions.len(), 2);
+        assert!(!parse("! ! true")[0].negated);
+        let groups = parse("echo !");
+        assert!(!groups[0].negated);
+        assert_eq!(groups[0].expressions[0].words, vec!["echo", "!"]);
+    }
+
+    #[test]
+    fn time_is_a_keyword_in_command_position() {
+        let groups = parse("time -p sleep 1");
+        assert!(groups[0].time == Some(TimeFormat::Posix));
+        assert_eq!(groups[0].expressions[0].words, vec!["sleep", "1"]);
+        assert!(parse("time")[0].time == Some(TimeFormat::Default));
+        let groups = parse("echo time");
+        assert!(groups[0].time.is_none());
+        assert_eq!(groups[0].expressions[0].words, vec!["echo", "time"]);
+    }
+
+    #[test]
+    fn pipe_takes_any_word() {
+        let groups = parse("echo a | b=(1 2)");
+        assert_eq!(groups[0].expressions.len(), 2);
+        assert_eq!(groups[0].expressions[1].arrays.get(&0), Some(&vec!["1".to_string(), "2".to_string()]));
+        for input in ["echo a | (x)", "echo a |", "echo a | ;"] {
+            let (tokens, _) = tokenize_with_spans(input).unwrap();
+            assert!(parse_tokens(&tokens).is_err(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::core::cmdoutput::CmdOutput;
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::eval::eval::eval_script;
+use crate::eval::traps::run_trap;
+use crate::parser::tokenizer::{Span, Token, TokenizationError};
+
+fn is_word(token: &Token, text: &str) -> bool {
+    matches!(token, Token::Word(word) if word == text)
+}
+
+// `name() { body; }` or `function name { body; }` at the start of `tokens`, which may span several lines.
+// The function is registered and the number of tokens it took is returned.
+pub fn define_function(state: &mut ShellState, expr: &str, tokens: &[Token], spans: &[Span]) -> Result<Option<usize>, ShellError> {
+    let (name, mut open) = match tokens {
+        [Token::Word(keyword), Token::Word(name), ..] if keyword == "function" => (name.strip_suffix("()").unwrap_or(name), 2),
+        [Token::Word(name), Token::Subexpression(parameters), ..] if parameters.is_empty() => (name.as_str(), 2),
+        // `name()` without space is a single word
+        [Token::Word(word), ..] if word.len() > 2 && word.ends_with("()") => (&word[..word.len() - 2], 1),
+        _ => return Ok(None)
+    };
+    if name.contains(['=', '/', '$']) {
+        return Ok(None);
+    }
+    if is_word(&tokens[0], "function") && matches!(tokens.get(2), Some(Token::Subexpression(parameters)) if parameters.is_empty()) {
+        open = 3;
+    }
+    let chars: Vec<char> = expr.chars().collect();
+    let Some(token) = tokens.get(open) else {
+        // The body starts on the next line
+        return Err(ShellError::Tokenization(TokenizationError::unmatched(spans[open - 1], "()")));
+    };
+    if !is_word(token, "{") {
+        return Ok(None);
+    }
+    // Braces only count as the first word of a command
+    let in_command_position = |index: usize| {
+        let previous = &tokens[index - 1];
+        let gap: String = chars[spans[index - 1].end.min(chars.len())..spans[index].start.min(chars.len())].iter().collect();
+        (matches!(previous, Token::CommandSeparator | Token::Background | Token::Pipe | Token::Operator(_))
+            || is_word(previous, "{")
+            || gap.contains('\n'))
+    };
+    let mut depth = 0;
+    let mut close = None;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if index == open || (is_word(token, "{") && in_command_position(index)) {
+            depth += 1;
+        } else if is_word(token, "}") && in_command_position(index) {
+            depth -= 1;
+            if depth == 0 {
+                close = Some(index);
+                break;
+            }
+        }
+    }
+    let Some(close) = close else {
+        return Err(ShellError::Tokenization(TokenizationError::unmatched(spans[open], "{")));
+    };
+    let body: String = chars[spans[open].end..spans[close].start].iter().collect();
+    state.functions.insert(name.to_string(), body.trim().to_string());
+    state.status = 0;
+    if matches!(tokens.get(close + 1), Some(Token::CommandSeparator)) {
+        return Ok(Some(close + 2));
+    }
+    Ok(Some(close + 1))
+}
+
+// As printed by `type`
+pub fn format_function(name: &str, body: &str) -> String {
+    let lines: Vec<String> = body.lines().map(|line| format!("    {}", line.trim())).collect();
+    format!("{} () \n{{\n{}\n}}", name, lines.join("\n"))
+}
+
+// Runs a function with `args` as positional parameters. Variables declared `local` are
+// dropped once it returns, and its output is collected like a builtin's.
+pub fn call_function(state: &mut ShellState, name: &str, args: &[String]) -> Result<CmdOutput, ShellError> {
+    let Some(body) = state.functions.get(name).cloned() else {
+        return Err(ShellError::NoBuiltin);
+    };
+    let arg0 = state.positional.first().cloned().unwrap_or_default();
+    let saved_positional = std::mem::replace(&mut state.positional, [vec![arg0], args.to_vec()].concat());
+    state.variables.push_scope();
+    state.captures.push(CmdOutput::new());
+    state.call_depth += 1;
+    let result = eval_script(state, &body, name);
+    state.call_depth -= 1;
+    let trap_result = run_trap(state, "RETURN");
+    let mut output = state.captures.pop().unwrap_or_else(CmdOutput::new);
+    state.variables.pop_scope();
+    state.positional = saved_positional;
+    trap_result?;
+    output.status = match result {
+        Ok(()) => Some(state.status),
+        Err(ShellError::ReturnRequest(code)) => Some(code),
+        Err(error) => return Err(error)
+    };
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize_with_spans;
+
+    fn define(state: &mut ShellState, input: &str) -> Option<usize> {
+        let (tokens, spans) = tokenize_with_spans(input).unwrap();
+        define_function(state, input, &tokens, &spans).unwrap()
+    }
+
+    #[test]
+    fn definitions_take_their_tokens() {
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let mut state = ShellState::new(&mut stdout, &mut stderr);
+        assert_eq!(define(&mut state, "f() { echo a; }; echo b"), Some(7));
+        assert_eq!(state.functions.get("f").map(String::as_str), Some("echo a;"));
+        assert_eq!(define(&mut state, "function g { { echo x; }; }"), Some(10));
+        assert_eq!(state.functions.get("g").map(String::as_str), Some("{ echo x; };"));
+        assert_eq!(define(&mut state, "echo f() {"), None);
+        assert_eq!(define(&mut state, "h() echo"), None);
+    }
+
+    #[test]
+    fn calls_see_their_arguments_and_locals() {
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let mut state = ShellState::new(&mut stdout, &mut stderr);
+        state.variables.set("lsh_test_f", "outer").unwrap();
+        define(&mut state, "f() { local lsh_test_f=$1; echo $# $lsh_test_f; return 3; }");
+        let output = call_function(&mut state, "f", &["inner".to_string(), "x".to_string()]).unwrap();
+        assert_eq!(output.stdout, b"2 inner\n");
+        assert_eq!(output.status, Some(3));
+        assert_eq!(state.variables.get("lsh_test_f"), Some("outer".to_string()));
+        assert!(matches!(call_function(&mut state, "missing", &[]), Err(ShellError::NoBuiltin)));
+    }
+
+    #[test]
+    fn bodies_are_printed_indented() {
+        assert_eq!(format_function("f", "echo a\n  echo b"), "f () \n{\n    echo a\n    echo b\n}");
+    }
+}
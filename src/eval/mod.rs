@@ -2,4 +2,8 @@ pub mod builtins;
 pub mod eval;
 pub mod execute;
 pub mod expression;
+pub mod functions;
 pub mod redirections;
+pub mod resolve;
+pub mod traps;
+pub mod timing;
@@ -1,7 +1,20 @@
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::process::Stdio;
+
+use crate::core::cmdoutput::CmdOutput;
 use crate::eval::expression::Redirection;
+use crate::core::osstr::word_to_os;
 use crate::core::fsio::{open_file, read_file_as_input, write_output_to_file, FSError};
 use crate::parser::tokenizer::RedirectionType;
 
+#[derive(Clone)]
+enum OutputSink {
+    Stdout,
+    Stderr,
+    File(String)
+}
 
 pub fn handle_input_redirections(redirections: &Vec<Redirection>) -> Result<Option<Vec<u8>>, FSError> {
   for (index, redirection) in redirections.iter().enumerate() {
@@ -24,18 +37,102 @@ pub fn handle_input_redirections(redirections: &Vec<Redirection>) -> Result<Opti
   return Ok(None);
 }
 
-pub fn handle_output_redirections(redirections: &Vec<Redirection>, output: &Vec<u8>) -> Result<bool, FSError> {
-  for (index, redirection) in redirections.iter().enumerate() {
-      if index == redirections.len() - 1 {
-          if let Err(error) = write_output_to_file(&output, &redirection.target, if redirection.rtype == RedirectionType::Output { false } else { true }) {
-              return Err(error);
-          }
-          return Ok(true);
-      } else {
-          if let Err(error) = open_file( &redirection.target, if redirection.rtype == RedirectionType::Output { false } else { true }) {
-              return Err(error);
-          }
+// Works out where stdout and stderr end up. Redirections are applied left to right,
+// so `>f 2>&1` sends both streams to f. File targets are created/truncated here.
+fn resolve_sinks(redirections: &[Redirection]) -> Result<(OutputSink, OutputSink), FSError> {
+  let mut stdout_sink = OutputSink::Stdout;
+  let mut stderr_sink = OutputSink::Stderr;
+  for redirection in redirections {
+      match redirection.rtype {
+          RedirectionType::Output | RedirectionType::Append => {
+              open_file(&redirection.target, redirection.rtype == RedirectionType::Output)?;
+              stdout_sink = OutputSink::File(redirection.target.clone());
+          },
+          RedirectionType::ErrOutput | RedirectionType::ErrAppend => {
+              open_file(&redirection.target, redirection.rtype == RedirectionType::ErrOutput)?;
+              stderr_sink = OutputSink::File(redirection.target.clone());
+          },
+          RedirectionType::ErrToOutput => stderr_sink = stdout_sink.clone(),
+          RedirectionType::OutputToError => stdout_sink = stderr_sink.clone(),
+          _ => ()
+      }
+  }
+  Ok((stdout_sink, stderr_sink))
+}
+
+// Creates/truncates the output files ahead of the command, failing on the first that cannot be opened
+pub fn check_output_redirections(redirections: &[Redirection]) -> Result<(), FSError> {
+  resolve_sinks(redirections)?;
+  Ok(())
+}
+
+// Routes the captured stdout/stderr of a command to their redirection targets.
+pub fn handle_output_redirections(redirections: &[Redirection], output: &mut CmdOutput) -> Result<(), FSError> {
+  let (stdout_sink, stderr_sink) = resolve_sinks(redirections)?;
+  let stdout = std::mem::take(&mut output.stdout);
+  let stderr = std::mem::take(&mut output.stderr);
+  route_output(&stdout, &stdout_sink, output)?;
+  route_output(&stderr, &stderr_sink, output)?;
+  Ok(())
+}
+
+fn sink_fd(sink: &OutputSink) -> Result<OwnedFd, FSError> {
+  let fd = match sink {
+      OutputSink::Stdout => io::stdout().as_fd().try_clone_to_owned().map_err(|error| FSError::new("/dev/stdout", &error)),
+      OutputSink::Stderr => io::stderr().as_fd().try_clone_to_owned().map_err(|error| FSError::new("/dev/stderr", &error)),
+      // Targets were already created/truncated, so append from here on
+      OutputSink::File(path) => return Ok(OwnedFd::from(open_file(path, false)?))
+  };
+  fd
+}
+
+// Streams for a background job, which writes directly instead of being captured
+pub fn background_stdio(redirections: &[Redirection]) -> Result<(Stdio, Stdio), FSError> {
+  let (stdout_sink, stderr_sink) = resolve_sinks(redirections)?;
+  Ok((Stdio::from(sink_fd(&stdout_sink)?), Stdio::from(sink_fd(&stderr_sink)?)))
+}
+
+// `exec` without a command: the redirections apply to the shell itself from now on
+pub fn apply_shell_redirections(inputs: &[Redirection], outputs: &[Redirection]) -> Result<(), FSError> {
+  let mut fds: Vec<(OwnedFd, i32)> = Vec::new();
+  if let Some(redirection) = inputs.iter().rev().find(|redirection| redirection.rtype == RedirectionType::Input) {
+      match File::open(word_to_os(&redirection.target)) {
+          Ok(file) => fds.push((OwnedFd::from(file), 0)),
+          Err(error) => return Err(FSError::new(&redirection.target, &error))
       }
   }
-  return Ok(false);
+  // Every target is opened before any descriptor is replaced, so `>&2 2>f` still sees the old stderr
+  let (stdout_sink, stderr_sink) = resolve_sinks(outputs)?;
+  fds.push((sink_fd(&stdout_sink)?, 1));
+  fds.push((sink_fd(&stderr_sink)?, 2));
+  for (fd, target) in fds {
+      if unsafe { libc::dup2(fd.as_raw_fd(), target) } == -1 {
+          return Err(FSError::new(&format!("/dev/fd/{}", target), &io::Error::last_os_error()));
+      }
+  }
+  Ok(())
+}
+
+// Copies of the standard descriptors, to put back when the program exec was given fails to start
+pub fn save_shell_descriptors() -> Vec<(OwnedFd, i32)> {
+  (0..3).filter_map(|target| {
+      // Kept above the standard ones and closed on exec, so the program does not inherit them
+      let fd = unsafe { libc::fcntl(target, libc::F_DUPFD_CLOEXEC, 10) };
+      (fd != -1).then(|| (unsafe { OwnedFd::from_raw_fd(fd) }, target))
+  }).collect()
+}
+
+pub fn restore_shell_descriptors(saved: Vec<(OwnedFd, i32)>) {
+  for (fd, target) in saved {
+      unsafe { libc::dup2(fd.as_raw_fd(), target) };
+  }
+}
+
+fn route_output(data: &[u8], sink: &OutputSink, output: &mut CmdOutput) -> Result<(), FSError> {
+  match sink {
+      OutputSink::Stdout => output.stdout.extend_from_slice(data),
+      OutputSink::Stderr => output.stderr.extend_from_slice(data),
+      OutputSink::File(path) => write_output_to_file(data, path, false)?
+  }
+  Ok(())
 }
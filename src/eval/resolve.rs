@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use crate::core::core::ShellState;
+use crate::core::pathcache::{is_executable, search_path};
+use crate::parser::tokenizer::KEYWORDS;
+
+pub enum CommandKind {
+    Alias(String),
+    Keyword,
+    Function(String),
+    Builtin,
+    File(PathBuf)
+}
+
+impl CommandKind {
+    pub fn kind_name(&self) -> &str {
+        match self {
+            CommandKind::Alias(_) => "alias",
+            CommandKind::Keyword => "keyword",
+            CommandKind::Function(_) => "function",
+            CommandKind::Builtin => "builtin",
+            CommandKind::File(_) => "file"
+        }
+    }
+}
+
+// Everything a name can refer to, in the order the shell would pick it.
+// Unless `all` is set, only the first match is returned. Functions are left out with `no_functions`.
+pub fn classify_command(state: &mut ShellState, name: &str, all: bool, path_only: bool, no_functions: bool) -> Vec<CommandKind> {
+    let mut kinds = Vec::new();
+    if !path_only {
+        if let Some(value) = state.aliases.get(name) {
+            kinds.push(CommandKind::Alias(value.clone()));
+        }
+        if KEYWORDS.contains(&name) {
+            kinds.push(CommandKind::Keyword);
+        }
+        if let Some(body) = state.functions.get(name).filter(|_| !no_functions) {
+            kinds.push(CommandKind::Function(body.clone()));
+        }
+        if state.builtins.is_enabled(name) {
+            kinds.push(CommandKind::Builtin);
+        }
+    }
+    if !all && !kinds.is_empty() {
+        kinds.truncate(1);
+        return kinds;
+    }
+    if name.contains('/') {
+        if is_executable(Path::new(name)) {
+            kinds.push(CommandKind::File(PathBuf::from(name)));
+        }
+    } else if all {
+        let path_var = state.path_cache.get_path_var();
+        for path in search_path(name, &path_var, true) {
+            kinds.push(CommandKind::File(path));
+        }
+    } else if let Some(entry) = state.path_cache.get(name) {
+        kinds.push(CommandKind::File(entry.path.clone()));
+    } else if let Some(path) = search_path(name, &state.path_cache.get_path_var(), false).into_iter().next() {
+        kinds.push(CommandKind::File(path));
+    }
+    if !all {
+        kinds.truncate(1);
+    }
+    kinds
+}
@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+use crate::core::core::ShellState;
+use crate::eval::execute::timeval_duration;
+use crate::eval::expression::TimeFormat;
+
+const DEFAULT_FORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+const POSIX_FORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+// User and system time used by the shell itself, where builtins run
+fn shell_times() -> (Duration, Duration) {
+    unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        libc::getrusage(libc::RUSAGE_SELF, &mut usage);
+        (timeval_duration(&usage.ru_utime), timeval_duration(&usage.ru_stime))
+    }
+}
+
+// `1.250`, or `0m1.250s` in the long format, truncated to `precision` decimals
+fn format_duration(time: Duration, precision: usize, long: bool) -> String {
+    let unit = 10u128.pow(precision as u32);
+    let total = time.as_micros() * unit / 1_000_000;
+    let (whole, fraction) = (total / unit, total % unit);
+    let mut text = if long { format!("{}m{}", whole / 60, whole % 60) } else { whole.to_string() };
+    if precision > 0 {
+        text.push_str(&format!(".{:0width$}", fraction, width = precision));
+    }
+    if long {
+        text.push('s');
+    }
+    text
+}
+
+// Expands the %R, %U, %S and %P escapes of a TIMEFORMAT, each taking an optional precision and `l`
+fn format_times(format: &str, real: Duration, user: Duration, system: Duration) -> String {
+    let mut report = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            report.push(c);
+            continue;
+        }
+        let mut precision = 3;
+        if let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            precision = digit.min(3) as usize;
+            chars.next();
+        }
+        let long = chars.next_if_eq(&'l').is_some();
+        match chars.next() {
+            Some('%') => report.push('%'),
+            Some('R') => report.push_str(&format_duration(real, precision, long)),
+            Some('U') => report.push_str(&format_duration(user, precision, long)),
+            Some('S') => report.push_str(&format_duration(system, precision, long)),
+            Some('P') => {
+                let percent = if real.is_zero() { 0.0 } else { (user + system).as_secs_f64() * 100.0 / real.as_secs_f64() };
+                report.push_str(&format!("{:.2}", percent));
+            },
+            Some(other) => {
+                report.push('%');
+                report.push(other);
+            },
+            None => report.push('%')
+        }
+    }
+    report.push('\n');
+    report
+}
+
+// Measures a `time` pipeline
+pub struct Timer {
+    format: TimeFormat,
+    start: Instant,
+    user: Duration,
+    system: Duration
+}
+
+impl Timer {
+    pub fn start(format: TimeFormat) -> Timer {
+        let (user, system) = shell_times();
+        Timer { format, start: Instant::now(), user, system }
+    }
+
+    // Report of the elapsed time, given the CPU time of the programs the pipeline ran
+    pub fn report(&self, state: &ShellState, user_time: Duration, system_time: Duration) -> String {
+        let real = self.start.elapsed();
+        let (user, system) = shell_times();
+        let user = user.saturating_sub(self.user) + user_time;
+        let system = system.saturating_sub(self.system) + system_time;
+        let format = match self.format {
+            TimeFormat::Posix => POSIX_FORMAT.to_string(),
+            TimeFormat::Default => state.variables.get("TIMEFORMAT").unwrap_or(DEFAULT_FORMAT.to_string())
+        };
+        if format.is_empty() {
+            return String::new();
+        }
+        format_times(&format, real, user, system)
+    }
+}
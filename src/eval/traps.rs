@@ -0,0 +1,85 @@
+use crossterm::style::Print;
+use crate::crossterm::QueueableCommand;
+
+use crate::core::core::ShellState;
+use crate::core::error::ShellError;
+use crate::core::signals::{signal_name, signal_number, take_pending_signals};
+use crate::eval::eval::eval_expr;
+
+// Conditions that are not signals but can be trapped
+pub const PSEUDO_SIGNALS: [&str; 4] = ["EXIT", "ERR", "DEBUG", "RETURN"];
+
+// Canonical name a trap is stored under: `EXIT`, `ERR`, ... or the signal name without SIG
+pub fn trap_name(spec: &str) -> Option<String> {
+    let upper = spec.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    if PSEUDO_SIGNALS.contains(&name) {
+        return Some(name.to_string());
+    }
+    if spec == "0" {
+        return Some("EXIT".to_string());
+    }
+    signal_number(spec).and_then(signal_name).map(String::from)
+}
+
+// Runs the command registered for a trap. Traps do not fire while another one runs,
+// and leave $? untouched. Only an exit request is propagated.
+pub fn run_trap(state: &mut ShellState, name: &str) -> Result<(), ShellError> {
+    if state.in_trap {
+        return Ok(());
+    }
+    let Some(command) = state.traps.get(name).filter(|command| !command.is_empty()).cloned() else {
+        return Ok(());
+    };
+    let status = state.status;
+    state.in_trap = true;
+    let result = eval_expr(state, &command);
+    state.in_trap = false;
+    state.status = status;
+    match result {
+        Err(ShellError::ExitRequest(code)) => Err(ShellError::ExitRequest(code)),
+        Err(error) => {
+            if let Ok(error_str) = String::from_utf8(error.to_output(&command)) {
+                state.stderr.queue(Print(format!("{}\n", error_str))).unwrap();
+            }
+            Ok(())
+        },
+        Ok(_) => Ok(())
+    }
+}
+
+pub fn run_pending_traps(state: &mut ShellState) -> Result<(), ShellError> {
+    for signal in take_pending_signals() {
+        if let Some(name) = signal_name(signal) {
+            run_trap(state, name)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pseudo_signals_are_canonical() {
+        assert_eq!(trap_name("exit").as_deref(), Some("EXIT"));
+        assert_eq!(trap_name("0").as_deref(), Some("EXIT"));
+        assert_eq!(trap_name("SIGERR").as_deref(), Some("ERR"));
+        assert_eq!(trap_name("Return").as_deref(), Some("RETURN"));
+    }
+
+    #[test]
+    fn signals_are_named_without_prefix() {
+        assert_eq!(trap_name("SIGINT").as_deref(), Some("INT"));
+        assert_eq!(trap_name("term").as_deref(), Some("TERM"));
+        assert_eq!(trap_name(&libc::SIGUSR1.to_string()).as_deref(), Some("USR1"));
+    }
+
+    #[test]
+    fn unknown_specs_are_rejected() {
+        assert_eq!(trap_name("NOPE"), None);
+        assert_eq!(trap_name("-1"), None);
+        assert_eq!(trap_name("99"), None);
+    }
+}
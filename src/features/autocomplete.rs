@@ -1,4 +1,4 @@
-use std::{env, fs};
+use std::fs;
 
 use crossterm::style::Print;
 use crossterm::terminal::{Clear, ClearType};
@@ -6,6 +6,7 @@ use crossterm::QueueableCommand;
 
 use crate::core::core::{ShellState};
 use crate::core::error::ShellError;
+use crate::core::osstr::os_to_word;
 use crate::rendering::autocomplete::render_options;
 
 pub struct AutocompleteState {
@@ -43,7 +44,7 @@ impl Autocomplete {
             if expr.starts_with("cd ") || expr.contains('/') || expr.contains('.') {
                 res = path_completion(&expr);
             } else {
-                res = command_completion(&expr);
+                res = command_completion(state, expr);
             }
             match res.len() {
                 0 => {
@@ -73,30 +74,23 @@ impl Autocomplete {
     }
 }
 
-fn command_completion(expr: &str) -> Vec<String> {
-    let mut available: Vec<String> = Vec::new();
-    let mut searchpaths: Vec<String> = Vec::new();
-    if let Ok(path) = env::var("PATH") {
-        searchpaths = path.split(":").map(String::from).collect();
+fn command_completion(state: &mut ShellState, expr: &str) -> Vec<String> {
+    let mut available: Vec<String> = state.path_cache.get_executables().iter()
+                                            .filter(|name| name.starts_with(expr))
+                                            .cloned()
+                                            .collect();
+    for builtin in state.builtins.list() {
+        if builtin.name().starts_with(expr) && state.builtins.is_enabled(builtin.name()) {
+            available.push(builtin.name().to_string());
+        }
     }
-    for searchpath in searchpaths {
-        if let Ok(entries) = fs::read_dir(searchpath) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(path_str) = path.file_name().and_then(|f| f.to_str()) {
-                            // Filter commands based on input
-                            if path_str.starts_with(expr) {
-                                available.push(path_str.to_string());
-                            }
-                        }
-                    }
-                }
-            }
+    for alias in state.aliases.keys() {
+        if alias.starts_with(expr) {
+            available.push(alias.clone());
         }
     }
     available.sort();
+    available.dedup();
     return available;
 }
 
@@ -107,7 +101,7 @@ fn print_options(state: &mut ShellState, astate: &AutocompleteState) -> Result<(
                 state.stdout.queue(Print(output)).unwrap();
                 return Ok(());
             } else {
-                return Err(ShellError::ExitRequest);
+                Err(ShellError::ExitRequest(0))
             }
         },
         Err(error) => {
@@ -127,7 +121,7 @@ fn path_completion(expr: &str) -> Vec<String> {
     } else {
         path.parent().unwrap_or(Path::new("."))
     };
-    let prefix = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let prefix = path.file_name().map(os_to_word).unwrap_or_default();
 
     // Read entries in the directory
     if let Ok(entries) = fs::read_dir(dir) {
@@ -136,9 +130,10 @@ fn path_completion(expr: &str) -> Vec<String> {
                 let path = entry.path();
 
                 // Match entries that start with the prefix
-                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if name.starts_with(prefix) {
-                        available.push(name.to_string());
+                // Bytes that are not UTF-8 are kept, so the completed name still opens the file
+                if let Some(name) = path.file_name().map(os_to_word) {
+                    if name.starts_with(&prefix) {
+                        available.push(name);
                     }
                 }
             }
@@ -0,0 +1,28 @@
+use std::env;
+
+use crate::core::core::ShellState;
+use crate::features::prompt::Prompt;
+
+// Rest of the most recent history entry that extends the input, preferring the entries run in
+// the current directory. Only offered with the cursor at the end of a single line of input.
+pub fn suggest(state: &ShellState, prompt: &Prompt) -> Option<String> {
+    let input = prompt.get_input();
+    if input.is_empty() || input.contains('\n') || prompt.get_cursor() != input.len() || state.search.is_some() {
+        return None;
+    }
+    let cwd = env::current_dir().ok().map(|dir| dir.to_string_lossy().to_string());
+    let values = state.history.get_values();
+    let mut fallback = None;
+    for index in (0..values.len()).rev() {
+        if values[index].len() <= input.len() || !values[index].starts_with(input.as_str()) {
+            continue;
+        }
+        if cwd.is_some() && state.history.get_directory(index) == cwd.as_deref() {
+            return Some(values[index][input.len()..].to_string());
+        }
+        if fallback.is_none() {
+            fallback = Some(index);
+        }
+    }
+    fallback.map(|index| values[index][input.len()..].to_string())
+}
@@ -0,0 +1,238 @@
+use std::env;
+use std::path::Path;
+
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+use crate::core::config::ThemeConfig;
+use crate::core::core::ShellState;
+use crate::core::pathcache::{is_executable, search_path};
+use crate::core::variables::parse_assignment;
+use crate::parser::tokenizer::{tokenize_with_spans, Token, KEYWORDS};
+
+// What a character of the input is part of, which decides its color
+#[derive(Clone, Copy, PartialEq)]
+pub enum Role {
+    Plain,
+    Command,
+    UnknownCommand,
+    Keyword,
+    String,
+    Variable,
+    Operator,
+    Redirection,
+    Comment,
+    Path
+}
+
+// A builtin, alias, function or program that would run
+fn is_resolvable(state: &mut ShellState, name: &str) -> bool {
+    if name.contains('/') {
+        return is_executable(Path::new(name));
+    }
+    state.builtins.is_enabled(name)
+        || state.aliases.contains_key(name)
+        || state.functions.contains_key(name)
+        || !search_path(name, &state.path_cache.get_path_var(), false).is_empty()
+}
+
+fn is_existing_path(word: &str) -> bool {
+    let path = match (word.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+        _ => Path::new(word).to_path_buf()
+    };
+    !word.is_empty() && path.exists()
+}
+
+fn fill(roles: &mut [Role], start: usize, end: usize, role: Role) {
+    let end = end.min(roles.len());
+    for slot in &mut roles[start..end] {
+        *slot = role;
+    }
+}
+
+// Colors what is not already part of a string or variable
+fn fill_plain(roles: &mut [Role], start: usize, end: usize, role: Role) {
+    let end = end.min(roles.len());
+    for slot in &mut roles[start..end] {
+        if *slot == Role::Plain {
+            *slot = role;
+        }
+    }
+}
+
+// End of the parameter starting with the `$` at `start`: `$name`, `$?` or `${...}`
+fn parameter_end(chars: &[char], start: usize, end: usize) -> usize {
+    match chars.get(start + 1) {
+        Some('{') => chars[start..end].iter().position(|c| *c == '}').map_or(end, |offset| start + offset + 1),
+        Some(c) if "?#@*$!-0123456789".contains(*c) => start + 2,
+        Some(c) if c.is_alphabetic() || *c == '_' => {
+            let mut index = start + 1;
+            while index < end && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            index
+        },
+        _ => start + 1
+    }
+}
+
+// Quoted parts and parameters within a word
+fn highlight_word(chars: &[char], start: usize, end: usize, roles: &mut [Role]) {
+    let mut index = start;
+    while index < end {
+        match chars[index] {
+            '\\' => {
+                fill(roles, index, (index + 2).min(end), Role::String);
+                index += 2;
+            },
+            '\'' => {
+                let close = chars[index + 1..end].iter().position(|c| *c == '\'').map_or(end, |offset| index + offset + 2);
+                fill(roles, index, close, Role::String);
+                index = close;
+            },
+            '"' => {
+                let mut close = index + 1;
+                while close < end && chars[close] != '"' {
+                    close += if chars[close] == '\\' { 2 } else { 1 };
+                }
+                let close = (close + 1).min(end);
+                fill(roles, index, close, Role::String);
+                // Parameters are still expanded within double quotes
+                let mut inner = index + 1;
+                while inner < close {
+                    if chars[inner] == '$' {
+                        let parameter = parameter_end(chars, inner, close);
+                        if parameter > inner + 1 {
+                            fill(roles, inner, parameter, Role::Variable);
+                        }
+                        inner = parameter;
+                    } else {
+                        inner += if chars[inner] == '\\' { 2 } else { 1 };
+                    }
+                }
+                index = close;
+            },
+            '$' => {
+                let parameter = parameter_end(chars, index, end);
+                if parameter > index + 1 {
+                    fill(roles, index, parameter, Role::Variable);
+                }
+                index = parameter;
+            },
+            _ => index += 1
+        }
+    }
+}
+
+// Highlights the characters `start..end` of the input, which may be the inside of a subexpression
+fn highlight_range(state: &mut ShellState, chars: &[char], start: usize, end: usize, roles: &mut [Role]) {
+    let text: String = chars[start..end].iter().collect();
+    // Incomplete input is highlighted up to what is left open, such as an unterminated quote
+    let (tokens, spans, open) = match tokenize_with_spans(&text) {
+        Ok((tokens, spans)) => (tokens, spans, None),
+        Err(error) => {
+            let cut = start + error.span.start.min(end - start);
+            let prefix: String = chars[start..cut].iter().collect();
+            match tokenize_with_spans(&prefix) {
+                Ok((tokens, spans)) => (tokens, spans, Some(cut)),
+                Err(_) => (Vec::new(), Vec::new(), Some(start))
+            }
+        }
+    };
+    let mut command_position = true;
+    let mut after_redirection = false;
+    for (token, span) in tokens.iter().zip(spans.iter()) {
+        let (token_start, token_end) = (start + span.start, (start + span.end).min(end));
+        let word: String = chars[token_start..token_end].iter().collect();
+        match token {
+            Token::Word(_) | Token::CompoundWord(_) => {
+                highlight_word(chars, token_start, token_end, roles);
+                if after_redirection {
+                    after_redirection = false;
+                    if state.config.theme.underline_paths && matches!(token, Token::Word(name) if is_existing_path(name)) {
+                        fill_plain(roles, token_start, token_end, Role::Path);
+                    }
+                } else if command_position {
+                    if KEYWORDS.contains(&word.as_str()) {
+                        fill(roles, token_start, token_end, Role::Keyword);
+                        // `for name in ...` and `case word in` are followed by words, not a command
+                        command_position = !matches!(word.as_str(), "for" | "select" | "case");
+                    } else if parse_assignment(&word).is_none() {
+                        // Names made by expansions are only known when the command runs
+                        if let Token::Word(name) = token {
+                            let role = if is_resolvable(state, name) { Role::Command } else { Role::UnknownCommand };
+                            fill_plain(roles, token_start, token_end, role);
+                        }
+                        command_position = false;
+                    }
+                } else if state.config.theme.underline_paths && matches!(token, Token::Word(name) if is_existing_path(name)) {
+                    fill_plain(roles, token_start, token_end, Role::Path);
+                }
+            },
+            Token::Redirection(_) => {
+                fill(roles, token_start, token_end, Role::Redirection);
+                after_redirection = true;
+            },
+            Token::Subexpression(_) => {
+                fill(roles, token_start, token_start + 1, Role::Operator);
+                fill(roles, token_end - 1, token_end, Role::Operator);
+                if token_end > token_start + 1 {
+                    highlight_range(state, chars, token_start + 1, token_end - 1, roles);
+                }
+            },
+            Token::ArrayAssignment(..) => (),
+            _ => {
+                fill(roles, token_start, token_end, Role::Operator);
+                command_position = true;
+            }
+        }
+    }
+    let tokens_end = spans.last().map_or(start, |span| start + span.end);
+    let limit = open.unwrap_or(end);
+    // Tokenization stops at a comment
+    if let Some(offset) = chars[tokens_end.min(limit)..limit].iter().position(|c| *c == '#') {
+        fill(roles, tokens_end + offset, limit, Role::Comment);
+    }
+    if let Some(open) = open {
+        match chars.get(open) {
+            Some('\'' | '"') => fill(roles, open, end, Role::String),
+            Some('(' | '`') => {
+                fill(roles, open, open + 1, Role::Operator);
+                highlight_range(state, chars, open + 1, end, roles);
+            },
+            Some('$') => fill(roles, open, end, Role::Variable),
+            _ => ()
+        }
+    }
+}
+
+// Role of each character of the input
+pub fn highlight(state: &mut ShellState, input: &str) -> Vec<Role> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut roles = vec![Role::Plain; chars.len()];
+    if state.config.theme.highlight {
+        highlight_range(state, &chars, 0, chars.len(), &mut roles);
+    }
+    roles
+}
+
+pub fn role_style(theme: &ThemeConfig, role: Role) -> ContentStyle {
+    let mut style = ContentStyle::new();
+    let color = match role {
+        Role::Plain => return style,
+        Role::Path => {
+            style.attributes.set(Attribute::Underlined);
+            return style;
+        },
+        Role::Command => &theme.command,
+        Role::UnknownCommand => &theme.unknown_command,
+        Role::Keyword => &theme.keyword,
+        Role::String => &theme.string,
+        Role::Variable => &theme.variable,
+        Role::Operator => &theme.operator,
+        Role::Redirection => &theme.redirection,
+        Role::Comment => &theme.comment
+    };
+    style.foreground_color = Color::try_from(color.as_str()).ok().filter(|color| *color != Color::Reset);
+    style
+}
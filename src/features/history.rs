@@ -1,8 +1,12 @@
 use std::{env, fs};
 use std::path::{Path, PathBuf};
 
+use crate::core::fsio::FSError;
+
 pub struct History {
     values: Vec<String>,
+    // Directory each entry was run in, unknown for the entries loaded from the history file
+    directories: Vec<Option<String>>,
 }
 
 fn get_store_path() -> Option<PathBuf> {
@@ -25,15 +29,14 @@ impl History {
     pub fn submit(&mut self, value: &str) {
         if let Some(index) = self.values.iter().position(|v| v == value) {
             self.values.remove(index);
+            self.directories.remove(index);
         }
         self.values.push(value.to_string());
+        self.directories.push(env::current_dir().ok().map(|dir| dir.to_string_lossy().to_string()));
     }
 
-    pub fn get_first_index(&self) -> Option<usize> {
-        if self.values.len() <= 0 {
-            return None;
-        }
-        return Some(self.values.len() - 1);
+    pub fn get_directory(&self, index: usize) -> Option<&str> {
+        self.directories.get(index)?.as_deref()
     }
 
     pub fn get_values(&self) -> &Vec<String> {
@@ -43,27 +46,45 @@ impl History {
     pub fn load() -> History {
         if let Some(config_path) = get_store_path() {
             if let Ok(data) = fs::read_to_string(config_path) {
+                // A line ending with a backslash continues on the next one
+                let mut values: Vec<String> = Vec::new();
+                let mut entry = String::new();
+                for line in data.lines() {
+                    match line.strip_suffix('\\') {
+                        Some(start) => {
+                            entry.push_str(start);
+                            entry.push('\n');
+                        },
+                        None => {
+                            entry.push_str(line);
+                            values.push(std::mem::take(&mut entry));
+                        }
+                    }
+                }
                 return History{
-                    values: data.lines().map(String::from).collect()
+                    directories: vec![None; values.len()],
+                    values
                 }
             }
         }
         return History{
-            values: Vec::new()
+            values: Vec::new(),
+            directories: Vec::new()
         }
     }
 
-    pub fn persist(&self) {
+    pub fn persist(&self) -> Result<(), FSError> {
         if let Some(config_path) = get_store_path() {
             // Create the config dir if missing
             if let Some(dir) = config_path.parent() {
                 if !dir.exists() {
-                    fs::create_dir_all(dir).unwrap();
+                    fs::create_dir_all(dir).map_err(|error| FSError::new(&dir.to_string_lossy(), &error))?;
                 }
             }
-            // Write history
-            let data = self.values.join("\n");
-            fs::write(&config_path, data).unwrap();
+            // Write history, one entry per line with the newlines of an entry escaped
+            let data = self.values.iter().map(|value| value.trim_end_matches('\n').replace('\n', "\\\n")).collect::<Vec<String>>().join("\n");
+            fs::write(&config_path, data).map_err(|error| FSError::new(&config_path.to_string_lossy(), &error))?;
         }
+        Ok(())
     }
 }
\ No newline at end of file
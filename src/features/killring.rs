@@ -0,0 +1,56 @@
+// Older entries are dropped past this size
+const MAX_ENTRIES: usize = 32;
+
+// Text removed by the kill commands, most recent last. Yanking inserts the current
+// entry and yank-pop rotates through the older ones.
+pub struct KillRing {
+    entries: Vec<String>,
+    index: usize
+}
+
+impl KillRing {
+    pub fn new() -> KillRing {
+        KillRing {
+            entries: Vec::new(),
+            index: 0
+        }
+    }
+
+    pub fn push(&mut self, text: &str) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(text.to_string());
+        self.index = self.entries.len() - 1;
+    }
+
+    // Consecutive kills build up a single entry, in the order the text had in the line
+    pub fn append(&mut self, text: &str) {
+        match self.entries.last_mut() {
+            Some(last) => last.push_str(text),
+            None => self.push(text)
+        }
+        self.index = self.entries.len() - 1;
+    }
+
+    pub fn prepend(&mut self, text: &str) {
+        match self.entries.last_mut() {
+            Some(last) => last.insert_str(0, text),
+            None => self.push(text)
+        }
+        self.index = self.entries.len() - 1;
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.entries.get(self.index).map(|entry| entry.as_str())
+    }
+
+    // Moves to the previous entry, wrapping around to the most recent
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.index = if self.index == 0 { self.entries.len() - 1 } else { self.index - 1 };
+        self.current()
+    }
+}
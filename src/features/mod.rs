@@ -1,4 +1,10 @@
 pub mod autocomplete;
+pub mod autosuggest;
+pub mod highlight;
 pub mod history;
+pub mod killring;
 pub mod prompt;
-pub mod promptscript;
\ No newline at end of file
+pub mod promptscript;
+pub mod search;
+pub mod suggestions;
+pub mod vi;
\ No newline at end of file
@@ -1,10 +1,31 @@
-use unic_emoji_char::is_emoji;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::features::killring::KillRing;
+
+// Largest numeric argument, so that `Alt-9 9 9 9 9 x` stays reasonable
+const MAX_ARGUMENT: i64 = 4096;
 
 pub struct Prompt {
     input_stash: Option<String>,
+    // Cursor when the input was stashed, the text before it is the history search prefix
+    stash_cursor: usize,
     input: String,
     cursor: usize,
     pub ps1: String,
+    kill_ring: KillRing,
+    // Where the last yank was inserted, replaced by yank-pop
+    yank_range: (usize, usize),
+    // Input and cursor before each edit, restored by undo
+    undo_stack: Vec<(String, usize)>,
+    redo_stack: Vec<(String, usize)>,
+    // Within a change, such as vi's `cw` up to leaving insert mode, edits are undone together
+    in_change: bool,
+    last_command: EditCommand,
+    this_command: EditCommand,
+    // Digits of the numeric argument typed with Alt, such as "-3"
+    argument: Option<String>,
+    // Rest of a history entry extending the input, shown after it
+    suggestion: Option<String>
 }
 
 pub enum CursorPosition {
@@ -12,42 +33,161 @@ pub enum CursorPosition {
     End
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum CursorMovement {
     One,
     Word
 }
 
+// Words are runs of alphanumeric characters, or for Ctrl-W anything but whitespace
+#[derive(PartialEq, Clone, Copy)]
+pub enum WordBoundary {
+    Alphanumeric,
+    Whitespace
+}
+
+pub enum WordCase {
+    Upper,
+    Lower,
+    Capitalize
+}
+
+// Kind of the edit made by a key, consecutive kills, inserts and replacements are grouped
+#[derive(PartialEq, Clone, Copy)]
+enum EditCommand {
+    Other,
+    Insert,
+    Kill,
+    Yank,
+    // The whole input replaced, by history browsing, search or completion
+    Replace
+}
+
 impl Prompt {
     pub fn new(ps1script: &str) -> Prompt {
         return Prompt{
             input_stash: None,
+            stash_cursor: 0,
             input: String::new(),
             cursor: 0,
-            ps1: ps1script.to_string()
+            ps1: ps1script.to_string(),
+            kill_ring: KillRing::new(),
+            yank_range: (0, 0),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            in_change: false,
+            last_command: EditCommand::Other,
+            this_command: EditCommand::Other,
+            argument: None,
+            suggestion: None
+        }
+    }
+
+    // Called for each key, before it is handled
+    pub fn begin_command(&mut self) {
+        self.last_command = self.this_command;
+        self.this_command = EditCommand::Other;
+    }
+
+    // Saves the input for undo. A run of inserted characters is undone at once, and so are
+    // replacements one after the other, such as going up through history several entries.
+    fn save_undo(&mut self, command: EditCommand) {
+        let grouped = matches!(command, EditCommand::Insert | EditCommand::Replace) && self.last_command == command;
+        if !self.in_change && !grouped {
+            self.undo_stack.push((self.input.clone(), self.cursor));
+            self.redo_stack.clear();
+        }
+        self.this_command = command;
+    }
+
+    pub fn begin_change(&mut self) {
+        self.save_undo(EditCommand::Other);
+        self.in_change = true;
+    }
+
+    pub fn end_change(&mut self) {
+        self.in_change = false;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some((input, cursor)) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push((std::mem::replace(&mut self.input, input), self.cursor));
+        self.cursor = cursor;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some((input, cursor)) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push((std::mem::replace(&mut self.input, input), self.cursor));
+        self.cursor = cursor;
+        true
+    }
+
+    // numeric argument
+
+    pub fn push_argument(&mut self, c: char) {
+        let argument = self.argument.get_or_insert_with(String::new);
+        if c != '-' || argument.is_empty() {
+            argument.push(c);
         }
     }
 
+    pub fn has_argument(&self) -> bool {
+        self.argument.is_some()
+    }
+
+    // The argument for the current key, 1 without one. A lone `-` is -1.
+    pub fn take_argument(&mut self) -> i64 {
+        let Some(argument) = self.argument.take() else {
+            return 1;
+        };
+        let value = match argument.as_str() {
+            "-" => -1,
+            digits => digits.parse::<i64>().unwrap_or(1)
+        };
+        value.clamp(-MAX_ARGUMENT, MAX_ARGUMENT)
+    }
+
     // input
 
     pub fn add_char(&mut self, c: char) {
+        self.save_undo(EditCommand::Insert);
         self.input.insert(self.cursor, c);
         self.cursor += c.len_utf8();
     }
 
+    // Removes the character before or after the cursor, with its combining marks
     pub fn remove_char(&mut self, back: bool) -> bool {
         if back && self.cursor > 0 {
-            self.cursor -= 1;
-            self.input.remove(self.cursor);
+            self.save_undo(EditCommand::Other);
+            let start = self.previous_boundary(self.cursor);
+            self.input.replace_range(start..self.cursor, "");
+            self.cursor = start;
             return true;
         } else if !back && self.cursor < self.input.len() {
-            self.input.remove(self.cursor);
+            self.save_undo(EditCommand::Other);
+            let end = self.next_boundary(self.cursor);
+            self.input.replace_range(self.cursor..end, "");
             return true;
         }
         return false;
     }
 
+    // Inserts text at the cursor, such as a paste, which is undone at once
+    pub fn insert_str(&mut self, text: &str) {
+        self.save_undo(EditCommand::Other);
+        self.input.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
     pub fn set_input(&mut self, str: &str) {
+        if self.input != str {
+            self.save_undo(EditCommand::Replace);
+        }
         self.input = str.to_string();
         self.cursor = str.len();
     }
@@ -55,14 +195,21 @@ impl Prompt {
     pub fn clear_input(&mut self) {
         self.input.clear();
         self.cursor = 0;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.in_change = false;
+        self.argument = None;
+    }
+
+    // Replaces the bytes `start..end`, leaving the cursor after the new text
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        self.save_undo(EditCommand::Other);
+        self.input.replace_range(start..end, text);
+        self.cursor = start + text.len();
     }
 
     pub fn truncate_input(&mut self) -> bool {
-        if self.cursor == self.input.len() {
-            return false;
-        }
-        self.input.truncate(self.cursor);
-        return true;
+        self.kill(self.cursor, self.input.len(), false)
     }
 
     pub fn has_input(&self) -> bool {
@@ -73,14 +220,172 @@ impl Prompt {
         return &self.input;
     }
 
-    pub fn get_input_rows(&self) -> usize {
-        return 1 + self.input.matches('\n').count();
+    // kill and yank
+
+    // Removes `start..end` into the kill ring, `backward` when killing towards the line start
+    pub fn kill(&mut self, start: usize, end: usize, backward: bool) -> bool {
+        if start >= end {
+            return false;
+        }
+        let appending = self.last_command == EditCommand::Kill;
+        self.save_undo(EditCommand::Kill);
+        let text = &self.input[start..end];
+        if !appending {
+            self.kill_ring.push(text);
+        } else if backward {
+            self.kill_ring.prepend(text);
+        } else {
+            self.kill_ring.append(text);
+        }
+        self.input.replace_range(start..end, "");
+        self.cursor = start;
+        true
+    }
+
+    pub fn kill_to_start(&mut self) -> bool {
+        self.kill(0, self.cursor, true)
+    }
+
+    // Kills `count` words after the cursor, or before it when negative
+    pub fn kill_words(&mut self, count: i64, boundary: WordBoundary) -> bool {
+        let position = self.word_position(count, boundary);
+        if position < self.cursor {
+            return self.kill(position, self.cursor, true);
+        }
+        self.kill(self.cursor, position, false)
+    }
+
+    pub fn yank(&mut self) -> bool {
+        let Some(text) = self.kill_ring.current().map(|text| text.to_string()) else {
+            return false;
+        };
+        self.save_undo(EditCommand::Yank);
+        self.input.insert_str(self.cursor, &text);
+        self.yank_range = (self.cursor, self.cursor + text.len());
+        self.cursor += text.len();
+        true
+    }
+
+    // Replaces the text just yanked with the previous kill
+    pub fn yank_pop(&mut self) -> bool {
+        if self.last_command != EditCommand::Yank {
+            return false;
+        }
+        let Some(text) = self.kill_ring.rotate().map(|text| text.to_string()) else {
+            return false;
+        };
+        self.save_undo(EditCommand::Yank);
+        let (start, end) = self.yank_range;
+        self.input.replace_range(start..end, &text);
+        self.yank_range = (start, start + text.len());
+        self.cursor = start + text.len();
+        true
+    }
+
+    // transpose and case
+
+    // Drags the character before the cursor forward, at the end of the line swaps the last two
+    pub fn transpose_chars(&mut self, count: i64) -> bool {
+        if self.cursor == 0 || self.input.graphemes(true).count() < 2 {
+            return false;
+        }
+        self.save_undo(EditCommand::Other);
+        if self.cursor == self.input.len() {
+            self.cursor = self.previous_boundary(self.cursor);
+        }
+        for _ in 0..count.max(1) {
+            if self.cursor == 0 || self.cursor == self.input.len() {
+                break;
+            }
+            let start = self.previous_boundary(self.cursor);
+            let end = self.next_boundary(self.cursor);
+            let before = self.input[start..self.cursor].to_string();
+            let after = self.input[self.cursor..end].to_string();
+            self.input.replace_range(start..end, &format!("{}{}", after, before));
+            self.cursor = end;
+        }
+        true
+    }
+
+    // Swaps the words around the cursor, or the last two at the end of the line
+    pub fn transpose_words(&mut self) -> bool {
+        let second_end = self.word_end_after(self.cursor, WordBoundary::Alphanumeric);
+        let second_start = self.word_start_before(second_end, WordBoundary::Alphanumeric);
+        let first_start = self.word_start_before(second_start, WordBoundary::Alphanumeric);
+        let first_end = self.word_end_after(first_start, WordBoundary::Alphanumeric);
+        if first_start >= second_start || first_end > second_start {
+            return false;
+        }
+        self.save_undo(EditCommand::Other);
+        let first = self.input[first_start..first_end].to_string();
+        let second = self.input[second_start..second_end].to_string();
+        let between = self.input[first_end..second_start].to_string();
+        self.input.replace_range(first_start..second_end, &format!("{}{}{}", second, between, first));
+        self.cursor = second_end;
+        true
+    }
+
+    // Changes the case of `count` words from the cursor, which moves past them.
+    // With a negative count the previous words change and the cursor stays.
+    pub fn change_case(&mut self, count: i64, case: WordCase) -> bool {
+        let position = self.word_position(count, WordBoundary::Alphanumeric);
+        let (start, end) = if position < self.cursor { (position, self.cursor) } else { (self.cursor, position) };
+        if start == end {
+            return false;
+        }
+        self.save_undo(EditCommand::Other);
+        let mut changed = String::new();
+        let mut in_word = false;
+        for c in self.input[start..end].chars() {
+            match case {
+                WordCase::Upper => changed.extend(c.to_uppercase()),
+                WordCase::Lower => changed.extend(c.to_lowercase()),
+                WordCase::Capitalize if !in_word && c.is_alphanumeric() => changed.extend(c.to_uppercase()),
+                WordCase::Capitalize => changed.extend(c.to_lowercase())
+            }
+            in_word = c.is_alphanumeric();
+        }
+        self.input.replace_range(start..end, &changed);
+        if position >= self.cursor {
+            self.cursor = start + changed.len();
+        }
+        true
+    }
+
+    // autosuggestion
+
+    pub fn set_suggestion(&mut self, suggestion: Option<String>) {
+        self.suggestion = suggestion;
+    }
+
+    pub fn get_suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    // Appends the suggestion to the input, or only up to the end of its next word
+    pub fn accept_suggestion(&mut self, word: bool) -> bool {
+        if self.cursor != self.input.len() {
+            return false;
+        }
+        let Some(suggestion) = self.suggestion.take() else {
+            return false;
+        };
+        let mut end = suggestion.len();
+        if word {
+            let start = suggestion.find(|c| Prompt::in_word(c, WordBoundary::Alphanumeric)).unwrap_or(end);
+            end = suggestion[start..].find(|c| !Prompt::in_word(c, WordBoundary::Alphanumeric)).map_or(end, |length| start + length);
+        }
+        self.save_undo(EditCommand::Other);
+        self.input.push_str(&suggestion[..end]);
+        self.cursor = self.input.len();
+        true
     }
 
     // stash
 
     pub fn stash_input(&mut self) {
         self.input_stash = Some(self.input.clone());
+        self.stash_cursor = self.cursor;
     }
 
     pub fn unstash_input(&mut self) {
@@ -89,6 +394,13 @@ impl Prompt {
         }
     }
 
+    pub fn get_stash_prefix(&self) -> &str {
+        match &self.input_stash {
+            Some(stash) => &stash[..self.stash_cursor.min(stash.len())],
+            None => ""
+        }
+    }
+
     pub fn clear_stash(&mut self) {
         if let Some(stash) = &mut self.input_stash {
             stash.clear();
@@ -96,73 +408,253 @@ impl Prompt {
     }
 
     // cursor
-    pub fn get_cursor_offset(&self) -> (usize, usize) {
-        let input_until_cursor = &self.input[..self.cursor];
-        let newline_count = input_until_cursor.matches('\n').count();
-        let mut column_index = input_until_cursor.chars().map(|c| if is_emoji(c) { 2 } else { 1 }).sum::<usize>();
-        if let Some(pos) = input_until_cursor.rfind('\n') {
-            let input_newline_cursor = &input_until_cursor[pos + 1..self.cursor];
-            column_index = input_newline_cursor.chars().map(|c| if is_emoji(c) { 2 } else { 1 }).sum::<usize>();
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, position: usize) {
+        if self.input.is_char_boundary(position) {
+            self.cursor = position;
         }
-        return (column_index, newline_count)
     }
 
-    pub fn move_cursor(&mut self, pos: CursorPosition) -> bool {
-        match pos {
-            CursorPosition::Origin => {
-                if self.cursor != 0 {
-                    self.cursor = 0;
-                    return true;
-                }
-            },
-            CursorPosition::End => {
-                if self.cursor != self.input.len()-1 {
-                    self.cursor = self.input.len()-1;
-                    return true;
-                }
+    // Characters are grapheme clusters, so that the cursor never stops within an accented
+    // letter, an emoji sequence or a flag
+    pub fn previous_boundary(&self, position: usize) -> usize {
+        self.input[..position].grapheme_indices(true).next_back().map_or(0, |(index, _)| index)
+    }
+
+    pub fn next_boundary(&self, position: usize) -> usize {
+        self.input[position..].graphemes(true).next().map_or(position, |grapheme| position + grapheme.len())
+    }
+
+    fn in_word(c: char, boundary: WordBoundary) -> bool {
+        match boundary {
+            WordBoundary::Alphanumeric => c.is_alphanumeric(),
+            WordBoundary::Whitespace => !c.is_whitespace()
+        }
+    }
+
+    // Start of the word before `position`, skipping the separators in between
+    fn word_start_before(&self, position: usize, boundary: WordBoundary) -> usize {
+        let mut start = position;
+        let mut seen_word = false;
+        for (index, c) in self.input[..position].char_indices().rev() {
+            if Prompt::in_word(c, boundary) {
+                seen_word = true;
+            } else if seen_word {
+                break;
             }
+            start = index;
         }
-        return false;
+        start
     }
 
-    pub fn move_cursor_left(&mut self, movement: CursorMovement) -> usize {
-        if self.cursor == 0 {
-            return 0;
-        }
-        let mut local_cursor = self.cursor - 1;
-        loop {
-            if !self.input.is_char_boundary(local_cursor) {
-                local_cursor -= 1;
-            } else {
-                if movement == CursorMovement::Word && local_cursor > 0 && self.input[local_cursor..].chars().next().unwrap_or_default().is_alphanumeric() {
-                    local_cursor -= 1;
-                    continue;
-                }
-                let diff = self.cursor - local_cursor;
-                self.cursor = local_cursor;
-                return diff;
+    // End of the word after `position`, skipping the separators in between
+    fn word_end_after(&self, position: usize, boundary: WordBoundary) -> usize {
+        let mut end = position;
+        let mut seen_word = false;
+        for c in self.input[position..].chars() {
+            if Prompt::in_word(c, boundary) {
+                seen_word = true;
+            } else if seen_word {
+                break;
             }
+            end += c.len_utf8();
+        }
+        end
+    }
+
+    // Position `count` words after the cursor, or before it when negative
+    fn word_position(&self, count: i64, boundary: WordBoundary) -> usize {
+        let mut position = self.cursor;
+        for _ in 0..count.unsigned_abs() {
+            position = if count < 0 { self.word_start_before(position, boundary) } else { self.word_end_after(position, boundary) };
         }
+        position
+    }
+
+    pub fn move_cursor(&mut self, pos: CursorPosition) -> bool {
+        let target = match pos {
+            CursorPosition::Origin => 0,
+            CursorPosition::End => self.input.len()
+        };
+        if self.cursor != target {
+            self.cursor = target;
+            return true;
+        }
+        false
+    }
+
+    pub fn move_cursor_left(&mut self, movement: CursorMovement) -> usize {
+        let target = match movement {
+            CursorMovement::One => self.previous_boundary(self.cursor),
+            CursorMovement::Word => self.word_start_before(self.cursor, WordBoundary::Alphanumeric)
+        };
+        let diff = self.cursor - target;
+        self.cursor = target;
+        diff
     }
 
     pub fn move_cursor_right(&mut self, movement: CursorMovement) -> usize {
-        if self.cursor == self.input.len() {
-            return 0;
-        }
-        let mut local_cursor = self.cursor + 1;
-        loop {
-            if !self.input.is_char_boundary(local_cursor) {
-                local_cursor += 1;
-            } else {
-                if movement == CursorMovement::Word && local_cursor < self.input.len() && self.input[local_cursor..].chars().next().unwrap_or_default().is_alphanumeric() {
-                    local_cursor += 1;
-                    continue;
-                }
-                let diff = local_cursor - self.cursor;
-                self.cursor = local_cursor;
-                return diff;
-            }
+        let target = match movement {
+            CursorMovement::One => self.next_boundary(self.cursor),
+            CursorMovement::Word => self.word_end_after(self.cursor, WordBoundary::Alphanumeric)
+        };
+        let diff = target - self.cursor;
+        self.cursor = target;
+        diff
+    }
+
+    // Moves `count` characters or words, to the left when negative
+    pub fn move_cursor_by(&mut self, count: i64, movement: CursorMovement) -> bool {
+        let mut moved = 0;
+        for _ in 0..count.unsigned_abs() {
+            moved += if count < 0 { self.move_cursor_left(movement) } else { self.move_cursor_right(movement) };
         }
+        moved > 0
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `e` with a combining acute accent, a woman technologist (ZWJ sequence), a flag and a CJK ideograph
+    const MIXED: &str = "ae\u{301}\u{1f469}\u{200d}\u{1f4bb}\u{1f1eb}\u{1f1f7}\u{6f22}";
+
+    fn prompt_with(input: &str, cursor: usize) -> Prompt {
+        let mut prompt = Prompt::new("");
+        prompt.set_input(input);
+        prompt.set_cursor(cursor);
+        prompt
+    }
+
+    #[test]
+    fn boundaries_skip_whole_clusters() {
+        let prompt = prompt_with(MIXED, MIXED.len());
+        let mut forward = vec![0];
+        while *forward.last().unwrap() < MIXED.len() {
+            forward.push(prompt.next_boundary(*forward.last().unwrap()));
+        }
+        assert_eq!(forward, vec![0, 1, 4, 15, 23, 26]);
+        let mut backward = vec![MIXED.len()];
+        while *backward.last().unwrap() > 0 {
+            backward.push(prompt.previous_boundary(*backward.last().unwrap()));
+        }
+        forward.reverse();
+        assert_eq!(backward, forward);
+    }
+
+    #[test]
+    fn boundaries_stop_at_the_ends() {
+        let prompt = prompt_with(MIXED, 0);
+        assert_eq!(prompt.previous_boundary(0), 0);
+        assert_eq!(prompt.next_boundary(MIXED.len()), MIXED.len());
+    }
+
+    #[test]
+    fn remove_char_takes_combining_marks() {
+        let mut prompt = prompt_with(MIXED, 4);
+        assert!(prompt.remove_char(true));
+        assert_eq!(prompt.get_input(), "a\u{1f469}\u{200d}\u{1f4bb}\u{1f1eb}\u{1f1f7}\u{6f22}");
+        assert_eq!(prompt.get_cursor(), 1);
+    }
+
+    #[test]
+    fn remove_char_takes_emoji_sequences_and_flags() {
+        let mut prompt = prompt_with(MIXED, 4);
+        assert!(prompt.remove_char(false));
+        assert_eq!(prompt.get_input(), "ae\u{301}\u{1f1eb}\u{1f1f7}\u{6f22}");
+        assert!(prompt.remove_char(false));
+        assert_eq!(prompt.get_input(), "ae\u{301}\u{6f22}");
+        assert_eq!(prompt.get_cursor(), 4);
+        assert!(prompt.remove_char(false));
+        assert!(!prompt.remove_char(false));
+        assert_eq!(prompt.get_input(), "ae\u{301}");
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn transpose_chars_swaps_clusters_at_the_end() {
+        let mut prompt = prompt_with("x\u{6f22}e\u{301}", "x\u{6f22}e\u{301}".len());
+        assert!(prompt.transpose_chars(1));
+        assert_eq!(prompt.get_input(), "xe\u{301}\u{6f22}");
+        assert_eq!(prompt.get_cursor(), "xe\u{301}\u{6f22}".len());
+    }
+
+    #[test]
+    fn transpose_chars_drags_a_cluster_forward() {
+        let input = "\u{1f1eb}\u{1f1f7}ab";
+        let mut prompt = prompt_with(input, 8);
+        assert!(prompt.transpose_chars(2));
+        assert_eq!(prompt.get_input(), "ab\u{1f1eb}\u{1f1f7}");
+        assert_eq!(prompt.get_cursor(), input.len());
+    }
+
+    #[test]
+    fn transpose_chars_needs_two_clusters() {
+        let mut prompt = prompt_with("e\u{301}", 3);
+        assert!(!prompt.transpose_chars(1));
+        assert_eq!(prompt.get_input(), "e\u{301}");
+    }
+
+    // Each key is a command, as in the read loop
+    fn type_text(prompt: &mut Prompt, text: &str) {
+        for c in text.chars() {
+            prompt.begin_command();
+            prompt.add_char(c);
+        }
+    }
+
+    #[test]
+    fn undo_takes_a_typed_run_at_once() {
+        let mut prompt = Prompt::new("");
+        type_text(&mut prompt, "echo hi");
+        prompt.begin_command();
+        assert!(prompt.undo());
+        assert_eq!(prompt.get_input(), "");
+        assert_eq!(prompt.get_cursor(), 0);
+        assert!(!prompt.undo());
+    }
+
+    #[test]
+    fn undo_restores_a_kill_to_the_end() {
+        let mut prompt = Prompt::new("");
+        type_text(&mut prompt, "echo hi");
+        prompt.set_cursor(4);
+        prompt.begin_command();
+        assert!(prompt.truncate_input());
+        assert_eq!(prompt.get_input(), "echo");
+        prompt.begin_command();
+        assert!(prompt.undo());
+        assert_eq!(prompt.get_input(), "echo hi");
+        assert_eq!(prompt.get_cursor(), 4);
+    }
+
+    #[test]
+    fn undo_returns_from_history_browsing_at_once() {
+        let mut prompt = Prompt::new("");
+        type_text(&mut prompt, "ls");
+        for entry in ["make", "cd src", "git status"] {
+            prompt.begin_command();
+            prompt.set_input(entry);
+        }
+        prompt.begin_command();
+        assert!(prompt.undo());
+        assert_eq!(prompt.get_input(), "ls");
+        assert_eq!(prompt.get_cursor(), 2);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut prompt = Prompt::new("");
+        type_text(&mut prompt, "ab");
+        prompt.begin_command();
+        assert!(prompt.undo());
+        type_text(&mut prompt, "c");
+        prompt.begin_command();
+        assert!(!prompt.redo());
+        assert_eq!(prompt.get_input(), "c");
+    }
+}
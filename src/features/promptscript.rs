@@ -4,6 +4,7 @@ use crossterm::style::{Color, Print, ResetColor, SetForegroundColor};
 use crate::crossterm::QueueableCommand;
 
 use crate::core::cmdoutput::CmdOutput;
+use crate::core::config::EditMode;
 use crate::core::core::ShellState;
 use crate::eval::eval::eval_expr;
 use crate::parser::expand::expand_variable;
@@ -103,9 +104,17 @@ fn render_ps_tokens(state: &mut ShellState, tokens: &[Token]) -> CmdOutput {
                     },
                     "cmd" => {
                         if let Some(expr) = value {
-                            eval_expr(state, expr).unwrap();
+                            // The prompt is still drawn when its command fails
+                            if let Err(error) = eval_expr(state, expr) {
+                                output.stderr.extend(error.to_output(expr));
+                                output.stderr.extend(b"\r\n");
+                            }
                         }
-                    }
+                    },
+                    // Vi editing mode, empty with the emacs keymap
+                    "mode" if state.edit_mode == EditMode::Vi => {
+                        cursor.queue(Print(state.vi.indicator())).unwrap();
+                    },
                     _ => ()
                 }
             }
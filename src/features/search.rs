@@ -0,0 +1,147 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::core::core::ShellState;
+use crate::features::prompt::Prompt;
+use crate::rendering::prompt::repaint_prompt;
+use crate::rendering::search::render_search;
+
+// Incremental history search, started with Ctrl-R (older entries) or Ctrl-S (newer entries)
+pub struct HistorySearch {
+    pub query: String,
+    pub reverse: bool,
+    // No entry matches the query, the last match stays shown
+    pub failed: bool,
+    // Index of the matching entry, and where the match starts in it
+    pub found: Option<(usize, usize)>,
+    // History entry the search starts from, the end of history when not browsing it
+    start: usize,
+    // Input and history position before the search, restored by Ctrl-G
+    original: String,
+    original_cursor: usize,
+    original_index: Option<usize>
+}
+
+impl HistorySearch {
+    fn new(state: &ShellState, prompt: &Prompt, history_idx: &Option<usize>, reverse: bool) -> HistorySearch {
+        HistorySearch {
+            query: String::new(),
+            reverse,
+            failed: false,
+            found: None,
+            start: history_idx.unwrap_or(state.history.get_values().len()),
+            original: prompt.get_input().clone(),
+            original_cursor: prompt.get_cursor(),
+            original_index: *history_idx
+        }
+    }
+
+    // Looks for the query from the current match, which is a candidate again when the query grew
+    fn search(&mut self, values: &[String], include_current: bool) {
+        if self.query.is_empty() {
+            self.failed = false;
+            return;
+        }
+        let (from, include) = match self.found {
+            Some((index, _)) => (index, include_current),
+            None => (self.start, false)
+        };
+        let candidates: Vec<usize> = if self.reverse {
+            let end = if include { from + 1 } else { from };
+            (0..end.min(values.len())).rev().collect()
+        } else {
+            let begin = if include { from } else { from + 1 };
+            (begin..values.len()).collect()
+        };
+        for index in candidates {
+            let position = if self.reverse { values[index].rfind(&self.query) } else { values[index].find(&self.query) };
+            if let Some(position) = position {
+                self.found = Some((index, position));
+                self.failed = false;
+                return;
+            }
+        }
+        self.failed = true;
+    }
+
+    // Shows the matching entry, or the original input until something matches
+    fn show(&self, state: &ShellState, prompt: &mut Prompt) {
+        match self.found {
+            Some((index, position)) => {
+                prompt.set_input(state.history.get(index));
+                prompt.set_cursor(position);
+            },
+            None => {
+                prompt.set_input(&self.original);
+                prompt.set_cursor(self.original_cursor);
+            }
+        }
+    }
+}
+
+pub fn start_search(state: &mut ShellState, prompt: &mut Prompt, history_idx: &mut Option<usize>, reverse: bool) {
+    // Like Up, keeps the input for when Down goes past the newest entry
+    if history_idx.is_none() {
+        prompt.stash_input();
+    }
+    state.search = Some(HistorySearch::new(state, prompt, history_idx, reverse));
+    render_search(state, prompt);
+}
+
+// Ends the search, leaving the match in the input and browsing history from it
+pub fn accept_search(state: &mut ShellState, prompt: &mut Prompt, history_idx: &mut Option<usize>) {
+    let Some(search) = state.search.take() else {
+        return;
+    };
+    if let Some((index, _)) = search.found {
+        *history_idx = Some(index);
+    }
+    repaint_prompt(state, prompt);
+}
+
+// Handles a key while searching. Keys that end the search are returned as None, after the
+// match is accepted into the input, so that they are then handled as usual: Enter runs it,
+// arrows and Esc leave it for editing.
+pub fn handle_search_key(state: &mut ShellState, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: KeyEvent) -> Option<(i32, bool)> {
+    let search = state.search.as_mut()?;
+    let values = state.history.get_values();
+    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+    match event.code {
+        KeyCode::Char(c @ ('r' | 's')) if ctrl => {
+            let reverse = c == 'r';
+            // Changing direction keeps the match and looks past it
+            search.reverse = reverse;
+            search.search(values, false);
+        },
+        KeyCode::Char('g') if ctrl => {
+            prompt.set_input(&search.original);
+            prompt.set_cursor(search.original_cursor);
+            *history_idx = search.original_index;
+            state.search = None;
+            repaint_prompt(state, prompt);
+            return Some((0, false));
+        },
+        KeyCode::Char(c) if !event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+            search.query.push(c);
+            search.search(values, true);
+        },
+        KeyCode::Backspace if event.modifiers.is_empty() => {
+            // Searches the shorter query again from where the search started
+            search.query.pop();
+            search.found = None;
+            search.search(values, true);
+        },
+        _ => {
+            accept_search(state, prompt, history_idx);
+            // Esc only accepts the match
+            if event.code == KeyCode::Esc {
+                return Some((0, false));
+            }
+            return None;
+        }
+    }
+    if let Some(search) = state.search.as_ref() {
+        search.show(state, prompt);
+    }
+    render_search(state, prompt);
+    Some((0, false))
+}
@@ -0,0 +1,87 @@
+use crate::core::core::ShellState;
+
+// Edit distance counting insertions, deletions, substitutions and transpositions of adjacent characters
+fn damerau_levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+// The builtin, alias, function or program in PATH closest to a mistyped command name.
+// Short names only accept a single typo.
+pub fn suggest_command(state: &mut ShellState, name: &str) -> Option<String> {
+    if name.contains('/') {
+        return None;
+    }
+    let typed: Vec<char> = name.chars().collect();
+    let max_distance = if typed.len() <= 4 { 1 } else { 2 };
+    let mut candidates: Vec<String> = state.builtins.list().iter()
+                                                     .map(|builtin| builtin.name().to_string())
+                                                     .filter(|name| state.builtins.is_enabled(name))
+                                                     .collect();
+    candidates.extend(state.aliases.keys().cloned());
+    candidates.extend(state.functions.keys().cloned());
+    candidates.extend(state.path_cache.get_executables().iter().cloned());
+    candidates.sort();
+    candidates.dedup();
+    let mut best: Option<(usize, String)> = None;
+    for candidate in candidates {
+        let chars: Vec<char> = candidate.chars().collect();
+        if chars.len().abs_diff(typed.len()) > max_distance {
+            continue;
+        }
+        let distance = damerau_levenshtein(&typed, &chars);
+        // Replacing every character is no suggestion
+        if distance <= max_distance && distance < typed.len() && distance < chars.len() && best.as_ref().is_none_or(|(best_distance, _)| distance < *best_distance) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &str, b: &str) -> usize {
+        damerau_levenshtein(&a.chars().collect::<Vec<char>>(), &b.chars().collect::<Vec<char>>())
+    }
+
+    #[test]
+    fn counts_single_edits() {
+        assert_eq!(distance("grep", "grep"), 0);
+        assert_eq!(distance("gep", "grep"), 1);
+        assert_eq!(distance("greep", "grep"), 1);
+        assert_eq!(distance("grap", "grep"), 1);
+    }
+
+    #[test]
+    fn counts_a_transposition_as_one_edit() {
+        assert_eq!(distance("gerp", "grep"), 1);
+        assert_eq!(distance("sl", "ls"), 1);
+    }
+
+    #[test]
+    fn counts_distant_names() {
+        assert_eq!(distance("", "ls"), 2);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("caf\u{e9}", "cafe"), 1);
+    }
+}
@@ -0,0 +1,779 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::terminal;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::core::core::ShellState;
+use crate::core::fsio::flush_output;
+use crate::core::readloop::{handle_event, handle_input, redraw_input};
+use crate::features::autocomplete::Autocomplete;
+use crate::features::prompt::Prompt;
+use crate::rendering::prompt::{highlight_selection, repaint_prompt};
+
+// Largest count, so that `99999x` stays reasonable
+const MAX_COUNT: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ViMode {
+    Insert,
+    Normal,
+    Visual
+}
+
+pub struct ViState {
+    pub mode: ViMode,
+    // Keys of the normal mode command being typed, such as `"a2d`
+    keys: Vec<KeyEvent>,
+    registers: HashMap<char, String>,
+    // Last `f`, `t`, `F` or `T` and its character, repeated by `;` and `,`
+    last_find: Option<(char, char)>,
+    // Keys of the last change, replayed by `.`
+    last_change: Vec<KeyEvent>,
+    // Keys of the change being made, which lasts until insert mode is left
+    recording: Option<Vec<KeyEvent>>,
+    replaying: bool,
+    // Where the selection started in visual mode, as a character index
+    visual_start: usize
+}
+
+// A complete normal mode command: `"a 2 d 3 w`
+struct Command {
+    register: Option<char>,
+    count: Option<usize>,
+    operator: Option<char>,
+    // Motion, text object or action keys: `w`, `fx`, `iw`, `x`
+    target: Vec<char>
+}
+
+enum Parse {
+    Incomplete,
+    Complete(Command)
+}
+
+impl ViState {
+    pub fn new() -> ViState {
+        ViState {
+            mode: ViMode::Insert,
+            keys: Vec::new(),
+            registers: HashMap::new(),
+            last_find: None,
+            last_change: Vec::new(),
+            recording: None,
+            replaying: false,
+            visual_start: 0
+        }
+    }
+
+    // Each line starts in insert mode
+    pub fn reset(&mut self) {
+        self.mode = ViMode::Insert;
+        self.keys.clear();
+        self.recording = None;
+    }
+
+    // Shown by the `[mode]` tag of the prompt script
+    pub fn indicator(&self) -> &str {
+        match self.mode {
+            ViMode::Insert => "(ins)",
+            ViMode::Normal => "(cmd)",
+            ViMode::Visual => "(vis)"
+        }
+    }
+
+    // `"a` selects a register, `"A` appends to it and `"_` discards
+    fn store(&mut self, register: Option<char>, text: &str, yank: bool) {
+        let text = match register {
+            Some('_') => return,
+            Some(name) if name.is_ascii_uppercase() => {
+                let stored = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                stored.push_str(text);
+                stored.clone()
+            },
+            Some(name) => {
+                self.registers.insert(name, text.to_string());
+                text.to_string()
+            },
+            None => text.to_string()
+        };
+        if yank && register.is_none() {
+            self.registers.insert('0', text.clone());
+        }
+        self.registers.insert('"', text);
+    }
+
+    fn register(&self, register: Option<char>) -> Option<String> {
+        self.registers.get(&register.map_or('"', |name| name.to_ascii_lowercase())).cloned()
+    }
+}
+
+fn key_char(event: &KeyEvent) -> Option<char> {
+    if event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+        return None;
+    }
+    match event.code {
+        KeyCode::Char(c) => Some(c),
+        // Backspace moves left in normal mode
+        KeyCode::Backspace => Some('h'),
+        _ => None
+    }
+}
+
+fn parse_count(keys: &[char], start: usize) -> (Option<usize>, usize) {
+    let mut end = start;
+    // `0` alone is a motion
+    while keys.get(end).is_some_and(|c| c.is_ascii_digit() && (end > start || *c != '0')) {
+        end += 1;
+    }
+    if end == start {
+        return (None, end);
+    }
+    let count: String = keys[start..end].iter().collect();
+    (count.parse::<usize>().ok().map(|count| count.min(MAX_COUNT)), end)
+}
+
+fn parse_command(keys: &[char], visual: bool) -> Parse {
+    let mut index = 0;
+    let mut register = None;
+    if keys.first() == Some(&'"') {
+        let Some(name) = keys.get(1) else {
+            return Parse::Incomplete;
+        };
+        register = Some(*name);
+        index = 2;
+    }
+    let (count, next) = parse_count(keys, index);
+    index = next;
+    let mut operator = None;
+    let mut motion_count = None;
+    // In visual mode operators apply to the selection right away
+    if let Some(&key) = keys.get(index).filter(|key| "dcy".contains(**key) && !visual) {
+        operator = Some(key);
+        (motion_count, index) = parse_count(keys, index + 1);
+    }
+    let Some(&key) = keys.get(index) else {
+        return Parse::Incomplete;
+    };
+    let takes_argument = "fFtTr".contains(key) || ((operator.is_some() || visual) && "ia".contains(key));
+    if takes_argument && keys.len() < index + 2 {
+        return Parse::Incomplete;
+    }
+    let count = match (count, motion_count) {
+        (None, None) => None,
+        (count, motion_count) => Some((count.unwrap_or(1) * motion_count.unwrap_or(1)).min(MAX_COUNT))
+    };
+    Parse::Complete(Command { register, count, operator, target: keys[index..].to_vec() })
+}
+
+// The input is handled as grapheme clusters, like the emacs bindings do: a letter and its
+// combining accents, or an emoji sequence, are one character for motions and edits.
+fn graphemes(input: &str) -> Vec<&str> {
+    input.graphemes(true).collect()
+}
+
+// Whether `grapheme` is the character `c` alone
+fn is_char(grapheme: &str, c: char) -> bool {
+    let mut chars = grapheme.chars();
+    chars.next() == Some(c) && chars.next().is_none()
+}
+
+fn is_blank(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+// Character classes of vi words: blanks, letters digits and underscores, other characters.
+// Big words (W, B, E) are anything but blanks.
+fn class(grapheme: &str, big: bool) -> u8 {
+    if is_blank(grapheme) {
+        return 0;
+    }
+    let c = grapheme.chars().next().unwrap_or(' ');
+    if big || c.is_alphanumeric() || c == '_' {
+        return 1;
+    }
+    2
+}
+
+fn next_word_start(text: &[&str], position: usize, big: bool) -> usize {
+    let mut index = position;
+    if index >= text.len() {
+        return text.len();
+    }
+    let start_class = class(text[index], big);
+    while index < text.len() && start_class != 0 && class(text[index], big) == start_class {
+        index += 1;
+    }
+    while index < text.len() && class(text[index], big) == 0 {
+        index += 1;
+    }
+    index
+}
+
+// Last character of the word at or after `start`
+fn word_end_from(text: &[&str], start: usize, big: bool) -> usize {
+    let mut index = start;
+    while index < text.len() && class(text[index], big) == 0 {
+        index += 1;
+    }
+    if index >= text.len() {
+        return text.len().saturating_sub(1);
+    }
+    let word_class = class(text[index], big);
+    while index + 1 < text.len() && class(text[index + 1], big) == word_class {
+        index += 1;
+    }
+    index
+}
+
+fn previous_word_start(text: &[&str], position: usize, big: bool) -> usize {
+    if position == 0 {
+        return 0;
+    }
+    let mut index = position.min(text.len()) - 1;
+    while index > 0 && class(text[index], big) == 0 {
+        index -= 1;
+    }
+    let word_class = class(text[index], big);
+    while index > 0 && class(text[index - 1], big) == word_class {
+        index -= 1;
+    }
+    index
+}
+
+fn first_non_blank(text: &[&str]) -> usize {
+    text.iter().position(|grapheme| !is_blank(grapheme)).unwrap_or(text.len().saturating_sub(1))
+}
+
+// `f` and `t` search forward, `F` and `T` backward, `t` and `T` stop next to the character
+fn find(text: &[&str], cursor: usize, kind: char, target: char, count: usize) -> Option<(usize, bool)> {
+    let forward = kind == 'f' || kind == 't';
+    let mut found = cursor;
+    for _ in 0..count {
+        found = if forward {
+            (found + 1..text.len()).find(|index| is_char(text[*index], target))?
+        } else {
+            (0..found).rev().find(|index| is_char(text[*index], target))?
+        };
+    }
+    let position = match kind {
+        't' => found - 1,
+        'T' => found + 1,
+        _ => found
+    };
+    Some((position, forward))
+}
+
+// Target of a motion, and whether an operator includes the character there
+fn motion(vi: &mut ViState, text: &[&str], cursor: usize, key: char, argument: Option<char>, count: usize) -> Option<(usize, bool)> {
+    let mut position = cursor;
+    match key {
+        'h' => Some((cursor.saturating_sub(count), false)),
+        'l' | ' ' => Some(((cursor + count).min(text.len()), false)),
+        '0' => Some((0, false)),
+        '^' => Some((first_non_blank(text), false)),
+        '$' => Some((text.len().saturating_sub(1), true)),
+        'w' | 'W' => {
+            for _ in 0..count {
+                position = next_word_start(text, position, key == 'W');
+            }
+            Some((position, false))
+        },
+        'b' | 'B' => {
+            for _ in 0..count {
+                position = previous_word_start(text, position, key == 'B');
+            }
+            Some((position, false))
+        },
+        'e' | 'E' => {
+            for _ in 0..count {
+                position = word_end_from(text, position + 1, key == 'E');
+            }
+            Some((position, true))
+        },
+        'f' | 'F' | 't' | 'T' => {
+            let target = argument?;
+            vi.last_find = Some((key, target));
+            find(text, cursor, key, target, count)
+        },
+        ';' | ',' => {
+            let (kind, target) = vi.last_find?;
+            let kind = if key == ';' {
+                kind
+            } else {
+                match kind {
+                    'f' => 'F',
+                    'F' => 'f',
+                    't' => 'T',
+                    _ => 't'
+                }
+            };
+            find(text, cursor, kind, target, count)
+        },
+        _ => None
+    }
+}
+
+fn word_object(text: &[&str], cursor: usize, around: bool, big: bool) -> Option<(usize, usize)> {
+    if text.is_empty() {
+        return None;
+    }
+    let cursor = cursor.min(text.len() - 1);
+    let word_class = class(text[cursor], big);
+    let mut start = cursor;
+    while start > 0 && class(text[start - 1], big) == word_class {
+        start -= 1;
+    }
+    let mut end = cursor + 1;
+    while end < text.len() && class(text[end], big) == word_class {
+        end += 1;
+    }
+    if around && word_class == 0 {
+        // On blanks, `aw` is the blanks and the word after them
+        if let Some(next_class) = text.get(end).map(|grapheme| class(grapheme, big)) {
+            while end < text.len() && class(text[end], big) == next_class {
+                end += 1;
+            }
+        }
+    } else if around {
+        // The blanks after the word, or before it when there are none
+        let word_end = end;
+        while end < text.len() && class(text[end], big) == 0 {
+            end += 1;
+        }
+        if end == word_end {
+            while start > 0 && class(text[start - 1], big) == 0 {
+                start -= 1;
+            }
+        }
+    }
+    Some((start, end))
+}
+
+// The first pair of quotes that ends at or after the cursor
+fn quote_object(text: &[&str], cursor: usize, around: bool, quote: char) -> Option<(usize, usize)> {
+    let quotes: Vec<usize> = (0..text.len()).filter(|index| is_char(text[*index], quote)).collect();
+    let pair = quotes.chunks(2).find(|pair| pair.len() == 2 && cursor <= pair[1])?;
+    if around {
+        return Some((pair[0], pair[1] + 1));
+    }
+    Some((pair[0] + 1, pair[1]))
+}
+
+// The innermost pair of brackets around the cursor
+fn bracket_object(text: &[&str], cursor: usize, around: bool, open: char, close: char) -> Option<(usize, usize)> {
+    if text.is_empty() {
+        return None;
+    }
+    let cursor = cursor.min(text.len() - 1);
+    let mut depth = 0;
+    let mut start = None;
+    for index in (0..=cursor).rev() {
+        if is_char(text[index], close) && index != cursor {
+            depth += 1;
+        } else if is_char(text[index], open) {
+            if depth == 0 {
+                start = Some(index);
+                break;
+            }
+            depth -= 1;
+        }
+    }
+    let start = start?;
+    depth = 0;
+    let mut end = None;
+    for (index, grapheme) in text.iter().enumerate().skip(start + 1) {
+        if is_char(grapheme, open) {
+            depth += 1;
+        } else if is_char(grapheme, close) {
+            if depth == 0 {
+                end = Some(index);
+                break;
+            }
+            depth -= 1;
+        }
+    }
+    let end = end?;
+    if around {
+        return Some((start, end + 1));
+    }
+    Some((start + 1, end))
+}
+
+fn text_object(text: &[&str], cursor: usize, around: bool, object: char) -> Option<(usize, usize)> {
+    match object {
+        'w' | 'W' => word_object(text, cursor, around, object == 'W'),
+        '"' | '\'' | '`' => quote_object(text, cursor, around, object),
+        '(' | ')' | 'b' => bracket_object(text, cursor, around, '(', ')'),
+        '[' | ']' => bracket_object(text, cursor, around, '[', ']'),
+        '{' | '}' | 'B' => bracket_object(text, cursor, around, '{', '}'),
+        '<' | '>' => bracket_object(text, cursor, around, '<', '>'),
+        _ => None
+    }
+}
+
+// Characters an operator applies to
+fn operator_range(vi: &mut ViState, text: &[&str], cursor: usize, operator: char, target: &[char], count: usize) -> Option<(usize, usize)> {
+    let key = target[0];
+    // `dd`, `cc` and `yy` take the whole line
+    if key == operator {
+        return Some((0, text.len()));
+    }
+    if key == 'i' || key == 'a' {
+        return text_object(text, cursor, key == 'a', target[1]);
+    }
+    // Like vi, `cw` on a word changes up to its end, leaving the blanks after it
+    if operator == 'c' && (key == 'w' || key == 'W') && text.get(cursor).is_some_and(|grapheme| !is_blank(grapheme)) {
+        let mut end = word_end_from(text, cursor, key == 'W');
+        for _ in 1..count {
+            end = word_end_from(text, end + 1, key == 'W');
+        }
+        return Some((cursor, (end + 1).min(text.len())));
+    }
+    let (position, inclusive) = motion(vi, text, cursor, key, target.get(1).copied(), count)?;
+    if position >= cursor {
+        return Some((cursor, (position + inclusive as usize).min(text.len())));
+    }
+    Some((position, cursor))
+}
+
+fn byte_index(text: &[&str], index: usize) -> usize {
+    text[..index.min(text.len())].iter().map(|grapheme| grapheme.len()).sum()
+}
+
+fn set_cursor(prompt: &mut Prompt, text: &[&str], index: usize) {
+    prompt.set_cursor(byte_index(text, index));
+}
+
+fn replace(prompt: &mut Prompt, text: &[&str], start: usize, end: usize, replacement: &str) {
+    prompt.replace_range(byte_index(text, start), byte_index(text, end), replacement);
+}
+
+fn set_mode(state: &mut ShellState, prompt: &Prompt, mode: ViMode) {
+    if state.vi.mode == mode {
+        return;
+    }
+    state.vi.mode = mode;
+    // The prompt shows the mode
+    if prompt.ps1.contains("[mode]") {
+        repaint_prompt(state, prompt);
+    }
+}
+
+fn enter_insert(state: &mut ShellState, prompt: &Prompt) {
+    set_mode(state, prompt, ViMode::Insert);
+}
+
+fn apply_operator(state: &mut ShellState, prompt: &mut Prompt, text: &[&str], operator: char, range: (usize, usize), register: Option<char>) {
+    let (start, end) = range;
+    let selected = text[start..end].concat();
+    state.vi.store(register, &selected, operator == 'y');
+    match operator {
+        'y' => set_cursor(prompt, text, start),
+        'c' => {
+            replace(prompt, text, start, end, "");
+            enter_insert(state, prompt);
+        },
+        _ => replace(prompt, text, start, end, "")
+    }
+}
+
+fn change_case(text: &[&str], start: usize, end: usize, key: char) -> String {
+    let mut changed = String::new();
+    for c in text[start..end].iter().flat_map(|grapheme| grapheme.chars()) {
+        match key {
+            'u' => changed.extend(c.to_lowercase()),
+            'U' => changed.extend(c.to_uppercase()),
+            _ if c.is_uppercase() => changed.extend(c.to_lowercase()),
+            _ => changed.extend(c.to_uppercase())
+        }
+    }
+    changed
+}
+
+// Creates the file the input is edited in, readable by the user only. The name is not
+// predictable, and an existing file or link found at it is never opened.
+fn create_edit_file() -> Option<(PathBuf, File)> {
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.subsec_nanos()).unwrap_or(0);
+    for attempt in 0..100u32 {
+        let path = env::temp_dir().join(format!("lambdash-edit-{}-{:08x}.sh", process::id(), seed.wrapping_add(attempt.wrapping_mul(0x9e3779b9))));
+        match OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path) {
+            Ok(file) => return Some((path, file)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(_) => return None
+        }
+    }
+    None
+}
+
+// `v`: edits the input in $VISUAL or $EDITOR, then runs it like bash does
+fn edit_in_editor(state: &mut ShellState, prompt: &mut Prompt) -> bool {
+    let Some((path, mut file)) = create_edit_file() else {
+        return false;
+    };
+    let written = file.write_all(format!("{}\n", prompt.get_input()).as_bytes());
+    drop(file);
+    if written.is_err() {
+        let _ = fs::remove_file(&path);
+        return false;
+    }
+    let editor = ["VISUAL", "EDITOR"].iter()
+                                     .filter_map(|name| state.variables.get(name))
+                                     .find(|editor| !editor.is_empty())
+                                     .unwrap_or("vi".to_string());
+    flush_output(state);
+    let _ = terminal::disable_raw_mode();
+    // Through sh, so that EDITOR may hold options
+    let status = process::Command::new("sh").arg("-c").arg(format!("{} \"$1\"", editor)).arg("sh").arg(&path).status();
+    let _ = terminal::enable_raw_mode();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    match (status, edited) {
+        (Ok(status), Ok(edited)) if status.success() => {
+            prompt.set_input(edited.trim_end_matches('\n'));
+            true
+        },
+        _ => false
+    }
+}
+
+fn visual_command(state: &mut ShellState, prompt: &mut Prompt, text: &[&str], cursor: usize, command: &Command) {
+    let start = state.vi.visual_start.min(cursor);
+    let end = (state.vi.visual_start.max(cursor) + 1).min(text.len());
+    match command.target[0] {
+        'd' | 'x' => {
+            apply_operator(state, prompt, text, 'd', (start, end), command.register);
+            set_mode(state, prompt, ViMode::Normal);
+        },
+        'c' | 's' => apply_operator(state, prompt, text, 'c', (start, end), command.register),
+        'y' => {
+            apply_operator(state, prompt, text, 'y', (start, end), command.register);
+            set_mode(state, prompt, ViMode::Normal);
+        },
+        key @ ('~' | 'u' | 'U') => {
+            replace(prompt, text, start, end, &change_case(text, start, end, key));
+            set_cursor(prompt, text, start);
+            set_mode(state, prompt, ViMode::Normal);
+        },
+        'r' => {
+            let replacement: String = std::iter::repeat_n(command.target[1], end - start).collect();
+            replace(prompt, text, start, end, &replacement);
+            set_cursor(prompt, text, start);
+            set_mode(state, prompt, ViMode::Normal);
+        },
+        // The cursor goes to the other end of the selection
+        'o' => {
+            set_cursor(prompt, text, state.vi.visual_start);
+            state.vi.visual_start = cursor;
+        },
+        key @ ('i' | 'a') => {
+            if let Some((object_start, object_end)) = text_object(text, cursor, key == 'a', command.target[1]) {
+                state.vi.visual_start = object_start;
+                set_cursor(prompt, text, object_end.saturating_sub(1).max(object_start));
+            }
+        },
+        'V' => set_mode(state, prompt, ViMode::Normal),
+        _ => ()
+    }
+}
+
+fn action(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, text: &[&str], cursor: usize, command: &Command) -> (i32, bool) {
+    let count = command.count.unwrap_or(1);
+    let register = command.register;
+    match command.target[0] {
+        'x' if cursor < text.len() => apply_operator(state, prompt, text, 'd', (cursor, (cursor + count).min(text.len())), register),
+        'X' if cursor > 0 => apply_operator(state, prompt, text, 'd', (cursor.saturating_sub(count), cursor), register),
+        's' => apply_operator(state, prompt, text, 'c', (cursor, (cursor + count).min(text.len())), register),
+        'S' => apply_operator(state, prompt, text, 'c', (0, text.len()), register),
+        'C' => apply_operator(state, prompt, text, 'c', (cursor, text.len()), register),
+        'D' => apply_operator(state, prompt, text, 'd', (cursor, text.len()), register),
+        'Y' => apply_operator(state, prompt, text, 'y', (0, text.len()), register),
+        'r' if cursor + count <= text.len() => {
+            let replacement: String = std::iter::repeat_n(command.target[1], count).collect();
+            replace(prompt, text, cursor, cursor + count, &replacement);
+            set_cursor(prompt, text, cursor + count - 1);
+        },
+        '~' if cursor < text.len() => {
+            let end = (cursor + count).min(text.len());
+            replace(prompt, text, cursor, end, &change_case(text, cursor, end, '~'));
+        },
+        key @ ('p' | 'P') => {
+            if let Some(pasted) = state.vi.register(register) {
+                let pasted = pasted.repeat(count);
+                let at = if key == 'p' && !text.is_empty() { cursor + 1 } else { cursor };
+                replace(prompt, text, at, at, &pasted);
+                set_cursor(prompt, text, at + pasted.graphemes(true).count().saturating_sub(1));
+            }
+        },
+        'i' => enter_insert(state, prompt),
+        'a' => {
+            set_cursor(prompt, text, (cursor + 1).min(text.len()));
+            enter_insert(state, prompt);
+        },
+        'I' => {
+            set_cursor(prompt, text, first_non_blank(text));
+            enter_insert(state, prompt);
+        },
+        'A' => {
+            set_cursor(prompt, text, text.len());
+            enter_insert(state, prompt);
+        },
+        'u' => {
+            for _ in 0..count {
+                prompt.undo();
+            }
+        },
+        '.' => {
+            let keys = state.vi.last_change.clone();
+            state.vi.replaying = true;
+            for _ in 0..count {
+                for key in &keys {
+                    handle_event(state, autocomplete, prompt, history_idx, Event::Key(*key));
+                }
+            }
+            state.vi.replaying = false;
+        },
+        'V' => {
+            state.vi.visual_start = cursor.min(text.len().saturating_sub(1));
+            set_mode(state, prompt, ViMode::Visual);
+        },
+        'v' => {
+            if edit_in_editor(state, prompt) {
+                repaint_prompt(state, prompt);
+                return (1, true);
+            }
+            repaint_prompt(state, prompt);
+        },
+        key @ ('j' | 'k') => {
+            let code = if key == 'j' { KeyCode::Down } else { KeyCode::Up };
+            // Every entry, the text before the cursor is no prefix in normal mode
+            prompt.set_cursor(0);
+            for _ in 0..count {
+                handle_input(state, autocomplete, prompt, history_idx, KeyEvent::new(code, KeyModifiers::NONE), 1);
+            }
+            prompt.set_cursor(0);
+        },
+        _ => ()
+    }
+    (0, false)
+}
+
+fn execute(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, command: Command, keys: Vec<KeyEvent>) -> (i32, bool) {
+    let input = prompt.get_input().clone();
+    let text = graphemes(&input);
+    let cursor = input[..prompt.get_cursor()].graphemes(true).count();
+    let count = command.count.unwrap_or(1);
+    let key = command.target[0];
+    let visual = state.vi.mode == ViMode::Visual;
+    let changes = command.operator.is_some_and(|operator| operator != 'y') || (!visual && "xXsSCDr~pPiaIA".contains(key));
+    if changes {
+        prompt.begin_change();
+        if !state.vi.replaying {
+            state.vi.recording = Some(keys);
+        }
+    }
+    let mut result = (0, false);
+    if let Some(operator) = command.operator {
+        if let Some(range) = operator_range(&mut state.vi, &text, cursor, operator, &command.target, count) {
+            apply_operator(state, prompt, &text, operator, range, command.register);
+        }
+    } else if let Some((position, _)) = motion(&mut state.vi, &text, cursor, key, command.target.get(1).copied(), count) {
+        set_cursor(prompt, &text, position);
+    } else if visual {
+        visual_command(state, prompt, &text, cursor, &command);
+    } else {
+        result = action(state, autocomplete, prompt, history_idx, &text, cursor, &command);
+    }
+    if state.vi.mode != ViMode::Insert {
+        finish_change(state, prompt);
+    }
+    result
+}
+
+// Closes the undo group and keeps the keys of the change for `.`
+fn finish_change(state: &mut ShellState, prompt: &mut Prompt) {
+    prompt.end_change();
+    if let Some(keys) = state.vi.recording.take() {
+        state.vi.last_change = keys;
+    }
+    // Outside insert mode the cursor stays on a character
+    let input = prompt.get_input();
+    if prompt.get_cursor() == input.len() && !input.is_empty() {
+        let last = prompt.previous_boundary(input.len());
+        prompt.set_cursor(last);
+    }
+}
+
+fn redraw(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &Prompt) {
+    redraw_input(state, autocomplete, prompt);
+    if state.vi.mode == ViMode::Visual {
+        let text = graphemes(prompt.get_input());
+        let cursor = prompt.get_input()[..prompt.get_cursor()].graphemes(true).count();
+        let start = state.vi.visual_start.min(cursor);
+        let end = (state.vi.visual_start.max(cursor) + 1).min(text.len());
+        highlight_selection(state, prompt, byte_index(&text, start), byte_index(&text, end));
+    }
+}
+
+// Handles a key in vi mode. Keys of insert mode, and keys such as Enter or Ctrl-C in the
+// other modes, are left to the emacs bindings by returning None.
+pub fn handle_vi_key(state: &mut ShellState, autocomplete: &mut Autocomplete, prompt: &mut Prompt, history_idx: &mut Option<usize>, event: KeyEvent) -> Option<(i32, bool)> {
+    if state.vi.mode == ViMode::Insert {
+        if !state.vi.replaying {
+            if let Some(recording) = state.vi.recording.as_mut() {
+                recording.push(event);
+            }
+        }
+        if event.code != KeyCode::Esc {
+            return None;
+        }
+        state.vi.mode = ViMode::Normal;
+        if prompt.get_cursor() > 0 {
+            let previous = prompt.previous_boundary(prompt.get_cursor());
+            prompt.set_cursor(previous);
+        }
+        finish_change(state, prompt);
+        if prompt.ps1.contains("[mode]") {
+            repaint_prompt(state, prompt);
+        }
+        redraw(state, autocomplete, prompt);
+        return Some((0, false));
+    }
+    if event.code == KeyCode::Char('r') && event.modifiers.contains(KeyModifiers::CONTROL) {
+        state.vi.keys.clear();
+        if prompt.redo() {
+            finish_change(state, prompt);
+            redraw(state, autocomplete, prompt);
+        }
+        return Some((0, false));
+    }
+    if event.code == KeyCode::Esc {
+        state.vi.keys.clear();
+        set_mode(state, prompt, ViMode::Normal);
+        redraw(state, autocomplete, prompt);
+        return Some((0, false));
+    }
+    if key_char(&event).is_none() {
+        state.vi.keys.clear();
+        return None;
+    }
+    state.vi.keys.push(event);
+    let keys: Vec<char> = state.vi.keys.iter().filter_map(key_char).collect();
+    match parse_command(&keys, state.vi.mode == ViMode::Visual) {
+        Parse::Incomplete => Some((0, false)),
+        Parse::Complete(command) => {
+            let keys = std::mem::take(&mut state.vi.keys);
+            let result = execute(state, autocomplete, prompt, history_idx, command, keys);
+            if !result.1 {
+                redraw(state, autocomplete, prompt);
+            }
+            Some(result)
+        }
+    }
+}
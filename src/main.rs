@@ -1,14 +1,15 @@
-use core::{error::ShellError, readloop::prompt_readloop};
-use std::io::{stdout, stderr};
+use core::{error::ShellError, fsio::{flush_output, ShellOutput}, readloop::prompt_readloop};
+use std::io::stderr;
 extern crate crossterm;
 
 use features::autocomplete::Autocomplete;
 use crossterm::{
     cursor,
     style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal,
     QueueableCommand
 };
-use rendering::prompt::{align_cursor_with_prompt, print_prompt};
+use rendering::{layout::Layout, prompt::{align_cursor_with_prompt, print_prompt}};
 
 mod core;
 mod eval;
@@ -19,45 +20,63 @@ mod rendering;
 use core::core::{ShellState};
 use features::prompt::Prompt;
 use eval::eval::eval_expr;
+use eval::traps::run_trap;
+use core::jobs::notify_finished_jobs;
+use core::osstr::text_to_word;
+use parser::tokenizer::TokenizationErrorKind;
 
 fn main() {
-    let mut stdout = stdout();
+    let mut stdout = ShellOutput::new(libc::STDOUT_FILENO);
     let mut stderr = stderr();
     let mut state: ShellState = ShellState::new(&mut stdout, &mut stderr);
     let mut prompt = Prompt::new(&state.config.prompt.ps1);
+    state.enter_shell_level();
     // main loop
     loop {
         let mut autocomplete = Autocomplete::new();
         let mut history_idx: Option<usize> = None;
         prompt.unstash_input();
+        notify_finished_jobs(&mut state);
+        state.vi.reset();
         print_prompt(&mut state, &prompt);
-        state.ps1pos = cursor::position().unwrap();
-        state.stderr.flush().unwrap();
+        // The read loop reports a failure to write the prompt
+        let _ = state.stdout.flush();
+        state.ps1pos = cursor::position().unwrap_or(state.ps1pos);
+        let _ = state.stderr.flush();
         state.stdout.queue(Print(prompt.get_input())).unwrap();
-        state.stdout.flush().unwrap();
+        let _ = state.stdout.flush();
         // read loop
         let mut chars_read = prompt_readloop(&mut state, &mut autocomplete, &mut prompt, &mut history_idx);
         state.stdout.queue(Print("\n")).unwrap()
                     .queue(cursor::MoveToColumn(0)).unwrap();
         if prompt.has_input() {
+            state.lineno += 1;
             let mut expr = prompt.get_input().clone();
             // eval loop
             loop {
-                match eval_expr(&mut state, &expr) {
+                match eval_expr(&mut state, &text_to_word(&expr)) {
                     Ok(_) => {
                         state.history.submit(&expr);
                         break; // Exit loop after successful execution
                     }
                     Err(e) => match e {
-                        ShellError::Tokenization(_) => {
+                        ShellError::Tokenization(error) if error.kind == TokenizationErrorKind::UnmatchedCharacter => {
+                            // The newline printed after the input scrolled the terminal if it was on the last row
+                            let (_, termrows) = terminal::size().unwrap_or(state.termsize);
+                            let rows = Layout::new(state.ps1pos.0, state.termsize.0).height(prompt.get_input());
+                            if state.ps1pos.1 + rows as u16 >= termrows {
+                                state.ps1pos.1 -= 1;
+                            }
                             prompt.add_char('\n');
-                            state.ps1pos.1 -= 1;
                             align_cursor_with_prompt(&mut state, &prompt);
-                            state.stdout.flush().unwrap();
+                            let _ = state.stdout.flush();
                             prompt_readloop(&mut state, &mut autocomplete, &mut prompt, &mut history_idx);
+                            state.stdout.queue(Print("\n")).unwrap()
+                                        .queue(cursor::MoveToColumn(0)).unwrap();
                             expr = prompt.get_input().clone();
                         }
-                        ShellError::ExitRequest => {
+                        ShellError::ExitRequest(code) => {
+                            state.status = code;
                             chars_read = -1;
                             break;
                         }
@@ -71,6 +90,10 @@ fn main() {
                                     .queue(ResetColor).unwrap()
                                     .queue(Print("\n")).unwrap();
                             }
+                            if let Err(ShellError::ExitRequest(code)) = run_trap(&mut state, "ERR") {
+                                state.status = code;
+                                chars_read = -1;
+                            }
                             break; // Exit loop on execution error
                         }
                     },
@@ -79,8 +102,19 @@ fn main() {
             prompt.clear_input();
         }
         if chars_read == -1 {
-            state.history.persist();
-            break;
+            // `exit N` inside the EXIT trap overrides the pending status
+            if let Err(ShellError::ExitRequest(code)) = run_trap(&mut state, "EXIT") {
+                state.status = code;
+            }
+            flush_output(&mut state);
+            if let Err(error) = state.history.persist() {
+                let error: ShellError = error.into();
+                if let Ok(error_str) = String::from_utf8(error.to_output("")) {
+                    let _ = state.stderr.queue(Print(format!("{}\n", error_str)));
+                }
+                let _ = state.stderr.flush();
+            }
+            std::process::exit(state.status);
         }
     }
 }
\ No newline at end of file
@@ -1,38 +1,389 @@
-use std::env;
-use glob::glob;
+use std::fs;
+
+use glob::Pattern;
+use unic_emoji_char::is_emoji;
 
 use crate::core::core::ShellState;
-use crate::parser::tokenizer::Token;
+use crate::core::osstr::{os_to_word, word_to_os};
+use crate::core::variables::Value;
+use crate::eval::builtins::dirstack::{get_stack, stack_index};
+use crate::parser::tokenizer::{tokenize, Token, WordPart};
+
+// Value of a parameter. `$@` and `${name[@]}` keep one field per element even
+// within double quotes; other lists (`$*`, `${name[*]}`) are joined with the first IFS character.
+pub struct Expansion {
+    pub values: Vec<String>,
+    pub separate: bool
+}
+
+impl Expansion {
+    fn single(value: String) -> Expansion {
+        Expansion { values: vec![value], separate: false }
+    }
+
+    pub fn join(&self, state: &ShellState) -> String {
+        let separator = match state.variables.get("IFS") {
+            Some(ifs) => ifs.chars().next().map(|c| c.to_string()).unwrap_or_default(),
+            None => " ".to_string()
+        };
+        self.values.join(&separator)
+    }
+}
+
+// Splits `name[subscript]rest` into its parts. Special parameters are a single character.
+fn split_parameter(expr: &str) -> Option<(&str, Option<&str>, &str)> {
+    let name_len = match expr.chars().next()? {
+        c if "?#@*$!-".contains(c) => 1,
+        c if c.is_ascii_digit() => expr.find(|c: char| !c.is_ascii_digit()).unwrap_or(expr.len()),
+        _ => expr.find(|c: char| !(c.is_alphanumeric() || c == '_' || is_emoji(c))).unwrap_or(expr.len())
+    };
+    let (name, rest) = expr.split_at(name_len);
+    if name.is_empty() {
+        return None;
+    }
+    if let Some(subscript) = rest.strip_prefix('[') {
+        let close = subscript.rfind(']')?;
+        return Some((name, Some(&subscript[..close]), &subscript[close + 1..]));
+    }
+    Some((name, None, rest))
+}
+
+// Subscripts may themselves contain quotes and parameters: `${map["$key"]}`
+fn expand_subscript(state: &mut ShellState, subscript: &str) -> String {
+    match tokenize(&subscript.to_string()) {
+        Ok(mut tokens) => {
+            expand_tokens(state, &mut tokens);
+            let words: Vec<String> = tokens.iter().filter_map(|token| match token {
+                Token::Word(word) => Some(word.clone()),
+                _ => None
+            }).collect();
+            words.join(" ")
+        },
+        Err(_) => subscript.to_string()
+    }
+}
+
+// Elements of a list parameter (`@`, `*`, `name[@]`, `name[*]`) along with their index
+fn list_values(state: &ShellState, name: &str) -> Vec<(usize, String)> {
+    if name == "@" || name == "*" {
+        return state.positional.iter().cloned().enumerate().skip(1).collect();
+    }
+    match state.variables.get_value(name) {
+        Some(Value::Indexed(values)) => values.into_iter().collect(),
+        Some(Value::Associative(values)) => values.into_values().enumerate().collect(),
+        Some(Value::Scalar(value)) => vec![(0, value)],
+        None => Vec::new()
+    }
+}
+
+fn scalar_value(state: &mut ShellState, name: &str, subscript: Option<&str>) -> Option<String> {
+    match (name, subscript) {
+        ("?", None) => Some(format!("{}", state.status)),
+        ("#", None) => Some(format!("{}", state.positional.len().saturating_sub(1))),
+        (digits, None) if digits.starts_with(|c: char| c.is_ascii_digit()) => state.positional.get(digits.parse::<usize>().ok()?).cloned(),
+        (name, Some(subscript)) => {
+            let key = expand_subscript(state, subscript);
+            state.variables.get_element(name, &key)
+        },
+        (name, None) => state.dynamic_parameter(name).or_else(|| state.variables.get(name))
+    }
+}
+
+// `:offset` or `:offset:length` applied to a list (by index) or to a string (by character)
+fn parse_slice(state: &mut ShellState, slice: &str) -> Option<(i64, Option<i64>)> {
+    let (offset, length) = match slice.split_once(':') {
+        Some((offset, length)) => (offset, Some(length)),
+        None => (slice, None)
+    };
+    let offset = expand_subscript(state, offset);
+    let offset = state.variables.eval_index(&offset)?;
+    let length = match length {
+        Some(length) => {
+            let length = expand_subscript(state, length);
+            Some(state.variables.eval_index(&length)?)
+        },
+        None => None
+    };
+    Some((offset, length))
+}
+
+fn slice_string(value: &str, offset: i64, length: Option<i64>) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let len = chars.len() as i64;
+    let start = if offset < 0 { (len + offset).max(0) } else { offset.min(len) };
+    let end = match length {
+        Some(length) if length < 0 => (len + length).max(start),
+        Some(length) => (start + length).min(len),
+        None => len
+    };
+    chars[start as usize..end as usize].iter().collect()
+}
+
+// Evaluates the content of `${...}` (or a bare `$name`). None when the parameter is unset.
+pub fn expand_parameter(state: &mut ShellState, expr: &str) -> Option<Expansion> {
+    // ${#name}, ${#name[@]}: length of a value or number of elements
+    if let Some(rest) = expr.strip_prefix('#').filter(|rest| !rest.is_empty()) {
+        let (name, subscript, tail) = split_parameter(rest)?;
+        if !tail.is_empty() {
+            return None;
+        }
+        let length = match subscript {
+            Some("@") | Some("*") => list_values(state, name).len(),
+            _ if name == "@" || name == "*" => list_values(state, name).len(),
+            _ => scalar_value(state, name, subscript).unwrap_or_default().chars().count()
+        };
+        return Some(Expansion::single(length.to_string()));
+    }
+    // ${!name[@]}: indices or keys of an array
+    if let Some(rest) = expr.strip_prefix('!').filter(|rest| !rest.is_empty()) {
+        let (name, subscript, tail) = split_parameter(rest)?;
+        if !tail.is_empty() || !matches!(subscript, Some("@") | Some("*")) {
+            return None;
+        }
+        let keys = match state.variables.get_value(name) {
+            Some(Value::Indexed(values)) => values.keys().map(|key| key.to_string()).collect(),
+            Some(Value::Associative(values)) => values.keys().cloned().collect(),
+            Some(Value::Scalar(_)) => vec!["0".to_string()],
+            None => Vec::new()
+        };
+        return Some(Expansion { values: keys, separate: subscript == Some("@") });
+    }
+    let (name, subscript, tail) = split_parameter(expr)?;
+    let slice = match tail {
+        "" => None,
+        tail => Some(parse_slice(state, tail.strip_prefix(':')?)?)
+    };
+    let is_list = matches!(subscript, Some("@") | Some("*")) || (subscript.is_none() && (name == "@" || name == "*"));
+    if is_list {
+        let separate = subscript == Some("@") || (subscript.is_none() && name == "@");
+        let mut values = list_values(state, name);
+        if let Some((offset, length)) = slice {
+            if name == "@" || name == "*" {
+                // Slices of the positional parameters count $0
+                values.insert(0, (0, state.positional.first().cloned().unwrap_or_default()));
+            }
+            let end = values.last().map(|(index, _)| *index as i64 + 1).unwrap_or(0);
+            let start = if offset < 0 { end + offset } else { offset };
+            values.retain(|(index, _)| *index as i64 >= start);
+            if let Some(length) = length {
+                if length < 0 {
+                    return None;
+                }
+                values.truncate(length as usize);
+            }
+        }
+        return Some(Expansion { values: values.into_iter().map(|(_, value)| value).collect(), separate });
+    }
+    let value = scalar_value(state, name, subscript)?;
+    Some(Expansion::single(match slice {
+        Some((offset, length)) => slice_string(&value, offset, length),
+        None => value
+    }))
+}
 
 pub fn expand_variable(state: &mut ShellState, var_name: &str) -> String {
-  match var_name {
-      "?" => format!("{}", state.status),
-      _ => {
-          match env::var(var_name) {
-              Ok(var_value) => var_value,
-              Err(_) => format!("${}", var_name)
-          }
-      }
+  match expand_parameter(state, var_name) {
+      Some(expansion) => expansion.join(state),
+      None => format!("${}", var_name)
   }
 }
 
+// Expands the tilde prefix of a word: `~` is $HOME, `~+` $PWD, `~-` $OLDPWD,
+// and `~N`, `~+N`, `~-N` are entries of the directory stack as listed by `dirs`
+pub fn expand_tilde(state: &ShellState, word: &str) -> Option<String> {
+    let (prefix, rest) = match word.find('/') {
+        Some(pos) => word.split_at(pos),
+        None => (word, "")
+    };
+    let expanded = match &prefix[1..] {
+        "" => state.variables.get("HOME")?,
+        "+" => state.variables.get("PWD")?,
+        "-" => state.variables.get("OLDPWD")?,
+        index => {
+            let stack = get_stack(state);
+            let index = if index.starts_with(['+', '-']) { index.to_string() } else { format!("+{}", index) };
+            stack.get(stack_index(&index, stack.len())?)?.clone()
+        }
+    };
+    Some(format!("{}{}", expanded, rest))
+}
+
+fn join_path(directory: &str, name: &str) -> String {
+    if directory.is_empty() {
+        return name.to_string();
+    }
+    if directory.ends_with('/') {
+        return format!("{}{}", directory, name);
+    }
+    format!("{}/{}", directory, name)
+}
+
+// Matches the pattern one path component at a time. Unlike the glob crate, which skips
+// names that are not valid UTF-8, names are matched in their escaped form and kept as is.
 pub fn expand_glob(glob_expr: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
-
-    // Use glob crate to match the glob expression
-    for entry in glob(glob_expr).unwrap() {
-        match entry {
-            Ok(path) => {
-                // Convert Path to String and push a Token::Word to the result vector
-                if let Some(path_str) = path.to_str() {
-                    tokens.push(Token::Word(path_str.to_string()));
+    // Like bash, an invalid pattern such as `[z-a]*` matches nothing and the word is kept as is
+    if Pattern::new(glob_expr).is_err() {
+        return Vec::new();
+    }
+    let mut paths: Vec<String> = vec![if glob_expr.starts_with('/') { "/".to_string() } else { String::new() }];
+    for component in glob_expr.split('/').filter(|component| !component.is_empty()) {
+        let Ok(pattern) = Pattern::new(component) else {
+            return Vec::new();
+        };
+        let mut matches = Vec::new();
+        for directory in paths {
+            if !component.contains(['*', '?', '[']) {
+                matches.push(join_path(&directory, component));
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(word_to_os(if directory.is_empty() { "." } else { &directory })) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries.flatten()
+                                                .map(|entry| os_to_word(&entry.file_name()))
+                                                .filter(|name| pattern.matches(name))
+                                                .collect();
+            names.sort();
+            matches.extend(names.iter().map(|name| join_path(&directory, name)));
+        }
+        paths = matches;
+    }
+    // `dir/*/` only matches directories
+    let directories_only = glob_expr.ends_with('/');
+    paths.into_iter()
+                .filter(|path| match fs::symlink_metadata(word_to_os(path)) {
+                    Ok(metadata) => !directories_only || metadata.is_dir(),
+                    Err(_) => false
+                })
+                .map(|path| Token::Word(if directories_only { format!("{}/", path) } else { path }))
+                .collect()
+}
+
+// Replaces aliases found in command position. An alias is not expanded again within its own expansion.
+pub fn expand_aliases(state: &ShellState, tokens: &mut Vec<Token>) {
+    let mut i = 0;
+    let mut command_position = true;
+    let mut expanded: Vec<String> = Vec::new();
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Word(word) if command_position => {
+                if let Some(value) = state.aliases.get(word).filter(|_| !expanded.contains(word)) {
+                    if let Ok(alias_tokens) = tokenize(value) {
+                        expanded.push(word.clone());
+                        tokens.splice(i..=i, alias_tokens);
+                        // Check the first word of the expansion for another alias
+                        continue;
+                    }
                 }
+                command_position = false;
+            },
+            Token::Pipe | Token::Background | Token::CommandSeparator | Token::Operator(_) => {
+                command_position = true;
+                expanded.clear();
+            },
+            _ => command_position = false
+        }
+        i += 1;
+    }
+}
+
+// A field being built while expanding a word
+struct Field {
+    text: String,
+    // The text with quoted parts escaped, used as the glob pattern
+    pattern: String,
+    quoted: bool,
+    glob: bool
+}
+
+impl Field {
+    fn new() -> Field {
+        Field { text: String::new(), pattern: String::new(), quoted: false, glob: false }
+    }
+
+    fn push_unquoted(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.pattern.push_str(text);
+        self.glob |= text.contains('*') || text.contains('?');
+    }
+
+    fn push_quoted(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.pattern.push_str(&Pattern::escape(text));
+        self.quoted = true;
+    }
+}
+
+// Unquoted expansions are split on IFS characters into separate fields
+fn split_into_fields(fields: &mut Vec<Field>, current: &mut Field, value: &str, ifs: &str) {
+    let mut pieces = value.split(|c: char| ifs.contains(c)).peekable();
+    while let Some(piece) = pieces.next() {
+        current.push_unquoted(piece);
+        if pieces.peek().is_some() {
+            let field = std::mem::replace(current, Field::new());
+            if !field.text.is_empty() || field.quoted {
+                fields.push(field);
             }
-            Err(_) => (),
         }
     }
+}
 
-    tokens
+// Expands the parameters of a word, then splits and globs the result into words
+fn expand_word(state: &mut ShellState, parts: &[WordPart]) -> Vec<Token> {
+    let ifs = state.variables.get("IFS").unwrap_or(" \t\n".to_string());
+    let mut fields: Vec<Field> = Vec::new();
+    let mut current = Field::new();
+    for (index, part) in parts.iter().enumerate() {
+        match part {
+            WordPart::Literal(text) => {
+                let tilde = if index == 0 && text.starts_with('~') && (parts.len() == 1 || text.contains('/')) { expand_tilde(state, text) } else { None };
+                match tilde {
+                    Some(expanded) => current.push_quoted(&expanded),
+                    None => current.push_unquoted(text)
+                }
+            },
+            WordPart::Quoted(text) => current.push_quoted(text),
+            WordPart::Parameter(expr, quoted) => {
+                let expansion = expand_parameter(state, expr).unwrap_or(Expansion { values: Vec::new(), separate: false });
+                if *quoted && expansion.separate {
+                    // "${array[@]}": one word per element, the first and last glued to the surrounding text
+                    let count = expansion.values.len();
+                    for (i, value) in expansion.values.iter().enumerate() {
+                        current.push_quoted(value);
+                        if i + 1 < count {
+                            fields.push(std::mem::replace(&mut current, Field::new()));
+                        }
+                    }
+                } else if *quoted {
+                    let value = expansion.join(state);
+                    current.push_quoted(&value);
+                } else {
+                    let count = expansion.values.len();
+                    for (i, value) in expansion.values.iter().enumerate() {
+                        split_into_fields(&mut fields, &mut current, value, &ifs);
+                        if i + 1 < count && (!current.text.is_empty() || current.quoted) {
+                            fields.push(std::mem::replace(&mut current, Field::new()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if !current.text.is_empty() || current.quoted {
+        fields.push(current);
+    }
+    let mut words = Vec::new();
+    for field in fields {
+        if field.glob {
+            let matches = expand_glob(&field.pattern);
+            if !matches.is_empty() {
+                words.extend(matches);
+                continue;
+            }
+        }
+        words.push(Token::Word(field.text));
+    }
+    words
 }
 
 pub fn expand_tokens(state: &mut ShellState, tokens: &mut Vec<Token>) {
@@ -41,14 +392,24 @@ pub fn expand_tokens(state: &mut ShellState, tokens: &mut Vec<Token>) {
         let token = &mut tokens[i];
 
         match token {
-            Token::Variable(var_name) => {
-                *token = Token::Word(expand_variable(state, var_name));
+            Token::CompoundWord(parts) => {
+                let parts = parts.clone();
+                let words = expand_word(state, &parts);
+                i += words.len();
+                tokens.splice(i - words.len()..=i - words.len(), words);
+            }
+            Token::ArrayAssignment(_, elements) => {
+                // Each element is expanded on its own and kept apart from the others
+                let mut expanded = elements.clone();
+                expand_tokens(state, &mut expanded);
+                expanded.retain(|element| matches!(element, Token::Word(_)));
+                *elements = expanded;
                 i += 1;
             }
             Token::Word(word) => {
-                if word.contains('~') {
-                    if let Ok(home) = env::var("HOME") {
-                        *token = Token::Word(word.replace("~", &home));
+                if word.starts_with('~') {
+                    if let Some(expanded) = expand_tilde(state, word) {
+                        *token = Token::Word(expanded);
                     }
                     i += 1;
                 } else if word.contains('*') || word.contains('?') {
@@ -68,4 +429,4 @@ pub fn expand_tokens(state: &mut ShellState, tokens: &mut Vec<Token>) {
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -6,10 +6,14 @@ use crate::core::error::StatusEnum;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum RedirectionType {
-    Input,  // >
-    Output, // <
-    Append, // >>
-    Heredoc // <<
+    Input,         // <
+    Output,        // >
+    Append,        // >>
+    Heredoc,       // <<
+    ErrOutput,     // 2>
+    ErrAppend,     // 2>>
+    ErrToOutput,   // 2>&1
+    OutputToError  // >&2
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -18,28 +22,81 @@ pub enum ConditionType {
     Or,  // ||
 }
 
+// A piece of a word. Quoted text is neither split into fields nor globbed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum WordPart {
+    Literal(String),
+    Quoted(String),
+    Parameter(String, bool)       // $name or ${...}, and whether it appeared within double quotes
+}
+
 #[derive(Clone)]
 pub enum Token {
     Word(String),
+    CompoundWord(Vec<WordPart>),  // word with quotes or parameters, resolved by expansion
     Pipe,                         // |
     Background,                   // &
     Negate,                       // !
     Subexpression(Vec<Token>),    // () or ``
     Redirection(RedirectionType), // >, <, >>, <<
-    Variable(String),             // $VAR or ${VAR}
+    ArrayAssignment(String, Vec<Token>), // name=(...) or name+=(...)
     Operator(ConditionType),      // && or ||
     CommandSeparator,             // ;
 }
 
 
+// Reserved words, only meaningful in command position
+pub const KEYWORDS: [&str; 21] = [
+    "!", "[[", "]]", "{", "}", "case", "do", "done", "elif", "else", "esac",
+    "fi", "for", "function", "if", "in", "select", "then", "time", "until", "while"
+];
+
+// Character range of the input a token or error comes from, end excluded
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub enum TokenizationError {
-    UnmatchedCharacter = 127
+pub enum TokenizationErrorKind {
+    UnmatchedCharacter = 127,  // the input is incomplete and may continue on the next line
+    InvalidRedirection = 230
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TokenizationError {
+    pub kind: TokenizationErrorKind,
+    pub span: Span,
+    pub message: String
+}
+
+impl TokenizationError {
+    fn new(kind: TokenizationErrorKind, span: Span, message: String) -> TokenizationError {
+        TokenizationError { kind, span, message }
+    }
+
+    // `(` and `${` with nothing to close them
+    pub fn unmatched(span: Span, opening: &str) -> TokenizationError {
+        TokenizationError::new(TokenizationErrorKind::UnmatchedCharacter, span, format!("unmatched `{}`", opening))
+    }
+
+    // Errors of a nested tokenization are relative to its content
+    fn shifted(mut self, offset: usize) -> TokenizationError {
+        self.span = Span::new(self.span.start + offset, self.span.end + offset);
+        self
+    }
 }
 
 impl StatusEnum for TokenizationError {
     fn status(&self) -> u16 {
-        *self as u16
+        self.kind as u16
     }
 }
 
@@ -76,48 +133,232 @@ pub fn parse_identifier(iter: &mut Peekable<std::str::Chars>, index: &mut i32) -
 }
 
 const SEPARATOR_CHARS: &str = "\"';|$<>";
-fn parse_until_separator(iter: &mut Peekable<std::str::Chars>, index: &mut i32) -> String {
-    let mut word = String::new();
+fn parse_until_separator(iter: &mut Peekable<std::str::Chars>, index: &mut i32, word: &mut String, parts: &mut Vec<WordPart>) {
     while let Some(&next) = iter.peek() {
-        if let Some(sequence) = handle_escaping(iter, index, next) {
-            word.push_str(&sequence);
+        if next == '\\' {
+            // Unquoted backslash escapes the next character
+            iter.next();
+            *index += 1;
+            if let Some(escaped) = iter.next() {
+                parts.push(WordPart::Literal(std::mem::take(word)));
+                parts.push(WordPart::Quoted(escaped.to_string()));
+                *index += 1;
+            }
+            continue;
         } else if next.is_whitespace() || SEPARATOR_CHARS.contains(next) {
             break;
+        } else if next == '(' && parts.is_empty() && is_assignment_prefix(word) {
+            // Start of an array assignment: `name=(`
+            break;
         }
         word.push(iter.next().unwrap());
         *index += 1;
     }
-    return word;
+}
+
+// `name=`, `name+=`, `name[sub]=` or `name[sub]+=`
+fn is_assignment_prefix(word: &str) -> bool {
+    let Some(target) = word.strip_suffix('=') else {
+        return false;
+    };
+    let target = target.strip_suffix('+').unwrap_or(target);
+    let name = match target.find('[') {
+        Some(pos) if target.ends_with(']') => &target[..pos],
+        Some(_) => return false,
+        None => target
+    };
+    is_name(name)
+}
+
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// What follows a `$`: a name, a special parameter or the content of `${...}`.
+// Returns None when the `$` is literal.
+fn parse_parameter(iter: &mut Peekable<std::str::Chars>, index: &mut i32) -> Result<Option<String>, TokenizationError> {
+    match iter.peek() {
+        Some('{') => {
+            let start = *index - 1;
+            iter.next();
+            *index += 1;
+            let mut depth = 1;
+            let mut content = String::new();
+            for next in iter.by_ref() {
+                *index += 1;
+                match next {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(Some(content));
+                        }
+                    },
+                    _ => ()
+                }
+                content.push(next);
+            }
+            Err(TokenizationError::unmatched(Span::new(start as usize, *index as usize), "${"))
+        },
+        Some(&c) if "?#@*$!-0123456789".contains(c) => {
+            iter.next();
+            *index += 1;
+            Ok(Some(c.to_string()))
+        },
+        Some(&c) if c.is_alphabetic() || c == '_' || is_emoji(c) => {
+            let mut name = String::new();
+            while let Some(&next) = iter.peek() {
+                if !(next.is_alphanumeric() || next == '_' || is_emoji(next)) {
+                    break;
+                }
+                name.push(next);
+                iter.next();
+                *index += 1;
+            }
+            Ok(Some(name))
+        },
+        _ => Ok(None)
+    }
+}
+
+// Within double quotes, parameters are still expanded but the text is not split or globbed
+fn parse_double_quoted(iter: &mut Peekable<std::str::Chars>, index: &mut i32) -> Result<Vec<WordPart>, TokenizationError> {
+    let start = *index - 1;
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    while let Some(next) = iter.next() {
+        *index += 1;
+        match next {
+            '"' => {
+                if !text.is_empty() || parts.is_empty() {
+                    parts.push(WordPart::Quoted(text));
+                }
+                return Ok(parts);
+            },
+            '\\' => {
+                // Backslash only escapes $ ` " \ and is kept otherwise
+                match iter.next() {
+                    Some(escaped) => {
+                        *index += 1;
+                        if !"$`\"\\".contains(escaped) {
+                            text.push('\\');
+                        }
+                        text.push(escaped);
+                    },
+                    None => text.push('\\')
+                }
+            },
+            '$' => match parse_parameter(iter, index)? {
+                Some(parameter) => {
+                    if !text.is_empty() {
+                        parts.push(WordPart::Quoted(std::mem::take(&mut text)));
+                    }
+                    parts.push(WordPart::Parameter(parameter, true));
+                },
+                None => text.push('$')
+            },
+            c => text.push(c)
+        }
+    }
+    Err(TokenizationError::unmatched(Span::new(start as usize, *index as usize), "\""))
 }
 
 fn parse_until_next(iter: &mut Peekable<std::str::Chars>, index: &mut i32, closing_char: char) -> Result<String, TokenizationError> {
+    let start = *index - 1;
     let mut closed = false;
     let mut content = String::new();
     while let Some(&next) = iter.peek() {
-        if let Some(sequence) = handle_escaping(iter, index, next) {
-            content.push_str(&sequence);
-        } if next == closing_char {
+        if next == '\\' && closing_char != '\'' {
+            iter.next();
+            *index += 1;
+            if let Some(escaped) = iter.next() {
+                *index += 1;
+                // Within double quotes, backslash only escapes $ ` " \ and is kept otherwise.
+                // Subexpressions keep their escapes for the nested tokenization.
+                if closing_char != '"' || !"$`\"\\".contains(escaped) {
+                    content.push('\\');
+                }
+                content.push(escaped);
+            } else {
+                content.push('\\');
+            }
+            continue;
+        } else if next == closing_char {
             closed = true;
             iter.next();
             *index += 1;
             break;
         }
         content.push(iter.next().unwrap());
+        *index += 1;
     }
     if !closed {
-        return Err(TokenizationError::UnmatchedCharacter);
+        let opening = if closing_char == ')' { '(' } else { closing_char };
+        return Err(TokenizationError::unmatched(Span::new(start as usize, *index as usize), &opening.to_string()));
     }
     return Ok(content);
 }
 
+// Quoted and unquoted parts not separated by whitespace form a single word
+fn push_part(tokens: &mut Vec<Token>, part: WordPart, glued: bool) {
+    if glued {
+        match tokens.last_mut() {
+            Some(Token::Word(word)) => {
+                if let WordPart::Literal(text) = &part {
+                    word.push_str(text);
+                } else {
+                    let first = WordPart::Literal(std::mem::take(word));
+                    *tokens.last_mut().unwrap() = Token::CompoundWord(vec![first, part]);
+                }
+                return;
+            },
+            Some(Token::CompoundWord(parts)) => {
+                parts.push(part);
+                return;
+            },
+            _ => ()
+        }
+    }
+    match part {
+        WordPart::Literal(text) => tokens.push(Token::Word(text)),
+        part => tokens.push(Token::CompoundWord(vec![part]))
+    }
+}
+
+// Only `>&2` and `2>&1` are supported, along with their no-op counterparts
+fn invalid_duplication(start: usize, index: i32, has_next: bool, operator: &str) -> TokenizationError {
+    let span = Span::new(start, index as usize + has_next as usize);
+    TokenizationError::new(TokenizationErrorKind::InvalidRedirection, span, format!("expected `1` or `2` after `{}`", operator))
+}
+
 pub fn tokenize(expr: &String) -> Result<Vec<Token>, TokenizationError> {
+    tokenize_with_spans(expr).map(|(tokens, _)| tokens)
+}
+
+// Tokenizes along with the span each token comes from, for error reporting
+pub fn tokenize_with_spans(expr: &str) -> Result<(Vec<Token>, Vec<Span>), TokenizationError> {
     let mut tokens = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
     let mut chars = expr.chars().peekable();
     let mut index = 0;
+    let mut in_word = false;
     while let Some(c) = chars.next() {
+        let start = index as usize;
+        let count = tokens.len();
         index += 1;
+        let glued = in_word;
+        in_word = false;
         match c {
-            '#' => break,
+            // A comment lasts until the end of the line
+            '#' if !glued => {
+                while chars.peek().is_some_and(|next| *next != '\n') {
+                    chars.next();
+                    index += 1;
+                }
+                continue;
+            },
             '|' => {
                 if chars.peek() == Some(&'&') {
                     chars.next();
@@ -127,7 +368,8 @@ pub fn tokenize(expr: &String) -> Result<Vec<Token>, TokenizationError> {
                     tokens.push(Token::Pipe);
                 }
             },
-            '!' => tokens.push(Token::Negate),
+            // Within or at the start of a word, `!` is an ordinary character
+            '!' if !glued && chars.peek().is_none_or(|next| next.is_whitespace()) => tokens.push(Token::Negate),
             '&' => {
                 if chars.peek() == Some(&'&') {
                     chars.next();
@@ -142,6 +384,20 @@ pub fn tokenize(expr: &String) -> Result<Vec<Token>, TokenizationError> {
                     chars.next();
                     index += 1;
                     tokens.push(Token::Redirection(RedirectionType::Append));
+                } else if chars.peek() == Some(&'&') {
+                    chars.next();
+                    index += 1;
+                    if chars.peek() == Some(&'2') {
+                        chars.next();
+                        index += 1;
+                        tokens.push(Token::Redirection(RedirectionType::OutputToError));
+                    } else if chars.peek() == Some(&'1') {
+                        // >&1 is a no-op
+                        chars.next();
+                        index += 1;
+                    } else {
+                        return Err(invalid_duplication(start, index, chars.peek().is_some(), ">&"));
+                    }
                 } else {
                     tokens.push(Token::Redirection(RedirectionType::Output));
                 }
@@ -150,43 +406,108 @@ pub fn tokenize(expr: &String) -> Result<Vec<Token>, TokenizationError> {
                 if chars.peek() == Some(&'<') {
                     chars.next();
                     index += 1;
+                    tokens.push(Token::Redirection(RedirectionType::Heredoc));
+                } else {
                     tokens.push(Token::Redirection(RedirectionType::Input));
+                }
+            },
+            '2' if chars.peek() == Some(&'>') => {
+                chars.next();
+                index += 1;
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                    index += 1;
+                    tokens.push(Token::Redirection(RedirectionType::ErrAppend));
+                } else if chars.peek() == Some(&'&') {
+                    chars.next();
+                    index += 1;
+                    if chars.peek() == Some(&'1') {
+                        chars.next();
+                        index += 1;
+                        tokens.push(Token::Redirection(RedirectionType::ErrToOutput));
+                    } else if chars.peek() == Some(&'2') {
+                        // 2>&2 is a no-op
+                        chars.next();
+                        index += 1;
+                    } else {
+                        return Err(invalid_duplication(start, index, chars.peek().is_some(), "2>&"));
+                    }
                 } else {
-                    tokens.push(Token::Redirection(RedirectionType::Heredoc));
+                    tokens.push(Token::Redirection(RedirectionType::ErrOutput));
                 }
             },
             '$' => {
-                tokens.push(Token::Variable(parse_identifier(&mut chars, &mut index)));
+                match parse_parameter(&mut chars, &mut index)? {
+                    Some(parameter) => push_part(&mut tokens, WordPart::Parameter(parameter, false), glued),
+                    None => push_part(&mut tokens, WordPart::Literal("$".to_string()), glued)
+                }
+                in_word = true;
             },
-            '\'' | '"' => match parse_until_next(&mut chars, &mut index, c) {
+            '\'' => match parse_until_next(&mut chars, &mut index, c) {
                 Ok(content) => {
-                    if let Some(last) = tokens.last_mut() {
-                        if let Token::Word(ref mut last_word) = last {
-                            last_word.push_str(&content);
-                        }
-                    } else {
-                        tokens.push(Token::Word(content))
-                    }
+                    push_part(&mut tokens, WordPart::Quoted(content), glued);
+                    in_word = true;
                 },
                 Err(error) => return Err(error)
             },
+            '"' => {
+                for (i, part) in parse_double_quoted(&mut chars, &mut index)?.into_iter().enumerate() {
+                    push_part(&mut tokens, part, glued || i > 0);
+                }
+                in_word = true;
+            },
             '`' | '(' => match parse_until_next(&mut chars, &mut index, if c == '(' { ')' } else { c }) {
                 Ok(content) => {
                     match tokenize(&content) {
                         Ok(subtokens) => tokens.push(Token::Subexpression(subtokens)),
-                        Err(error) => return Err(error)
+                        Err(error) => return Err(error.shifted(start + 1))
                     }
                 },
                 Err(error) => return Err(error)
             },
             ';' => tokens.push(Token::CommandSeparator),
+            // A newline ends the command, unless the line ends with `|`, `&&` or `||` and it goes on
+            '\n' if tokens.last().is_some_and(|last| !matches!(last, Token::CommandSeparator | Token::Background | Token::Pipe | Token::Operator(_) | Token::Negate)) => {
+                tokens.push(Token::CommandSeparator);
+            },
             c if c.is_whitespace() => continue,
             c => {
-                let mut word = c.to_string();
-                word.push_str(&parse_until_separator(&mut chars, &mut index));
-                tokens.push(Token::Word(word));
+                let mut word = String::new();
+                let mut parts = Vec::new();
+                if c == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        index += 1;
+                        parts.push(WordPart::Quoted(escaped.to_string()));
+                    }
+                } else {
+                    word.push(c);
+                }
+                parse_until_separator(&mut chars, &mut index, &mut word, &mut parts);
+                if !glued && parts.is_empty() && chars.peek() == Some(&'(') && is_assignment_prefix(&word) {
+                    chars.next();
+                    index += 1;
+                    let content_start = index as usize;
+                    let content = parse_until_next(&mut chars, &mut index, ')')?;
+                    let elements = tokenize(&content).map_err(|error| error.shifted(content_start))?;
+                    tokens.push(Token::ArrayAssignment(word, elements));
+                } else {
+                    parts.push(WordPart::Literal(word));
+                    for (i, part) in parts.into_iter().filter(|part| *part != WordPart::Literal(String::new())).enumerate() {
+                        push_part(&mut tokens, part, glued || i > 0);
+                    }
+                    in_word = true;
+                }
             }
         }
+        // A part glued to the previous word extends its span
+        if tokens.len() == count && glued && in_word {
+            if let Some(span) = spans.last_mut() {
+                span.end = index as usize;
+            }
+        }
+        while spans.len() < tokens.len() {
+            spans.push(Span::new(start, index as usize));
+        }
     }
-    Ok(tokens)
+    Ok((tokens, spans))
 }
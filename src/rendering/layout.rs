@@ -0,0 +1,143 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// Columns taken by text on the terminal. Each grapheme cluster, such as a letter with combining
+// accents, an emoji sequence or a flag, is drawn as one character, two columns wide when East
+// Asian wide or an emoji.
+pub fn text_width(text: &str) -> usize {
+  text.graphemes(true).map(|grapheme| grapheme.width()).sum()
+}
+
+// Where the input goes on screen. It starts after the prompt, at `ps1col`, and each of its
+// lines does too. Lines longer than the terminal wrap to the first column of the next row.
+pub struct Layout {
+  pub ps1col: usize,
+  pub width: usize
+}
+
+// Characters `start..end` of the input shown on a row, from column `col`
+pub struct LayoutRow {
+  pub start: usize,
+  pub end: usize,
+  pub col: usize
+}
+
+impl Layout {
+  pub fn new(ps1col: u16, width: u16) -> Layout {
+    Layout { ps1col: ps1col as usize, width: (width as usize).max(1) }
+  }
+
+  // Rows taken by the input, with the characters of each
+  pub fn rows(&self, input: &str) -> Vec<LayoutRow> {
+    let mut rows = vec![LayoutRow { start: 0, end: 0, col: self.ps1col }];
+    let mut col = self.ps1col;
+    let mut index = 0;
+    for grapheme in input.graphemes(true) {
+      let length = grapheme.chars().count();
+      if grapheme == "\n" || grapheme == "\r\n" {
+        index += length;
+        rows.push(LayoutRow { start: index, end: index, col: self.ps1col });
+        col = self.ps1col;
+        continue;
+      }
+      let width = grapheme.width();
+      // A character that does not fit goes to the next row, like the terminal does
+      if col + width > self.width {
+        rows.push(LayoutRow { start: index, end: index, col: 0 });
+        col = 0;
+      }
+      col += width;
+      index += length;
+      if let Some(row) = rows.last_mut() {
+        row.end = index;
+      }
+    }
+    rows
+  }
+
+  // Column and row, counted from the first row of the input, of the byte `position` of the input
+  pub fn position(&self, input: &str, position: usize) -> (usize, usize) {
+    let mut col = self.ps1col;
+    let mut row = 0;
+    for grapheme in input[..position].graphemes(true) {
+      if grapheme == "\n" || grapheme == "\r\n" {
+        row += 1;
+        col = self.ps1col;
+        continue;
+      }
+      let width = grapheme.width();
+      if col + width > self.width {
+        row += 1;
+        col = 0;
+      }
+      col += width;
+    }
+    // After a full row, the cursor is at the start of the next one
+    if col >= self.width {
+      row += 1;
+      col = 0;
+    }
+    (col, row)
+  }
+
+  // Rows the input and the cursor at its end take
+  pub fn height(&self, input: &str) -> usize {
+    self.position(input, input.len()).1 + 1
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn text_width_counts_clusters_by_columns() {
+    assert_eq!(text_width("abc"), 3);
+    assert_eq!(text_width("\u{6f22}\u{5b57}"), 4);
+    assert_eq!(text_width("e\u{301}"), 1);
+    assert_eq!(text_width("\u{1f469}\u{200d}\u{1f4bb}"), 2);
+    assert_eq!(text_width("\u{1f1eb}\u{1f1f7}"), 2);
+    assert_eq!(text_width("a\u{6f22}e\u{301}\u{1f1eb}\u{1f1f7}"), 6);
+  }
+
+  #[test]
+  fn position_counts_widths_after_the_prompt() {
+    let layout = Layout::new(2, 20);
+    let input = "e\u{301}\u{6f22}\u{1f469}\u{200d}\u{1f4bb}x";
+    assert_eq!(layout.position(input, 0), (2, 0));
+    assert_eq!(layout.position(input, 3), (3, 0));
+    assert_eq!(layout.position(input, 6), (5, 0));
+    assert_eq!(layout.position(input, 17), (7, 0));
+    assert_eq!(layout.position(input, input.len()), (8, 0));
+  }
+
+  #[test]
+  fn position_wraps_a_wide_character_at_the_edge() {
+    // Seven columns after a prompt of two leave one column on the row, too narrow for the ideograph
+    let layout = Layout::new(2, 10);
+    let input = "abcdefg\u{6f22}";
+    assert_eq!(layout.position(input, 7), (9, 0));
+    assert_eq!(layout.position(input, input.len()), (2, 1));
+    assert_eq!(layout.height(input), 2);
+    let rows = layout.rows(input);
+    assert_eq!(rows.len(), 2);
+    assert_eq!((rows[0].start, rows[0].end, rows[0].col), (0, 7, 2));
+    assert_eq!((rows[1].start, rows[1].end, rows[1].col), (7, 8, 0));
+  }
+
+  #[test]
+  fn position_after_a_full_row_is_on_the_next() {
+    let layout = Layout::new(2, 10);
+    let input = "abcdef\u{1f1eb}\u{1f1f7}";
+    assert_eq!(layout.position(input, input.len()), (0, 1));
+    assert_eq!(layout.height(input), 2);
+  }
+
+  #[test]
+  fn position_starts_new_lines_after_the_prompt() {
+    let layout = Layout::new(4, 10);
+    let input = "a\u{6f22}\ne\u{301}";
+    assert_eq!(layout.position(input, input.len()), (5, 1));
+    assert_eq!(layout.rows(input).len(), 2);
+  }
+}
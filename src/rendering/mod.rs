@@ -1,2 +1,4 @@
 pub mod autocomplete;
-pub mod prompt;
\ No newline at end of file
+pub mod layout;
+pub mod prompt;
+pub mod search;
\ No newline at end of file
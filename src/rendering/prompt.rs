@@ -1,44 +1,139 @@
-use crossterm::{cursor, style::Print, terminal::{self, Clear, ClearType}, QueueableCommand};
+use crossterm::{cursor, style::{Attribute, Color, Print, PrintStyledContent, ResetColor, SetAttribute, SetForegroundColor}, terminal::{self, Clear, ClearType}, QueueableCommand};
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::{core::core::ShellState, features::{prompt::Prompt, promptscript::eval_ps}};
+use crate::{core::{core::ShellState, fsio::flush_output}, features::{highlight::{highlight, role_style, Role}, prompt::Prompt, promptscript::eval_ps}, rendering::layout::{text_width, Layout}};
 
 
 pub fn clear_prompt_input(state: &mut ShellState, prompt: &Prompt) {
   let (ps1col, ps1row) = state.ps1pos;
-  for line in 0..prompt.get_input_rows() {
-      state.stdout.queue(cursor::MoveTo(ps1col, ps1row + line as u16)).unwrap()
+  let (_, termrows) = terminal::size().unwrap_or(state.termsize);
+  // Rows below the screen are not there yet, moving to them would stop on the last one
+  let rows = Layout::new(ps1col, state.termsize.0).height(prompt.get_input()).min(termrows.saturating_sub(ps1row) as usize);
+  for row in 0..rows {
+      let col = if row == 0 { ps1col } else { 0 };
+      state.stdout.queue(cursor::MoveTo(col, ps1row + row as u16)).unwrap()
                   .queue(Clear(ClearType::UntilNewLine)).unwrap();
   }
   state.stdout.queue(cursor::MoveTo(ps1col, ps1row)).unwrap();
 }
 
-pub fn print_prompt_input(state: &mut ShellState, input: &String) {
-  let (ps1col, ps1row) = &mut state.ps1pos;
-  let (_, termrows) = terminal::size().unwrap();
-  let input_lines: Vec<&str> = input.split('\n').collect();
-  for (row, input_line) in input_lines.iter().enumerate() {
-      state.stdout.queue(cursor::MoveTo(*ps1col, *ps1row)).unwrap();
-      if row < input_lines.len() - 1 {
-          state.stdout.queue(Print(format!("{}\n", input_line))).unwrap();
-          // handle newline scrolling whole term, therefore moving ps1
-          if *ps1row + (row as u16) >= termrows - 1 {
-              *ps1row -= 1;
-          }
-      } else {
-          state.stdout.queue(Print(format!("{}", input_line))).unwrap();
+// Scrolls the terminal up until `row` rows below ps1 are on screen, moving ps1 with it
+fn scroll_to_row(state: &mut ShellState, row: usize) {
+  let (_, termrows) = terminal::size().unwrap_or(state.termsize);
+  let bottom = state.ps1pos.1 as usize + row;
+  if bottom >= termrows as usize {
+      let lines = (bottom + 1 - termrows as usize).min(state.ps1pos.1 as usize) as u16;
+      if lines > 0 {
+          state.stdout.queue(terminal::ScrollUp(lines)).unwrap();
+          state.ps1pos.1 -= lines;
+      }
+  }
+}
+
+// Prints a line of input in runs of characters with the same role
+fn print_highlighted(state: &mut ShellState, line: &str, roles: &[Role]) {
+  let chars: Vec<char> = line.chars().collect();
+  let mut start = 0;
+  while start < chars.len() {
+      let role = roles.get(start).copied().unwrap_or(Role::Plain);
+      let mut end = start + 1;
+      while end < chars.len() && roles.get(end).copied().unwrap_or(Role::Plain) == role {
+          end += 1;
       }
+      let text: String = chars[start..end].iter().collect();
+      let style = role_style(&state.config.theme, role);
+      state.stdout.queue(PrintStyledContent(style.apply(text))).unwrap();
+      start = end;
+  }
+}
+
+// Prints the input row by row as the terminal would wrap it, so that every row starts
+// where the layout expects it
+pub fn print_prompt_input(state: &mut ShellState, input: &str) {
+  let roles = highlight(state, input);
+  let chars: Vec<char> = input.chars().collect();
+  let rows = Layout::new(state.ps1pos.0, state.termsize.0).rows(input);
+  for (index, row) in rows.iter().enumerate() {
+      scroll_to_row(state, index);
+      state.stdout.queue(cursor::MoveTo(row.col as u16, state.ps1pos.1 + index as u16)).unwrap();
+      let text: String = chars[row.start..row.end].iter().collect();
+      print_highlighted(state, &text, &roles[row.start..row.end]);
   }
 }
 
+// Moves the terminal cursor to the byte `position` of the input
+fn move_to_input(state: &mut ShellState, input: &str, position: usize) {
+  let (col, row) = Layout::new(state.ps1pos.0, state.termsize.0).position(input, position);
+  scroll_to_row(state, row);
+  state.stdout.queue(cursor::MoveTo(col as u16, state.ps1pos.1 + row as u16)).unwrap();
+}
+
 pub fn align_cursor_with_prompt(state: &mut ShellState, prompt: &Prompt) {
-  let (ps1col, ps1row) = state.ps1pos;
-  let (curcol, currow) = prompt.get_cursor_offset();
-  state.stdout.queue(cursor::MoveTo(ps1col + curcol as u16, ps1row + currow as u16)).unwrap();
+  move_to_input(state, prompt.get_input(), prompt.get_cursor());
+}
+
+// Greyed-out suggestion after the end of the input, cut at the edge of the terminal.
+// Drawing it also clears the previous one, and the cursor is put back where it was.
+pub fn render_suggestion(state: &mut ShellState, prompt: &Prompt) {
+  let input = prompt.get_input();
+  let (col, _) = Layout::new(state.ps1pos.0, state.termsize.0).position(input, input.len());
+  move_to_input(state, input, input.len());
+  state.stdout.queue(Clear(ClearType::UntilNewLine)).unwrap();
+  if let Some(suggestion) = prompt.get_suggestion() {
+      let width = (state.termsize.0 as usize).saturating_sub(col + 1);
+      let mut visible = String::new();
+      for grapheme in suggestion.lines().next().unwrap_or("").graphemes(true) {
+          if text_width(&visible) + text_width(grapheme) > width {
+              break;
+          }
+          visible.push_str(grapheme);
+      }
+      state.stdout.queue(SetForegroundColor(Color::DarkGrey)).unwrap()
+                  .queue(Print(visible)).unwrap()
+                  .queue(ResetColor).unwrap();
+  }
+  align_cursor_with_prompt(state, prompt);
 }
 
 pub fn print_prompt(state: &mut ShellState, prompt: &Prompt) {
   let ps1out = eval_ps(state, &prompt.ps1);
+  state.stderr.write_all(&ps1out.stderr).unwrap();
   if let Ok(ps1) = String::from_utf8(ps1out.stdout) {
       state.stdout.queue(Print(ps1)).unwrap();
   }
 }
+
+// Draws the prompt again in place, for prompts that change while a line is edited
+pub fn repaint_prompt(state: &mut ShellState, prompt: &Prompt) {
+  let ps1out = eval_ps(state, &prompt.ps1);
+  let ps1 = String::from_utf8_lossy(&ps1out.stdout).into_owned();
+  let origin = state.ps1pos.1.saturating_sub(ps1.matches('\n').count() as u16);
+  state.stdout.queue(cursor::MoveTo(0, origin)).unwrap()
+              .queue(Clear(ClearType::FromCursorDown)).unwrap()
+              .queue(Print(ps1)).unwrap();
+  flush_output(state);
+  state.ps1pos = cursor::position().unwrap_or(state.ps1pos);
+  print_prompt_input(state, prompt.get_input());
+  align_cursor_with_prompt(state, prompt);
+}
+
+// Shows the bytes `start..end` of the input in reverse video, row by row
+pub fn highlight_selection(state: &mut ShellState, prompt: &Prompt, start: usize, end: usize) {
+  let input = prompt.get_input();
+  let chars: Vec<char> = input.chars().collect();
+  let (start, end) = (input[..start].chars().count(), input[..end].chars().count());
+  let rows = Layout::new(state.ps1pos.0, state.termsize.0).rows(input);
+  for (index, row) in rows.iter().enumerate() {
+      let (from, to) = (start.max(row.start), end.min(row.end));
+      if from >= to {
+          continue;
+      }
+      let col = row.col + text_width(&chars[row.start..from].iter().collect::<String>());
+      let text: String = chars[from..to].iter().collect();
+      state.stdout.queue(cursor::MoveTo(col as u16, state.ps1pos.1 + index as u16)).unwrap()
+                  .queue(SetAttribute(Attribute::Reverse)).unwrap()
+                  .queue(Print(text)).unwrap()
+                  .queue(SetAttribute(Attribute::Reset)).unwrap();
+  }
+  align_cursor_with_prompt(state, prompt);
+}
@@ -0,0 +1,28 @@
+use crossterm::{cursor, style::Print, terminal::{Clear, ClearType}, QueueableCommand};
+
+use crate::{core::core::ShellState, features::prompt::Prompt, rendering::{layout::text_width, prompt::{align_cursor_with_prompt, highlight_selection, print_prompt_input}}};
+
+// `(reverse-i-search)`query': entry` in place of the last line of the prompt, with the match highlighted
+pub fn render_search(state: &mut ShellState, prompt: &Prompt) {
+  let Some(search) = state.search.as_ref() else {
+    return;
+  };
+  let label = format!("({}{}-i-search)`{}': ",
+                      if search.failed { "failed " } else { "" },
+                      if search.reverse { "reverse" } else { "forward" },
+                      search.query);
+  let highlight = match search.found {
+    Some((_, position)) if !search.query.is_empty() => Some((position, position + search.query.len())),
+    _ => None
+  };
+  let row = state.ps1pos.1;
+  state.stdout.queue(cursor::MoveTo(0, row)).unwrap()
+              .queue(Clear(ClearType::FromCursorDown)).unwrap()
+              .queue(Print(&label)).unwrap();
+  state.ps1pos = (text_width(&label) as u16, row);
+  print_prompt_input(state, prompt.get_input());
+  match highlight {
+    Some((start, end)) => highlight_selection(state, prompt, start, end),
+    None => align_cursor_with_prompt(state, prompt)
+  }
+}